@@ -0,0 +1,278 @@
+//! Bitcoin amounts: [`Sats`] and [`MilliSats`].
+//!
+//! Both are thin wrappers around `i64` that serialize as plain integers, so
+//! they match the API's wire format. Arithmetic is checked, and both parse
+//! from strings with a unit:
+//!
+//! ```
+//! use lnbot::{MilliSats, Sats};
+//!
+//! assert_eq!("1.5k sats".parse::<Sats>().unwrap(), Sats::new(1_500));
+//! assert_eq!("0.001 BTC".parse::<Sats>().unwrap(), Sats::new(100_000));
+//! assert_eq!("2500".parse::<Sats>().unwrap(), Sats::new(2_500));
+//! assert_eq!("1.5 sats".parse::<MilliSats>().unwrap(), MilliSats::new(1_500));
+//! assert_eq!(Sats::new(1_500).to_string(), "1500 sats");
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+const MSATS_PER_SAT: i128 = 1_000;
+const MSATS_PER_BTC: i128 = 100_000_000_000;
+
+/// Error returned when an amount string cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ParseAmountError {
+    #[error("invalid amount {0:?}")]
+    InvalidNumber(String),
+
+    #[error("unknown unit {0:?}")]
+    UnknownUnit(String),
+
+    #[error("amount is more precise than the unit allows")]
+    TooPrecise,
+
+    #[error("amount is out of range")]
+    OutOfRange,
+}
+
+macro_rules! amount_type {
+    ($name:ident, $scale:expr) => {
+        impl $name {
+            /// An amount of zero.
+            pub const ZERO: Self = Self(0);
+            /// The largest representable amount.
+            pub const MAX: Self = Self(i64::MAX);
+
+            /// Wraps a plain integer amount.
+            pub const fn new(value: i64) -> Self {
+                Self(value)
+            }
+
+            /// The amount as a plain integer.
+            pub const fn get(self) -> i64 {
+                self.0
+            }
+
+            /// Returns `true` if the amount is greater than zero.
+            pub const fn is_positive(self) -> bool {
+                self.0 > 0
+            }
+
+            /// Returns `true` if the amount is less than zero.
+            pub const fn is_negative(self) -> bool {
+                self.0 < 0
+            }
+
+            /// Adds two amounts, or returns `None` on overflow.
+            pub fn checked_add(self, rhs: Self) -> Option<Self> {
+                self.0.checked_add(rhs.0).map(Self)
+            }
+
+            /// Subtracts `rhs`, or returns `None` on overflow.
+            pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+                self.0.checked_sub(rhs.0).map(Self)
+            }
+
+            /// Multiplies by `rhs`, or returns `None` on overflow.
+            pub fn checked_mul(self, rhs: i64) -> Option<Self> {
+                self.0.checked_mul(rhs).map(Self)
+            }
+
+            /// Divides by `rhs`, rounding toward zero, or returns `None` if
+            /// `rhs` is zero or the result overflows.
+            pub fn checked_div(self, rhs: i64) -> Option<Self> {
+                self.0.checked_div(rhs).map(Self)
+            }
+
+            /// Adds two amounts, clamping at the numeric bounds.
+            pub fn saturating_add(self, rhs: Self) -> Self {
+                Self(self.0.saturating_add(rhs.0))
+            }
+
+            /// Subtracts `rhs`, clamping at the numeric bounds.
+            pub fn saturating_sub(self, rhs: Self) -> Self {
+                Self(self.0.saturating_sub(rhs.0))
+            }
+        }
+
+        impl From<i64> for $name {
+            fn from(value: i64) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<i32> for $name {
+            fn from(value: i32) -> Self {
+                Self(value.into())
+            }
+        }
+
+        impl From<u32> for $name {
+            fn from(value: u32) -> Self {
+                Self(value.into())
+            }
+        }
+
+        impl TryFrom<u64> for $name {
+            type Error = std::num::TryFromIntError;
+
+            fn try_from(value: u64) -> Result<Self, Self::Error> {
+                i64::try_from(value).map(Self)
+            }
+        }
+
+        impl From<$name> for i64 {
+            fn from(amount: $name) -> Self {
+                amount.0
+            }
+        }
+
+        impl PartialEq<i64> for $name {
+            fn eq(&self, other: &i64) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl PartialEq<$name> for i64 {
+            fn eq(&self, other: &$name) -> bool {
+                *self == other.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseAmountError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                parse(s, $scale).map(Self)
+            }
+        }
+    };
+}
+
+/// An amount in satoshis.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Sats(i64);
+
+amount_type!(Sats, MSATS_PER_SAT);
+
+impl Sats {
+    /// Converts to millisatoshis, or `None` on overflow.
+    pub fn to_msats(self) -> Option<MilliSats> {
+        self.0.checked_mul(1_000).map(MilliSats)
+    }
+}
+
+impl fmt::Display for Sats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = if self.0.abs() == 1 { "sat" } else { "sats" };
+        write!(f, "{} {}", self.0, unit)
+    }
+}
+
+/// An amount in millisatoshis.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct MilliSats(i64);
+
+amount_type!(MilliSats, 1);
+
+impl MilliSats {
+    /// Converts to whole satoshis, rounding down.
+    pub fn to_sats_floor(self) -> Sats {
+        Sats(self.0.div_euclid(1_000))
+    }
+
+    /// Converts to whole satoshis, rounding up.
+    pub fn to_sats_ceil(self) -> Sats {
+        let sats = self.0.div_euclid(1_000);
+        Sats(if self.0.rem_euclid(1_000) == 0 {
+            sats
+        } else {
+            sats + 1
+        })
+    }
+}
+
+impl fmt::Display for MilliSats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = if self.0.abs() == 1 { "msat" } else { "msats" };
+        write!(f, "{} {}", self.0, unit)
+    }
+}
+
+/// Parses an amount in units of `scale` millisatoshis.
+fn parse(s: &str, scale: i128) -> Result<i64, ParseAmountError> {
+    let msats = parse_msats(s, scale)?;
+    if msats % scale != 0 {
+        return Err(ParseAmountError::TooPrecise);
+    }
+    i64::try_from(msats / scale).map_err(|_| ParseAmountError::OutOfRange)
+}
+
+/// Parses `"<number>[k|M] [unit]"` into millisatoshis. Without a unit, the
+/// number is in units of `default_scale` millisatoshis.
+fn parse_msats(s: &str, default_scale: i128) -> Result<i128, ParseAmountError> {
+    let invalid = || ParseAmountError::InvalidNumber(s.to_string());
+    let s = s.trim();
+    let end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(s.len());
+    let (number, rest) = s.split_at(end);
+
+    let (negative, number) = match number.as_bytes().first() {
+        Some(b'-') => (true, &number[1..]),
+        Some(b'+') => (false, &number[1..]),
+        _ => (false, number),
+    };
+    let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+    let digits = [int, frac].concat();
+    if digits.is_empty() || digits.len() > 30 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let mantissa: i128 = digits.parse().map_err(|_| invalid())?;
+
+    let rest = rest.trim();
+    let (multiplier, scale) = match unit_scale(rest, default_scale) {
+        Some(scale) => (1, scale),
+        None => {
+            let multiplier = match rest.chars().next() {
+                Some('k' | 'K') => 1_000,
+                Some('M') => 1_000_000,
+                _ => return Err(ParseAmountError::UnknownUnit(rest.to_string())),
+            };
+            let scale = unit_scale(rest[1..].trim_start(), default_scale)
+                .ok_or_else(|| ParseAmountError::UnknownUnit(rest.to_string()))?;
+            (multiplier, scale)
+        }
+    };
+
+    let divisor = 10i128.pow(frac.len() as u32);
+    let scaled = mantissa
+        .checked_mul(multiplier)
+        .and_then(|v| v.checked_mul(scale))
+        .ok_or(ParseAmountError::OutOfRange)?;
+    if scaled % divisor != 0 {
+        return Err(ParseAmountError::TooPrecise);
+    }
+    let msats = scaled / divisor;
+    Ok(if negative { -msats } else { msats })
+}
+
+fn unit_scale(unit: &str, default_scale: i128) -> Option<i128> {
+    match unit.to_ascii_lowercase().as_str() {
+        "" => Some(default_scale),
+        "sat" | "sats" | "satoshi" | "satoshis" => Some(MSATS_PER_SAT),
+        "msat" | "msats" | "millisat" | "millisats" => Some(1),
+        "btc" => Some(MSATS_PER_BTC),
+        _ => None,
+    }
+}
@@ -0,0 +1,282 @@
+//! Human-in-the-loop payment approval.
+//!
+//! An [`ApprovalHandler`] attached to a wallet handle with
+//! [`Wallet::with_approval_handler`](crate::Wallet::with_approval_handler) is
+//! consulted by [`PaymentsResource::create`](crate::resources::PaymentsResource::create)
+//! and [`L402Resource::pay`](crate::resources::L402Resource::pay) before the
+//! payment is sent. It receives a [`PaymentPreview`], including the
+//! destination resolved by
+//! [`PaymentsResource::resolve`](crate::resources::PaymentsResource::resolve),
+//! and answers with an [`Approval`]. A denied payment fails with
+//! [`LnBotError::ApprovalDenied`].
+//!
+//! Built-in handlers: [`AlwaysApprove`], [`DenyAbove`], [`AboveThreshold`]
+//! to only ask for larger payments, and [`ChannelApprovalHandler`] to
+//! forward requests to a UI.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), lnbot::LnBotError> {
+//! use lnbot::approval::{AboveThreshold, ChannelApprovalHandler};
+//! use lnbot::LnBot;
+//!
+//! let (handler, mut requests) = ChannelApprovalHandler::new(16);
+//! let w = LnBot::new("uk_...")
+//!     .wallet("wal_...")?
+//!     .with_approval_handler(AboveThreshold::new(1_000, handler));
+//!
+//! tokio::spawn(async move {
+//!     while let Some(request) = requests.recv().await {
+//!         println!("approve {:?} sats to {}?", request.preview().amount, request.preview().target);
+//!         request.approve();
+//!     }
+//! });
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::amount::Sats;
+use crate::errors::LnBotError;
+use crate::types::ResolveTargetResponse;
+
+/// What is being paid for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PaymentKind {
+    /// A payment created with `payments().create`.
+    Payment,
+    /// An L402 challenge paid with `l402().pay`.
+    L402,
+}
+
+/// A payment awaiting approval.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PaymentPreview {
+    pub kind: PaymentKind,
+    /// The target as given: a BOLT11 invoice, Lightning address or LNURL.
+    pub target: String,
+    /// Amount, from the resolved destination if it fixes one, or else
+    /// from the request.
+    pub amount: Option<Sats>,
+    /// Maximum routing fee, if capped.
+    pub max_fee: Option<Sats>,
+    pub reference: Option<String>,
+    /// The destination as resolved by the API.
+    pub destination: ResolveTargetResponse,
+}
+
+/// Changes an [`ApprovalHandler`] applies to a payment before approving it.
+///
+/// An amount fixed by the destination, such as a BOLT11 invoice's or an
+/// L402 payment's, cannot be changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaymentChanges {
+    pub amount: Option<Sats>,
+    pub max_fee: Option<Sats>,
+    pub reference: Option<String>,
+}
+
+impl PaymentChanges {
+    /// Creates an empty set of changes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a new amount, in sats.
+    #[must_use]
+    pub fn amount(mut self, amount: impl Into<Sats>) -> Self {
+        self.amount = Some(amount.into());
+        self
+    }
+
+    /// Sets a new maximum routing fee, in sats.
+    #[must_use]
+    pub fn max_fee(mut self, max_fee: impl Into<Sats>) -> Self {
+        self.max_fee = Some(max_fee.into());
+        self
+    }
+
+    /// Sets a new reference.
+    #[must_use]
+    pub fn reference(mut self, reference: impl Into<String>) -> Self {
+        self.reference = Some(reference.into());
+        self
+    }
+}
+
+/// An [`ApprovalHandler`]'s decision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Approval {
+    /// Send the payment as is.
+    Approve,
+    /// Refuse the payment, with a reason.
+    Deny(String),
+    /// Send the payment with changes.
+    Modify(PaymentChanges),
+}
+
+/// Decides whether a payment may be sent.
+pub trait ApprovalHandler: Send + Sync + 'static {
+    /// Reviews a payment before it is sent.
+    fn review(&self, preview: PaymentPreview) -> BoxFuture<'_, Approval>;
+}
+
+/// Approves every payment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlwaysApprove;
+
+impl ApprovalHandler for AlwaysApprove {
+    fn review(&self, _preview: PaymentPreview) -> BoxFuture<'_, Approval> {
+        Box::pin(async { Approval::Approve })
+    }
+}
+
+/// Denies payments above the given amount, or of unknown amount.
+#[derive(Debug, Clone, Copy)]
+pub struct DenyAbove(pub Sats);
+
+impl ApprovalHandler for DenyAbove {
+    fn review(&self, preview: PaymentPreview) -> BoxFuture<'_, Approval> {
+        let approval = match preview.amount {
+            Some(amount) if amount <= self.0 => Approval::Approve,
+            Some(amount) => Approval::Deny(format!("{} is above the limit of {}", amount, self.0)),
+            None => Approval::Deny("the payment amount is unknown".into()),
+        };
+        Box::pin(async move { approval })
+    }
+}
+
+/// Approves payments up to `threshold` and asks `handler` about the
+/// rest, including payments of unknown amount.
+#[derive(Debug, Clone)]
+pub struct AboveThreshold<H> {
+    threshold: Sats,
+    handler: H,
+}
+
+impl<H: ApprovalHandler> AboveThreshold<H> {
+    /// Wraps `handler`, consulting it only above `threshold` sats.
+    pub fn new(threshold: impl Into<Sats>, handler: H) -> Self {
+        Self {
+            threshold: threshold.into(),
+            handler,
+        }
+    }
+}
+
+impl<H: ApprovalHandler> ApprovalHandler for AboveThreshold<H> {
+    fn review(&self, preview: PaymentPreview) -> BoxFuture<'_, Approval> {
+        match preview.amount {
+            Some(amount) if amount <= self.threshold => Box::pin(async { Approval::Approve }),
+            _ => self.handler.review(preview),
+        }
+    }
+}
+
+/// Forwards payments to a channel, for a UI or operator to decide on.
+///
+/// Payments are denied if the receiver is dropped, if an
+/// [`ApprovalRequest`] is dropped without an answer, or once the optional
+/// timeout elapses.
+#[derive(Debug, Clone)]
+pub struct ChannelApprovalHandler {
+    sender: mpsc::Sender<ApprovalRequest>,
+    timeout: Option<Duration>,
+}
+
+impl ChannelApprovalHandler {
+    /// Creates a handler and the receiver for its requests, queueing up to
+    /// `buffer` pending requests.
+    pub fn new(buffer: usize) -> (Self, mpsc::Receiver<ApprovalRequest>) {
+        let (sender, receiver) = mpsc::channel(buffer);
+        (
+            Self {
+                sender,
+                timeout: None,
+            },
+            receiver,
+        )
+    }
+
+    /// Denies payments left unanswered for `timeout`.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl ApprovalHandler for ChannelApprovalHandler {
+    fn review(&self, preview: PaymentPreview) -> BoxFuture<'_, Approval> {
+        Box::pin(async move {
+            let (respond, response) = oneshot::channel();
+            let decision = async {
+                self.sender
+                    .send(ApprovalRequest { preview, respond })
+                    .await
+                    .ok()?;
+                response.await.ok()
+            };
+            let decision = match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, decision)
+                    .await
+                    .unwrap_or_else(|_| Some(Approval::Deny("approval timed out".into()))),
+                None => decision.await,
+            };
+            decision.unwrap_or_else(|| Approval::Deny("approval channel closed".into()))
+        })
+    }
+}
+
+/// A payment waiting for a decision, received from a [`ChannelApprovalHandler`].
+#[derive(Debug)]
+pub struct ApprovalRequest {
+    preview: PaymentPreview,
+    respond: oneshot::Sender<Approval>,
+}
+
+impl ApprovalRequest {
+    /// The payment awaiting approval.
+    pub fn preview(&self) -> &PaymentPreview {
+        &self.preview
+    }
+
+    /// Answers with `approval`.
+    pub fn respond(self, approval: Approval) {
+        let _ = self.respond.send(approval);
+    }
+
+    /// Approves the payment as is.
+    pub fn approve(self) {
+        self.respond(Approval::Approve);
+    }
+
+    /// Denies the payment.
+    pub fn deny(self, reason: impl Into<String>) {
+        self.respond(Approval::Deny(reason.into()));
+    }
+
+    /// Approves the payment with changes.
+    pub fn modify(self, changes: PaymentChanges) {
+        self.respond(Approval::Modify(changes));
+    }
+}
+
+/// Asks `handler` about a payment, returning the changes to apply, if any.
+pub(crate) async fn review(
+    handler: &dyn ApprovalHandler,
+    preview: PaymentPreview,
+) -> Result<Option<PaymentChanges>, LnBotError> {
+    match handler.review(preview).await {
+        Approval::Approve => Ok(None),
+        Approval::Modify(changes) => Ok(Some(changes)),
+        Approval::Deny(reason) => Err(LnBotError::ApprovalDenied { reason }),
+    }
+}
@@ -0,0 +1,480 @@
+//! Offline BOLT11 invoice decoding.
+//!
+//! Parses and validates [BOLT11] payment requests without calling the API:
+//! bech32 checksum, network prefix, amount, tagged fields and the payee
+//! signature. The payee public key is recovered from the signature, or
+//! checked against the `n` field when the invoice carries one.
+//!
+//! [BOLT11]: https://github.com/lightning/bolts/blob/master/11-payment-encoding.md
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use lnbot::bolt11::Bolt11Invoice;
+//!
+//! let invoice: Bolt11Invoice = "lnbc2500u1p...".parse()?;
+//! println!("{:?} msat, expires at {:?}", invoice.amount_msat, invoice.expires_at());
+//! # Ok::<(), lnbot::bolt11::Bolt11Error>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+use crate::amount::MilliSats;
+use crate::preimage::PaymentHash;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHECKSUM_LEN: usize = 6;
+const TIMESTAMP_LEN: usize = 7;
+const SIGNATURE_LEN: usize = 104;
+
+/// Expiry applied when an invoice has no `x` field.
+pub const DEFAULT_EXPIRY: Duration = Duration::from_secs(3600);
+
+/// `min_final_cltv_expiry_delta` applied when an invoice has no `c` field.
+pub const DEFAULT_MIN_FINAL_CLTV_EXPIRY_DELTA: u64 = 18;
+
+/// Error returned when a BOLT11 string cannot be decoded.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum Bolt11Error {
+    #[error("invoice mixes upper and lower case characters")]
+    MixedCase,
+
+    #[error("missing bech32 separator")]
+    MissingSeparator,
+
+    #[error("invalid bech32 character {0:?}")]
+    InvalidCharacter(char),
+
+    #[error("invalid bech32 checksum")]
+    InvalidChecksum,
+
+    #[error("invalid human-readable prefix {0:?}")]
+    InvalidPrefix(String),
+
+    #[error("invalid amount {0:?}")]
+    InvalidAmount(String),
+
+    #[error("invoice data is too short")]
+    TooShort,
+
+    #[error("invalid {0} field")]
+    InvalidField(&'static str),
+
+    #[error("missing payment hash")]
+    MissingPaymentHash,
+
+    #[error("missing description or description hash")]
+    MissingDescription,
+
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    #[error("signature does not match the payee public key")]
+    PayeeMismatch,
+}
+
+/// The network an invoice is for, taken from its prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Network {
+    Bitcoin,
+    Testnet,
+    Signet,
+    Regtest,
+    Unknown(String),
+}
+
+impl Network {
+    fn from_prefix(prefix: &str) -> Self {
+        match prefix {
+            "bc" => Self::Bitcoin,
+            "tb" => Self::Testnet,
+            "tbs" => Self::Signet,
+            "bcrt" => Self::Regtest,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+
+    /// The BOLT11 currency prefix, e.g. `"bc"` for mainnet.
+    pub fn prefix(&self) -> &str {
+        match self {
+            Self::Bitcoin => "bc",
+            Self::Testnet => "tb",
+            Self::Signet => "tbs",
+            Self::Regtest => "bcrt",
+            Self::Unknown(prefix) => prefix,
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.prefix())
+    }
+}
+
+/// One hop of a private route hint (`r` field).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteHintHop {
+    /// Compressed public key of the node at the start of the channel.
+    pub pubkey: [u8; 33],
+    pub short_channel_id: u64,
+    pub fee_base_msat: u32,
+    pub fee_proportional_millionths: u32,
+    pub cltv_expiry_delta: u16,
+}
+
+/// A private route to the payee, as a list of hops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteHint {
+    pub hops: Vec<RouteHintHop>,
+}
+
+/// Feature bits advertised by an invoice (`9` field).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Features {
+    words: Vec<u8>,
+}
+
+impl Features {
+    /// Returns `true` if the given feature bit is set.
+    pub fn is_set(&self, bit: usize) -> bool {
+        let index = bit / 5;
+        index < self.words.len() && self.words[self.words.len() - 1 - index] & (1 << (bit % 5)) != 0
+    }
+
+    /// Iterates over the set feature bits in ascending order.
+    pub fn bits(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.words.len() * 5).filter(|&bit| self.is_set(bit))
+    }
+
+    /// Returns `true` if no feature bit is set.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+}
+
+/// A decoded and signature-checked BOLT11 invoice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Bolt11Invoice {
+    pub network: Network,
+    /// Requested amount in millisatoshis. `None` for "any amount" invoices.
+    pub amount_msat: Option<u64>,
+    /// Creation time in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub payment_hash: PaymentHash,
+    pub payment_secret: Option<[u8; 32]>,
+    pub description: Option<String>,
+    pub description_hash: Option<[u8; 32]>,
+    /// How long after `timestamp` the invoice stays payable.
+    pub expiry: Duration,
+    pub min_final_cltv_expiry_delta: u64,
+    /// Compressed public key of the payee, recovered from the signature.
+    pub payee_pubkey: [u8; 33],
+    pub route_hints: Vec<RouteHint>,
+    pub features: Features,
+    pub metadata: Option<Vec<u8>>,
+}
+
+impl Bolt11Invoice {
+    /// Decodes and validates a BOLT11 string. A `lightning:` URI prefix is accepted.
+    pub fn parse(s: &str) -> Result<Self, Bolt11Error> {
+        let s = s.trim();
+        if let Some(c) = s.chars().find(|c| !c.is_ascii()) {
+            return Err(Bolt11Error::InvalidCharacter(c));
+        }
+        let s = s
+            .get(..10)
+            .filter(|p| p.eq_ignore_ascii_case("lightning:"))
+            .map_or(s, |_| &s[10..]);
+        if s.bytes().any(|b| b.is_ascii_lowercase()) && s.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Err(Bolt11Error::MixedCase);
+        }
+        let s = s.to_ascii_lowercase();
+
+        let (hrp, data) = bech32_decode(&s)?;
+        let (network, amount_msat) = parse_hrp(hrp)?;
+
+        if data.len() < TIMESTAMP_LEN + SIGNATURE_LEN {
+            return Err(Bolt11Error::TooShort);
+        }
+        let (signed, signature) = data.split_at(data.len() - SIGNATURE_LEN);
+        let timestamp =
+            words_to_u64(&signed[..TIMESTAMP_LEN]).ok_or(Bolt11Error::InvalidField("timestamp"))?;
+
+        let mut invoice = Self {
+            network,
+            amount_msat,
+            timestamp,
+            payment_hash: PaymentHash::from_bytes([0; 32]),
+            payment_secret: None,
+            description: None,
+            description_hash: None,
+            expiry: DEFAULT_EXPIRY,
+            min_final_cltv_expiry_delta: DEFAULT_MIN_FINAL_CLTV_EXPIRY_DELTA,
+            payee_pubkey: [0; 33],
+            route_hints: Vec::new(),
+            features: Features::default(),
+            metadata: None,
+        };
+        let mut payment_hash = None;
+        let mut payee = None;
+
+        let mut fields = &signed[TIMESTAMP_LEN..];
+        while !fields.is_empty() {
+            if fields.len() < 3 {
+                return Err(Bolt11Error::TooShort);
+            }
+            let tag = CHARSET[fields[0] as usize];
+            let len = (fields[1] as usize) << 5 | fields[2] as usize;
+            let value = fields.get(3..3 + len).ok_or(Bolt11Error::TooShort)?;
+            fields = &fields[3 + len..];
+
+            // Fields with an unexpected length are skipped, as BOLT11 requires.
+            match tag {
+                b'p' if len == 52 => payment_hash = Some(PaymentHash::from_bytes(to_array(value))),
+                b's' if len == 52 => invoice.payment_secret = Some(to_array(value)),
+                b'h' if len == 52 => invoice.description_hash = Some(to_array(value)),
+                b'n' if len == 53 => payee = Some(to_array(value)),
+                b'd' => {
+                    invoice.description = Some(
+                        String::from_utf8(words_to_bytes(value))
+                            .map_err(|_| Bolt11Error::InvalidField("description"))?,
+                    )
+                }
+                b'x' => {
+                    let secs = words_to_u64(value).ok_or(Bolt11Error::InvalidField("expiry"))?;
+                    invoice.expiry = Duration::from_secs(secs);
+                }
+                b'c' => {
+                    invoice.min_final_cltv_expiry_delta = words_to_u64(value)
+                        .ok_or(Bolt11Error::InvalidField("min_final_cltv_expiry_delta"))?
+                }
+                b'm' => invoice.metadata = Some(words_to_bytes(value)),
+                b'9' => {
+                    invoice.features = Features {
+                        words: value.to_vec(),
+                    }
+                }
+                b'r' => invoice
+                    .route_hints
+                    .push(parse_route_hint(&words_to_bytes(value))?),
+                _ => {}
+            }
+        }
+
+        invoice.payment_hash = payment_hash.ok_or(Bolt11Error::MissingPaymentHash)?;
+        if invoice.description.is_none() && invoice.description_hash.is_none() {
+            return Err(Bolt11Error::MissingDescription);
+        }
+        invoice.payee_pubkey = recover_payee(hrp, signed, signature)?;
+        if matches!(payee, Some(payee) if payee != invoice.payee_pubkey) {
+            return Err(Bolt11Error::PayeeMismatch);
+        }
+        Ok(invoice)
+    }
+
+    /// Requested amount, or `None` for "any amount" invoices.
+    pub fn amount(&self) -> Option<MilliSats> {
+        self.amount_msat
+            .map(|msat| MilliSats::try_from(msat).unwrap_or(MilliSats::MAX))
+    }
+
+    /// Requested amount in whole satoshis, rounded down.
+    pub fn amount_sats(&self) -> Option<u64> {
+        self.amount_msat.map(|msat| msat / 1000)
+    }
+
+    /// When the invoice was created.
+    pub fn created_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.timestamp)
+    }
+
+    /// When the invoice stops being payable, or `None` if that is too far in
+    /// the future for `SystemTime` to represent.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.created_at().checked_add(self.expiry)
+    }
+
+    /// Returns `true` if the invoice has expired.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at().is_some_and(|at| SystemTime::now() >= at)
+    }
+}
+
+impl FromStr for Bolt11Invoice {
+    type Err = Bolt11Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn bech32_decode(s: &str) -> Result<(&str, Vec<u8>), Bolt11Error> {
+    let sep = s.rfind('1').ok_or(Bolt11Error::MissingSeparator)?;
+    let (hrp, data) = (&s[..sep], &s[sep + 1..]);
+    if hrp.is_empty() || data.len() < CHECKSUM_LEN {
+        return Err(Bolt11Error::MissingSeparator);
+    }
+
+    let words = data
+        .chars()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|&x| x as char == c)
+                .map(|i| i as u8)
+                .ok_or(Bolt11Error::InvalidCharacter(c))
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values.extend(&words);
+    if polymod(&values) != 1 {
+        return Err(Bolt11Error::InvalidChecksum);
+    }
+
+    let mut words = words;
+    words.truncate(words.len() - CHECKSUM_LEN);
+    Ok((hrp, words))
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk: u32 = 1;
+    for &v in values {
+        let top = chk >> 25;
+        chk = (chk & 0x1ffffff) << 5 ^ u32::from(v);
+        for (i, g) in GEN.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn parse_hrp(hrp: &str) -> Result<(Network, Option<u64>), Bolt11Error> {
+    let rest = hrp
+        .strip_prefix("ln")
+        .ok_or_else(|| Bolt11Error::InvalidPrefix(hrp.to_string()))?;
+    let split = rest
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (currency, amount) = rest.split_at(split);
+    if currency.is_empty() {
+        return Err(Bolt11Error::InvalidPrefix(hrp.to_string()));
+    }
+    let network = Network::from_prefix(currency);
+    if amount.is_empty() {
+        return Ok((network, None));
+    }
+
+    let invalid = || Bolt11Error::InvalidAmount(amount.to_string());
+    let (digits, multiplier) = match amount.as_bytes()[amount.len() - 1] {
+        b'0'..=b'9' => (amount, None),
+        m => (&amount[..amount.len() - 1], Some(m)),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let value: u64 = digits.parse().map_err(|_| invalid())?;
+    let msat = match multiplier {
+        None => value.checked_mul(100_000_000_000),
+        Some(b'm') => value.checked_mul(100_000_000),
+        Some(b'u') => value.checked_mul(100_000),
+        Some(b'n') => value.checked_mul(100),
+        Some(b'p') if value % 10 == 0 => Some(value / 10),
+        _ => None,
+    };
+    Ok((network, Some(msat.ok_or_else(invalid)?)))
+}
+
+fn parse_route_hint(bytes: &[u8]) -> Result<RouteHint, Bolt11Error> {
+    const HOP_LEN: usize = 51;
+    if bytes.is_empty() || bytes.len() % HOP_LEN != 0 {
+        return Err(Bolt11Error::InvalidField("route hint"));
+    }
+    let hops = bytes
+        .chunks_exact(HOP_LEN)
+        .map(|hop| RouteHintHop {
+            pubkey: hop[..33].try_into().unwrap(),
+            short_channel_id: u64::from_be_bytes(hop[33..41].try_into().unwrap()),
+            fee_base_msat: u32::from_be_bytes(hop[41..45].try_into().unwrap()),
+            fee_proportional_millionths: u32::from_be_bytes(hop[45..49].try_into().unwrap()),
+            cltv_expiry_delta: u16::from_be_bytes(hop[49..51].try_into().unwrap()),
+        })
+        .collect();
+    Ok(RouteHint { hops })
+}
+
+fn recover_payee(hrp: &str, signed: &[u8], signature: &[u8]) -> Result<[u8; 33], Bolt11Error> {
+    let signature = words_to_bytes(signature);
+    let recovery_id = RecoveryId::from_byte(signature[64]).ok_or(Bolt11Error::InvalidSignature)?;
+    let signature =
+        Signature::from_slice(&signature[..64]).map_err(|_| Bolt11Error::InvalidSignature)?;
+
+    let mut preimage = hrp.as_bytes().to_vec();
+    preimage.extend(words_to_bytes_padded(signed));
+    let digest = Sha256::digest(&preimage);
+
+    let key = VerifyingKey::recover_from_prehash(&digest, &signature, recovery_id)
+        .map_err(|_| Bolt11Error::InvalidSignature)?;
+    Ok(to_array_bytes(key.to_encoded_point(true).as_bytes()))
+}
+
+/// Reads big-endian 5-bit words as an integer, or `None` if it needs more
+/// than 64 bits.
+fn words_to_u64(words: &[u8]) -> Option<u64> {
+    words.iter().try_fold(0u64, |acc, &w| {
+        (acc >> 59 == 0).then(|| acc << 5 | u64::from(w))
+    })
+}
+
+/// Regroups 5-bit words into bytes, dropping incomplete trailing bits.
+fn words_to_bytes(words: &[u8]) -> Vec<u8> {
+    regroup(words, false)
+}
+
+/// Regroups 5-bit words into bytes, zero-padding the last byte.
+fn words_to_bytes_padded(words: &[u8]) -> Vec<u8> {
+    regroup(words, true)
+}
+
+fn regroup(words: &[u8], pad: bool) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(words.len() * 5 / 8 + 1);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for &w in words {
+        acc = (acc << 5) | u32::from(w);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    if pad && bits > 0 {
+        bytes.push((acc << (8 - bits)) as u8);
+    }
+    bytes
+}
+
+fn to_array<const N: usize>(words: &[u8]) -> [u8; N] {
+    to_array_bytes(&words_to_bytes(words))
+}
+
+fn to_array_bytes<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut out = [0; N];
+    out.copy_from_slice(&bytes[..N]);
+    out
+}
@@ -0,0 +1,212 @@
+//! Builder for configuring an [`LnBot`] client.
+
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Proxy, Url};
+
+use crate::client::{ClientConfig, LnBot, DEFAULT_BASE_URL, USER_AGENT};
+use crate::errors::LnBotError;
+use crate::ids::ApiKey;
+use crate::retry::RetryPolicy;
+use crate::secret::Secret;
+
+/// Environment variable holding the API key, read by [`LnBotBuilder::from_env`].
+pub const API_KEY_ENV: &str = "LNBOT_API_KEY";
+
+/// Environment variable holding the base URL, read by [`LnBotBuilder::from_env`].
+pub const BASE_URL_ENV: &str = "LNBOT_BASE_URL";
+
+/// Configures and builds an [`LnBot`] client.
+///
+/// Obtain one with [`LnBot::builder`]. Nothing is validated until
+/// [`build`](LnBotBuilder::build) is called.
+///
+/// # Examples
+///
+/// ```
+/// # fn example() -> Result<(), lnbot::LnBotError> {
+/// use std::time::Duration;
+/// use lnbot::LnBot;
+///
+/// let client = LnBot::builder()
+///     .api_key("uk_...")
+///     .connect_timeout(Duration::from_secs(5))
+///     .timeout(Duration::from_secs(30))
+///     .user_agent("my-agent/1.0")
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LnBotBuilder {
+    api_key: Option<ApiKey>,
+    base_url: Option<String>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    sse_idle_timeout: Option<Duration>,
+    user_agent: Option<String>,
+    headers: Vec<(String, String)>,
+    proxy: Option<Secret<String>>,
+    retry: Option<RetryPolicy>,
+}
+
+impl LnBotBuilder {
+    /// Creates an empty builder. Equivalent to [`LnBot::builder`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a builder pre-filled from the `LNBOT_API_KEY` and
+    /// `LNBOT_BASE_URL` environment variables, when they are set.
+    pub fn from_env() -> Self {
+        let mut builder = Self::new();
+        if let Ok(key) = std::env::var(API_KEY_ENV) {
+            builder.api_key = Some(key.into());
+        }
+        if let Ok(url) = std::env::var(BASE_URL_ENV) {
+            builder.base_url = Some(url);
+        }
+        builder
+    }
+
+    /// Sets the API key. Without one, the client only works with public endpoints.
+    #[must_use]
+    pub fn api_key(mut self, api_key: impl Into<ApiKey>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Overrides the base URL for the API.
+    #[must_use]
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    /// Sets the timeout for establishing a connection.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout for a whole request, from sending it to reading the
+    /// response body. Does not apply to SSE streams.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Ends SSE streams with [`LnBotError::SseIdleTimeout`] when no data,
+    /// including keep-alive comments, arrives for this long.
+    #[must_use]
+    pub fn sse_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.sse_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Appends a product token to the default `lnbot-rust/<version>` User-Agent.
+    #[must_use]
+    pub fn user_agent(mut self, suffix: impl Into<String>) -> Self {
+        self.user_agent = Some(suffix.into());
+        self
+    }
+
+    /// Adds a header sent with every request.
+    #[must_use]
+    pub fn default_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Routes all requests through the given proxy URL. The URL may carry
+    /// credentials, so it is redacted from `Debug` output and errors.
+    #[must_use]
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(Secret::new(url.into()));
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] applied to every request.
+    #[must_use]
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Validates the configuration and builds the client.
+    pub fn build(self) -> Result<LnBot, LnBotError> {
+        if matches!(&self.api_key, Some(key) if key.expose_secret().trim().is_empty()) {
+            return Err(config_error("API key must not be empty"));
+        }
+
+        let base_url = self
+            .base_url
+            .as_deref()
+            .unwrap_or(DEFAULT_BASE_URL)
+            .trim_end_matches('/')
+            .to_string();
+        let parsed = Url::parse(&base_url)
+            .map_err(|e| config_error(format!("invalid base URL {:?}: {}", base_url, e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(config_error(format!(
+                "base URL must use http or https, got {:?}",
+                parsed.scheme()
+            )));
+        }
+
+        for (name, timeout) in [
+            ("connect timeout", self.connect_timeout),
+            ("timeout", self.timeout),
+            ("SSE idle timeout", self.sse_idle_timeout),
+        ] {
+            if timeout == Some(Duration::ZERO) {
+                return Err(config_error(format!("{} must be greater than zero", name)));
+            }
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| config_error(format!("invalid header name {:?}", name)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| config_error(format!("invalid value for header {:?}", name)))?;
+            headers.append(name, value);
+        }
+
+        let user_agent = match &self.user_agent {
+            Some(suffix) => format!("{} {}", USER_AGENT, suffix.trim()),
+            None => USER_AGENT.to_string(),
+        };
+
+        let mut http = Client::builder()
+            .user_agent(user_agent)
+            .default_headers(headers);
+        if let Some(timeout) = self.connect_timeout {
+            http = http.connect_timeout(timeout);
+        }
+        if let Some(url) = &self.proxy {
+            let proxy = Proxy::all(url.expose_secret())
+                .map_err(|e| config_error(format!("invalid proxy URL: {}", e.without_url())))?;
+            http = http.proxy(proxy);
+        }
+        let http = http
+            .build()
+            .map_err(|e| config_error(format!("failed to build HTTP client: {}", e)))?;
+
+        Ok(LnBot::from_config(ClientConfig {
+            http,
+            base_url,
+            api_key: self.api_key,
+            retry: self.retry.unwrap_or_else(RetryPolicy::none),
+            timeout: self.timeout,
+            sse_idle_timeout: self.sse_idle_timeout,
+        }))
+    }
+}
+
+fn config_error(message: impl Into<String>) -> LnBotError {
+    LnBotError::Config(message.into())
+}
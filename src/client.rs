@@ -1,18 +1,35 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::StreamExt;
 use reqwest::{Client, RequestBuilder, Response};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::errors::{from_status, LnBotError};
+use crate::approval::ApprovalHandler;
+use crate::builder::LnBotBuilder;
+use crate::errors::{from_status, retry_after, LnBotError};
+use crate::ids::{ApiKey, WalletId};
+use crate::policy::SpendingPolicy;
 use crate::resources::*;
+use crate::retry::RetryPolicy;
+use crate::sse::{self, SseEvent};
 use crate::types::*;
 
-const DEFAULT_BASE_URL: &str = "https://api.ln.bot";
+pub(crate) const DEFAULT_BASE_URL: &str = "https://api.ln.bot";
+pub(crate) const USER_AGENT: &str = concat!("lnbot-rust/", env!("CARGO_PKG_VERSION"));
 
 /// LnBot API client.
 ///
-/// Create an instance with [`LnBot::new`] (authenticated) or
-/// [`LnBot::unauthenticated`] (for public endpoints), then access
-/// wallet-scoped resources through [`wallet`](LnBot::wallet).
+/// Create an instance with [`LnBot::new`] (authenticated),
+/// [`LnBot::unauthenticated`] (for public endpoints) or [`LnBot::builder`]
+/// (for timeouts, proxies and other settings), then access wallet-scoped
+/// resources through [`wallet`](LnBot::wallet).
+///
+/// The client is cheap to clone: clones share the same connection pool and
+/// configuration, so it can be stored in structs or moved into spawned tasks.
 ///
 /// # Examples
 ///
@@ -21,48 +38,88 @@ const DEFAULT_BASE_URL: &str = "https://api.ln.bot";
 /// use lnbot::{LnBot, CreateInvoiceRequest};
 ///
 /// let client = LnBot::new("uk_...");
-/// let w = client.wallet("wal_...");
+/// let w = client.wallet("wal_...")?;
 /// let invoice = w.invoices().create(&CreateInvoiceRequest::new(1000)).await?;
 /// println!("{}", invoice.bolt11);
 /// # Ok(())
 /// # }
 /// ```
+#[derive(Clone)]
 pub struct LnBot {
+    pub(crate) inner: Arc<ClientConfig>,
+}
+
+#[derive(Clone)]
+pub(crate) struct ClientConfig {
     pub(crate) http: Client,
     pub(crate) base_url: String,
-    pub(crate) api_key: Option<String>,
+    pub(crate) api_key: Option<ApiKey>,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) sse_idle_timeout: Option<Duration>,
 }
 
-impl LnBot {
-    /// Creates a new authenticated client with the given API key.
-    pub fn new(api_key: impl Into<String>) -> Self {
+impl Default for ClientConfig {
+    fn default() -> Self {
         Self {
-            http: Client::new(),
+            http: Client::builder()
+                .user_agent(USER_AGENT)
+                .build()
+                .expect("failed to build HTTP client"),
             base_url: DEFAULT_BASE_URL.to_string(),
-            api_key: Some(api_key.into()),
+            api_key: None,
+            retry: RetryPolicy::none(),
+            timeout: None,
+            sse_idle_timeout: None,
         }
     }
+}
+
+impl LnBot {
+    /// Creates a new authenticated client with the given API key.
+    pub fn new(api_key: impl Into<ApiKey>) -> Self {
+        Self::from_config(ClientConfig {
+            api_key: Some(api_key.into()),
+            ..ClientConfig::default()
+        })
+    }
 
     /// Creates a new unauthenticated client for public endpoints.
     pub fn unauthenticated() -> Self {
+        Self::from_config(ClientConfig::default())
+    }
+
+    pub(crate) fn from_config(config: ClientConfig) -> Self {
         Self {
-            http: Client::new(),
-            base_url: DEFAULT_BASE_URL.to_string(),
-            api_key: None,
+            inner: Arc::new(config),
         }
     }
 
+    /// Returns a [`LnBotBuilder`] for configuring timeouts, proxies,
+    /// headers and other client settings.
+    pub fn builder() -> LnBotBuilder {
+        LnBotBuilder::new()
+    }
+
     /// Overrides the base URL for the API.
     #[must_use]
     pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
-        self.base_url = url.into().trim_end_matches('/').to_string();
+        Arc::make_mut(&mut self.inner).base_url = url.into().trim_end_matches('/').to_string();
         self
     }
 
     /// Overrides the inner [`reqwest::Client`] used for HTTP requests.
     #[must_use]
     pub fn with_http_client(mut self, client: Client) -> Self {
-        self.http = client;
+        Arc::make_mut(&mut self.inner).http = client;
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] applied to every request. Requests are not
+    /// retried unless a policy is set.
+    #[must_use]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        Arc::make_mut(&mut self.inner).retry = policy;
         self
     }
 
@@ -78,51 +135,127 @@ impl LnBot {
 
     /// Returns a wallet handle for the given wallet ID.
     ///
-    /// All wallet-scoped operations go through this handle.
-    pub fn wallet(&self, wallet_id: &str) -> Wallet<'_> {
-        assert!(!wallet_id.is_empty(), "wallet_id must not be empty");
-        Wallet {
-            client: self,
-            prefix: format!("/v1/wallets/{}", wallet_id),
-        }
+    /// All wallet-scoped operations go through this handle. The handle owns a
+    /// clone of the client, so it can outlive `self`. Fails with
+    /// [`LnBotError::InvalidId`] if `wallet_id` is not a valid [`WalletId`].
+    pub fn wallet<I>(&self, wallet_id: I) -> Result<Wallet, LnBotError>
+    where
+        I: TryInto<WalletId>,
+        LnBotError: From<I::Error>,
+    {
+        let id = wallet_id.try_into()?;
+        Ok(Wallet {
+            client: self.clone(),
+            prefix: format!("/v1/wallets/{}", urlencoding::encode(id.as_str())).into(),
+            id,
+            policy: None,
+            approval: None,
+        })
     }
 
     /// Access wallet operations (create, list).
-    pub fn wallets(&self) -> WalletsResource<'_> {
-        WalletsResource { client: self }
+    pub fn wallets(&self) -> WalletsResource {
+        WalletsResource {
+            client: self.clone(),
+        }
     }
 
     /// Access API key operations.
-    pub fn keys(&self) -> KeysResource<'_> {
-        KeysResource { client: self }
+    pub fn keys(&self) -> KeysResource {
+        KeysResource {
+            client: self.clone(),
+        }
     }
 
     /// Access public invoice operations (no auth required).
-    pub fn invoices(&self) -> PublicInvoicesResource<'_> {
-        PublicInvoicesResource { client: self }
+    pub fn invoices(&self) -> PublicInvoicesResource {
+        PublicInvoicesResource {
+            client: self.clone(),
+        }
     }
 
     /// Access backup operations.
-    pub fn backup(&self) -> BackupResource<'_> {
-        BackupResource { client: self }
+    pub fn backup(&self) -> BackupResource {
+        BackupResource {
+            client: self.clone(),
+        }
     }
 
     /// Access restore operations.
-    pub fn restore(&self) -> RestoreResource<'_> {
-        RestoreResource { client: self }
+    pub fn restore(&self) -> RestoreResource {
+        RestoreResource {
+            client: self.clone(),
+        }
+    }
+
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("{}{}", self.inner.base_url, path)
     }
 
     pub(crate) fn auth(&self, req: RequestBuilder) -> RequestBuilder {
-        let req = req.header("Accept", "application/json");
-        match &self.api_key {
-            Some(key) => req.bearer_auth(key),
+        let mut req = req.header("Accept", "application/json");
+        if let Some(timeout) = self.inner.timeout {
+            req = req.timeout(timeout);
+        }
+        match &self.inner.api_key {
+            Some(key) => req.bearer_auth(key.expose_secret()),
             None => req,
         }
     }
 
+    /// Opens an SSE connection and returns its body as a byte stream,
+    /// enforcing the configured idle timeout between chunks.
+    pub(crate) async fn sse_bytes(
+        &self,
+        url: &str,
+        last_event_id: Option<&str>,
+    ) -> Result<impl Stream<Item = Result<Bytes, LnBotError>> + Send + Sync + 'static, LnBotError>
+    {
+        let mut req = self
+            .inner
+            .http
+            .get(url)
+            .header("Accept", "text/event-stream");
+        if let Some(ref key) = self.inner.api_key {
+            req = req.bearer_auth(key.expose_secret());
+        }
+        if let Some(id) = last_event_id {
+            req = req.header("Last-Event-ID", id);
+        }
+        let resp = check_status(req.send().await?).await?;
+        let idle = self.inner.sse_idle_timeout;
+        let mut body = resp.bytes_stream();
+        Ok(async_stream::try_stream! {
+            loop {
+                let next = match idle {
+                    Some(idle) => tokio::time::timeout(idle, body.next())
+                        .await
+                        .map_err(|_| LnBotError::SseIdleTimeout(idle))?,
+                    None => body.next().await,
+                };
+                match next {
+                    Some(chunk) => yield chunk?,
+                    None => break,
+                }
+            }
+        })
+    }
+
+    /// Opens an SSE connection and decodes it into events.
+    pub(crate) async fn sse_events(
+        &self,
+        url: &str,
+    ) -> Result<impl Stream<Item = Result<SseEvent, LnBotError>> + Send + Sync + 'static, LnBotError>
+    {
+        Ok(sse::decode(self.sse_bytes(url, None).await?))
+    }
+
     pub(crate) async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, LnBotError> {
-        let req = self.auth(self.http.get(format!("{}{}", self.base_url, path)));
-        handle_json(req.send().await?).await
+        let url = self.url(path);
+        let resp = self
+            .execute(|| self.auth(self.inner.http.get(&url)), true)
+            .await?;
+        handle_json(resp).await
     }
 
     pub(crate) async fn get_with_params<T: DeserializeOwned>(
@@ -137,10 +270,11 @@ impl LnBot {
         if let Some(after) = params.after {
             query.push(("after", after.to_string()));
         }
-        let req = self
-            .auth(self.http.get(format!("{}{}", self.base_url, path)))
-            .query(&query);
-        handle_json(req.send().await?).await
+        let url = self.url(path);
+        let resp = self
+            .execute(|| self.auth(self.inner.http.get(&url)).query(&query), true)
+            .await?;
+        handle_json(resp).await
     }
 
     pub(crate) async fn get_with_query<T: DeserializeOwned>(
@@ -148,22 +282,29 @@ impl LnBot {
         path: &str,
         query: &[(&str, &str)],
     ) -> Result<T, LnBotError> {
-        let req = self
-            .auth(self.http.get(format!("{}{}", self.base_url, path)))
-            .query(query);
-        handle_json(req.send().await?).await
+        let url = self.url(path);
+        let resp = self
+            .execute(|| self.auth(self.inner.http.get(&url)).query(query), true)
+            .await?;
+        handle_json(resp).await
     }
 
+    /// Sends a `POST` that is never retried, since repeating it may have side effects.
     pub(crate) async fn post<T: DeserializeOwned>(
         &self,
         path: &str,
         body: Option<&(impl Serialize + ?Sized)>,
     ) -> Result<T, LnBotError> {
-        let mut req = self.auth(self.http.post(format!("{}{}", self.base_url, path)));
-        if let Some(b) = body {
-            req = req.json(b);
-        }
-        handle_json(req.send().await?).await
+        handle_json(self.send_post(path, body, false).await?).await
+    }
+
+    /// Sends a `POST` that is safe to repeat and may be retried.
+    pub(crate) async fn post_idempotent<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: Option<&(impl Serialize + ?Sized)>,
+    ) -> Result<T, LnBotError> {
+        handle_json(self.send_post(path, body, true).await?).await
     }
 
     pub(crate) async fn post_no_response(
@@ -171,11 +312,7 @@ impl LnBot {
         path: &str,
         body: Option<&(impl Serialize + ?Sized)>,
     ) -> Result<(), LnBotError> {
-        let mut req = self.auth(self.http.post(format!("{}{}", self.base_url, path)));
-        if let Some(b) = body {
-            req = req.json(b);
-        }
-        handle_empty(req.send().await?).await
+        handle_empty(self.send_post(path, body, false).await?).await
     }
 
     pub(crate) async fn patch<T: DeserializeOwned>(
@@ -183,25 +320,135 @@ impl LnBot {
         path: &str,
         body: &(impl Serialize + ?Sized),
     ) -> Result<T, LnBotError> {
-        let req = self
-            .auth(self.http.patch(format!("{}{}", self.base_url, path)))
-            .json(body);
-        handle_json(req.send().await?).await
+        let url = self.url(path);
+        let resp = self
+            .execute(|| self.auth(self.inner.http.patch(&url)).json(body), true)
+            .await?;
+        handle_json(resp).await
     }
 
     pub(crate) async fn delete(&self, path: &str) -> Result<(), LnBotError> {
-        let req = self.auth(self.http.delete(format!("{}{}", self.base_url, path)));
-        handle_empty(req.send().await?).await
+        let url = self.url(path);
+        let resp = self
+            .execute(|| self.auth(self.inner.http.delete(&url)), true)
+            .await?;
+        handle_empty(resp).await
+    }
+
+    async fn send_post(
+        &self,
+        path: &str,
+        body: Option<&(impl Serialize + ?Sized)>,
+        idempotent: bool,
+    ) -> Result<Response, LnBotError> {
+        let url = self.url(path);
+        self.execute(
+            || {
+                let req = self.auth(self.inner.http.post(&url));
+                match body {
+                    Some(b) => req.json(b),
+                    None => req,
+                }
+            },
+            idempotent,
+        )
+        .await
+    }
+
+    /// Sends the request built by `build`, retrying transient failures
+    /// according to the client's [`RetryPolicy`] when `idempotent` is set.
+    /// Rate-limited requests are retried even when not idempotent, if the
+    /// policy waits on them.
+    async fn execute(
+        &self,
+        build: impl Fn() -> RequestBuilder,
+        idempotent: bool,
+    ) -> Result<Response, LnBotError> {
+        let policy = &self.inner.retry;
+        let started = Instant::now();
+        let mut attempt = 1;
+        let mut rate_limited = 0;
+        loop {
+            let can_retry = idempotent && attempt < policy.max_attempts;
+            let delay = match build().send().await {
+                Ok(resp) if resp.status().as_u16() == 429 => {
+                    // A rate-limited request was not processed, so it is safe
+                    // to repeat even when it is not idempotent.
+                    let Some(max_wait) = policy.rate_limit_wait else {
+                        return Ok(resp);
+                    };
+                    if rate_limited >= policy.max_rate_limit_retries {
+                        return Ok(resp);
+                    }
+                    rate_limited += 1;
+                    // Back off at least as much as for other failures, even
+                    // when the server says to retry at once.
+                    let delay = retry_after(resp.headers())
+                        .unwrap_or_default()
+                        .max(policy.delay_for(rate_limited));
+                    if started.elapsed() + delay > max_wait {
+                        return Ok(resp);
+                    }
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                Ok(resp) if can_retry && policy.retries_status(resp.status().as_u16()) => {
+                    policy.delay_for(attempt)
+                }
+                Ok(resp) => return Ok(resp),
+                Err(err) if can_retry && policy.retries_error(&err) => policy.delay_for(attempt),
+                Err(err) => return Err(err.into()),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
     }
 }
 
 /// A wallet handle. All wallet-scoped operations go through this.
-pub struct Wallet<'a> {
-    pub(crate) client: &'a LnBot,
-    pub(crate) prefix: String,
+///
+/// Handles are owned and cheap to clone. They, their resources and the
+/// streams they return are `Send + Sync + 'static`, so they can be stored in
+/// structs or moved into spawned tasks.
+#[derive(Clone)]
+pub struct Wallet {
+    pub(crate) client: LnBot,
+    pub(crate) id: WalletId,
+    pub(crate) prefix: Arc<str>,
+    pub(crate) policy: Option<Arc<SpendingPolicy>>,
+    pub(crate) approval: Option<Arc<dyn ApprovalHandler>>,
 }
 
-impl<'a> Wallet<'a> {
+/// An owned wallet handle. Alias for [`Wallet`].
+pub type WalletHandle = Wallet;
+
+impl Wallet {
+    /// Returns a handle that checks every payment against `policy` before
+    /// sending it. Clones of the handle share the policy's budgets.
+    #[must_use]
+    pub fn with_spending_policy(mut self, policy: SpendingPolicy) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Returns a handle that asks `handler` to approve every payment before
+    /// sending it. Approval happens before the spending policy is checked.
+    #[must_use]
+    pub fn with_approval_handler(mut self, handler: impl ApprovalHandler) -> Self {
+        self.approval = Some(Arc::new(handler));
+        self
+    }
+
+    /// The ID of this wallet.
+    pub fn id(&self) -> &WalletId {
+        &self.id
+    }
+
+    /// Returns the spending policy attached to this handle, if any.
+    pub fn spending_policy(&self) -> Option<&SpendingPolicy> {
+        self.policy.as_deref()
+    }
+
     /// Returns the wallet's current state.
     pub async fn get(&self) -> Result<WalletResponse, LnBotError> {
         self.client.get(&self.prefix).await
@@ -213,93 +460,93 @@ impl<'a> Wallet<'a> {
     }
 
     /// Access wallet key operations.
-    pub fn key(&self) -> WalletKeyResource<'_> {
+    pub fn key(&self) -> WalletKeyResource {
         WalletKeyResource {
-            client: self.client,
-            prefix: &self.prefix,
+            client: self.client.clone(),
+            prefix: self.prefix.clone(),
         }
     }
 
     /// Access invoice operations.
-    pub fn invoices(&self) -> InvoicesResource<'_> {
+    pub fn invoices(&self) -> InvoicesResource {
         InvoicesResource {
-            client: self.client,
-            prefix: &self.prefix,
+            client: self.client.clone(),
+            prefix: self.prefix.clone(),
         }
     }
 
     /// Access payment operations.
-    pub fn payments(&self) -> PaymentsResource<'_> {
+    pub fn payments(&self) -> PaymentsResource {
         PaymentsResource {
-            client: self.client,
-            prefix: &self.prefix,
+            client: self.client.clone(),
+            prefix: self.prefix.clone(),
+            policy: self.policy.clone(),
+            approval: self.approval.clone(),
         }
     }
 
     /// Access Lightning address operations.
-    pub fn addresses(&self) -> AddressesResource<'_> {
+    pub fn addresses(&self) -> AddressesResource {
         AddressesResource {
-            client: self.client,
-            prefix: &self.prefix,
+            client: self.client.clone(),
+            prefix: self.prefix.clone(),
         }
     }
 
     /// Access transaction operations.
-    pub fn transactions(&self) -> TransactionsResource<'_> {
+    pub fn transactions(&self) -> TransactionsResource {
         TransactionsResource {
-            client: self.client,
-            prefix: &self.prefix,
+            client: self.client.clone(),
+            prefix: self.prefix.clone(),
         }
     }
 
     /// Access webhook operations.
-    pub fn webhooks(&self) -> WebhooksResource<'_> {
+    pub fn webhooks(&self) -> WebhooksResource {
         WebhooksResource {
-            client: self.client,
-            prefix: &self.prefix,
+            client: self.client.clone(),
+            prefix: self.prefix.clone(),
         }
     }
 
     /// Access the real-time wallet event stream.
-    pub fn events(&self) -> EventsResource<'_> {
+    pub fn events(&self) -> EventsResource {
         EventsResource {
-            client: self.client,
-            prefix: &self.prefix,
+            client: self.client.clone(),
+            prefix: self.prefix.clone(),
         }
     }
 
     /// Access L402 paywall operations.
-    pub fn l402(&self) -> L402Resource<'_> {
+    pub fn l402(&self) -> L402Resource {
         L402Resource {
-            client: self.client,
-            prefix: &self.prefix,
+            client: self.client.clone(),
+            prefix: self.prefix.clone(),
+            policy: self.policy.clone(),
+            approval: self.approval.clone(),
         }
     }
 }
 
 pub(crate) async fn check_status(resp: Response) -> Result<Response, LnBotError> {
-    let status = resp.status().as_u16();
-    if status >= 400 {
-        let body = resp.text().await.unwrap_or_default();
-        return Err(from_status(status, body));
+    if resp.status().as_u16() >= 400 {
+        return Err(error_from(resp).await);
     }
     Ok(resp)
 }
 
 async fn handle_json<T: DeserializeOwned>(resp: Response) -> Result<T, LnBotError> {
-    let status = resp.status().as_u16();
-    if status >= 400 {
-        let body = resp.text().await.unwrap_or_default();
-        return Err(from_status(status, body));
-    }
-    Ok(resp.json().await?)
+    Ok(check_status(resp).await?.json().await?)
 }
 
 async fn handle_empty(resp: Response) -> Result<(), LnBotError> {
-    let status = resp.status().as_u16();
-    if status >= 400 {
-        let body = resp.text().await.unwrap_or_default();
-        return Err(from_status(status, body));
-    }
+    check_status(resp).await?;
     Ok(())
 }
+
+async fn error_from(resp: Response) -> LnBotError {
+    let status = resp.status().as_u16();
+    let headers = resp.headers().clone();
+    let body = resp.text().await.unwrap_or_default();
+    from_status(status, &headers, body)
+}
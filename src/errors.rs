@@ -1,32 +1,168 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde_json::{Map, Value};
 use thiserror::Error;
 
+use crate::amount::{ParseAmountError, Sats};
+use crate::bolt11::Bolt11Error;
+use crate::ids::ParseIdError;
+use crate::l402::L402ParseError;
+use crate::policy::PolicyViolation;
+use crate::preimage::ParseHashError;
+use crate::types::{InvoiceResponse, PaymentResponse};
+use crate::webhooks::WebhookError;
+
 /// Errors returned by the LnBot SDK.
+///
+/// Errors from the API carry the raw response `body` and, when the body is a
+/// JSON error payload, its parsed form as an [`ApiError`]. Use
+/// [`code`](Self::code), [`status`](Self::status) and
+/// [`request_id`](Self::request_id) to inspect them without matching on
+/// every variant.
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum LnBotError {
     /// The server rejected the request as invalid (HTTP 400).
     #[error("Bad Request (400): {body}")]
-    BadRequest { body: String },
+    BadRequest {
+        body: String,
+        error: Option<Box<ApiError>>,
+    },
 
     /// The API key is missing or invalid (HTTP 401).
     #[error("Unauthorized (401): {body}")]
-    Unauthorized { body: String },
+    Unauthorized {
+        body: String,
+        error: Option<Box<ApiError>>,
+    },
 
     /// The API key lacks permission for this operation (HTTP 403).
     #[error("Forbidden (403): {body}")]
-    Forbidden { body: String },
+    Forbidden {
+        body: String,
+        error: Option<Box<ApiError>>,
+    },
 
     /// The requested resource was not found (HTTP 404).
     #[error("Not Found (404): {body}")]
-    NotFound { body: String },
+    NotFound {
+        body: String,
+        error: Option<Box<ApiError>>,
+    },
 
     /// The request conflicted with existing state (HTTP 409).
     #[error("Conflict (409): {body}")]
-    Conflict { body: String },
+    Conflict {
+        body: String,
+        error: Option<Box<ApiError>>,
+    },
+
+    /// Too many requests were sent in a given amount of time (HTTP 429).
+    ///
+    /// `retry_after` is parsed from the `Retry-After` header, which may be
+    /// given either in seconds or as an HTTP date. `limit`, `remaining` and
+    /// `reset` mirror the `X-RateLimit-*` headers as sent by the server.
+    #[error("Too Many Requests (429): {body}")]
+    RateLimited {
+        retry_after: Option<Duration>,
+        limit: Option<u64>,
+        remaining: Option<u64>,
+        reset: Option<u64>,
+        body: String,
+        error: Option<Box<ApiError>>,
+    },
 
     /// An API error with a non-standard status code.
     #[error("API error (HTTP {status}): {body}")]
-    Api { status: u16, body: String },
+    Api {
+        status: u16,
+        body: String,
+        error: Option<Box<ApiError>>,
+    },
+
+    /// The client configuration is invalid.
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    /// A request carried an amount the API would reject, such as a zero or
+    /// negative invoice amount. Nothing was sent.
+    #[error("Invalid {field}: {amount}")]
+    InvalidAmount { field: &'static str, amount: Sats },
+
+    /// An amount string could not be parsed.
+    #[error("Invalid amount: {0}")]
+    ParseAmount(#[from] ParseAmountError),
+
+    /// An identifier such as a wallet ID is malformed. Nothing was sent.
+    #[error("Invalid ID: {0}")]
+    InvalidId(#[from] ParseIdError),
+
+    /// An SSE stream received no data within the configured idle timeout.
+    #[error("SSE stream idle for {0:?}")]
+    SseIdleTimeout(Duration),
+
+    /// A BOLT11 invoice could not be decoded.
+    #[error("Invalid BOLT11 invoice: {0}")]
+    Bolt11(#[from] Bolt11Error),
+
+    /// A payment did not reach a final state before the deadline. Carries
+    /// the last known state of the payment.
+    #[error(
+        "Payment {} did not settle or fail in time (last status: {:?})",
+        payment.number,
+        payment.status
+    )]
+    PaymentTimeout { payment: Box<PaymentResponse> },
+
+    /// An invoice was neither settled nor expired before the deadline.
+    /// Carries the last known state of the invoice.
+    #[error(
+        "Invoice {} was not settled before the deadline (last status: {:?})",
+        invoice.number,
+        invoice.status
+    )]
+    InvoiceTimeout { invoice: Box<InvoiceResponse> },
+
+    /// An L402 challenge asked for more than the configured maximum price.
+    #[error("L402 price of {price} exceeds the limit of {max_price}")]
+    L402PriceExceeded { price: Sats, max_price: Sats },
+
+    /// An L402 challenge could not be paid or its token could not be used.
+    #[error("L402 error: {0}")]
+    L402(String),
+
+    /// An L402 challenge or token could not be parsed.
+    #[error("Invalid L402 header: {0}")]
+    L402Parse(#[from] L402ParseError),
+
+    /// A payment request failed in a way that leaves unknown whether the
+    /// payment was sent. Sending the request again with the same
+    /// `idempotency_key` will not pay twice.
+    #[error("Outcome of payment {idempotency_key} is unknown: {source}")]
+    PaymentOutcomeUnknown {
+        idempotency_key: String,
+        source: Box<LnBotError>,
+    },
+
+    /// A payment was refused by the wallet handle's spending policy before
+    /// anything was sent.
+    #[error("Payment refused by spending policy: {0}")]
+    PolicyViolation(#[from] PolicyViolation),
+
+    /// A payment was denied by the wallet handle's approval handler.
+    #[error("Payment denied: {reason}")]
+    ApprovalDenied { reason: String },
+
+    /// A webhook delivery failed verification.
+    #[error("Invalid webhook: {0}")]
+    Webhook(#[from] WebhookError),
+
+    /// A payment hash or preimage is not 32 bytes of hex.
+    #[error("Invalid payment hash or preimage: {0}")]
+    InvalidHash(#[from] ParseHashError),
 
     /// An HTTP transport error from `reqwest`.
     #[error("HTTP error: {0}")]
@@ -37,13 +173,246 @@ pub enum LnBotError {
     Json(#[from] serde_json::Error),
 }
 
-pub(crate) fn from_status(status: u16, body: String) -> LnBotError {
+impl LnBotError {
+    /// Whether the failure is likely temporary, so that repeating the same
+    /// request or reopening a stream may succeed.
+    pub(crate) fn is_transient(&self) -> bool {
+        match self {
+            Self::Http(_) | Self::SseIdleTimeout(_) | Self::RateLimited { .. } => true,
+            Self::Api { status, .. } => *status >= 500,
+            Self::PaymentOutcomeUnknown { source, .. } => source.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// Whether the API may have processed the request despite the failure.
+    /// A rate-limited request was refused before being processed.
+    pub(crate) fn outcome_unknown(&self) -> bool {
+        match self {
+            Self::Http(_) | Self::SseIdleTimeout(_) | Self::PaymentOutcomeUnknown { .. } => true,
+            Self::Api { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// The HTTP status code, for errors returned by the API.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::BadRequest { .. } => Some(400),
+            Self::Unauthorized { .. } => Some(401),
+            Self::Forbidden { .. } => Some(403),
+            Self::NotFound { .. } => Some(404),
+            Self::Conflict { .. } => Some(409),
+            Self::RateLimited { .. } => Some(429),
+            Self::Api { status, .. } => Some(*status),
+            Self::PaymentOutcomeUnknown { source, .. } => source.status(),
+            _ => None,
+        }
+    }
+
+    /// The parsed error payload, when the API returned one.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::BadRequest { error, .. }
+            | Self::Unauthorized { error, .. }
+            | Self::Forbidden { error, .. }
+            | Self::NotFound { error, .. }
+            | Self::Conflict { error, .. }
+            | Self::RateLimited { error, .. }
+            | Self::Api { error, .. } => error.as_deref(),
+            Self::PaymentOutcomeUnknown { source, .. } => source.api_error(),
+            _ => None,
+        }
+    }
+
+    /// The machine-readable error code sent by the API, if any.
+    pub fn code(&self) -> Option<&ErrorCode> {
+        self.api_error()?.code.as_ref()
+    }
+
+    /// The ID the API assigned to the failed request, for support requests.
+    pub fn request_id(&self) -> Option<&str> {
+        self.api_error()?.request_id.as_deref()
+    }
+
+    /// The idempotency key of a payment whose outcome is unknown.
+    pub fn idempotency_key(&self) -> Option<&str> {
+        match self {
+            Self::PaymentOutcomeUnknown {
+                idempotency_key, ..
+            } => Some(idempotency_key),
+            _ => None,
+        }
+    }
+}
+
+impl From<Infallible> for LnBotError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+pub(crate) fn from_status(status: u16, headers: &HeaderMap, body: String) -> LnBotError {
+    let error = ApiError::parse(&body, headers).map(Box::new);
     match status {
-        400 => LnBotError::BadRequest { body },
-        401 => LnBotError::Unauthorized { body },
-        403 => LnBotError::Forbidden { body },
-        404 => LnBotError::NotFound { body },
-        409 => LnBotError::Conflict { body },
-        _ => LnBotError::Api { status, body },
+        400 => LnBotError::BadRequest { body, error },
+        401 => LnBotError::Unauthorized { body, error },
+        403 => LnBotError::Forbidden { body, error },
+        404 => LnBotError::NotFound { body, error },
+        409 => LnBotError::Conflict { body, error },
+        429 => LnBotError::RateLimited {
+            retry_after: retry_after(headers),
+            limit: rate_limit_header(headers, "limit"),
+            remaining: rate_limit_header(headers, "remaining"),
+            reset: rate_limit_header(headers, "reset"),
+            body,
+            error,
+        },
+        _ => LnBotError::Api {
+            status,
+            body,
+            error,
+        },
+    }
+}
+
+/// An error payload returned by the API.
+///
+/// Parsed from `application/problem+json` bodies (`type`, `title`,
+/// `detail`) as well as plain `{"code": ..., "message": ...}` objects,
+/// optionally nested under `"error"`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ApiError {
+    /// The machine-readable error code, from `code` or the last segment of `type`.
+    pub code: Option<ErrorCode>,
+    /// The human-readable explanation, from `detail`, `message` or `title`.
+    pub message: String,
+    /// Additional information, such as per-field validation errors.
+    pub details: Option<Value>,
+    /// The request ID, from the body or the `X-Request-Id` header.
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    /// Parses a JSON error body. Returns `None` if it is not a JSON object.
+    pub(crate) fn parse(body: &str, headers: &HeaderMap) -> Option<Self> {
+        let root: Map<String, Value> = serde_json::from_str(body).ok()?;
+        let error = match root.get("error") {
+            Some(Value::Object(inner)) => inner,
+            _ => &root,
+        };
+        let text = |obj: &Map<String, Value>, keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| obj.get(*key)?.as_str())
+                .map(str::to_string)
+        };
+
+        let code = text(error, &["code"])
+            .or_else(|| problem_type(error.get("type")?.as_str()?))
+            .map(|code| ErrorCode::from(code.as_str()));
+        let message = text(error, &["detail", "message", "title", "error"]).unwrap_or_default();
+        let details = ["details", "errors"]
+            .iter()
+            .find_map(|key| error.get(*key))
+            .cloned();
+        let request_id = text(error, &["requestId", "request_id", "traceId"])
+            .or_else(|| text(&root, &["requestId", "request_id", "traceId"]))
+            .or_else(|| {
+                let value = headers.get("x-request-id")?.to_str().ok()?.trim();
+                Some(value.to_string())
+            });
+        Some(Self {
+            code,
+            message,
+            details,
+            request_id,
+        })
+    }
+}
+
+/// The code in a problem `type` URI such as `https://ln.bot/errors/invoice-expired`.
+fn problem_type(uri: &str) -> Option<String> {
+    if uri == "about:blank" {
+        return None;
+    }
+    let code = uri.rsplit(['/', '#']).next()?;
+    (!code.is_empty()).then(|| code.to_string())
+}
+
+/// A machine-readable API error code.
+///
+/// Codes are matched case-insensitively, with `-` and `_` treated alike.
+/// Codes this version of the SDK does not know are kept in `Unknown`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    InsufficientBalance,
+    AddressTaken,
+    InvoiceExpired,
+    InvoiceAlreadyPaid,
+    AmountBelowMinimum,
+    AmountAboveMaximum,
+    InvalidTarget,
+    ValidationFailed,
+    Unknown(String),
+}
+
+impl ErrorCode {
+    /// The code in `snake_case`, as sent by the API.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::InsufficientBalance => "insufficient_balance",
+            Self::AddressTaken => "address_taken",
+            Self::InvoiceExpired => "invoice_expired",
+            Self::InvoiceAlreadyPaid => "invoice_already_paid",
+            Self::AmountBelowMinimum => "amount_below_minimum",
+            Self::AmountAboveMaximum => "amount_above_maximum",
+            Self::InvalidTarget => "invalid_target",
+            Self::ValidationFailed => "validation_failed",
+            Self::Unknown(code) => code,
+        }
+    }
+}
+
+impl From<&str> for ErrorCode {
+    fn from(s: &str) -> Self {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "insufficient_balance" => Self::InsufficientBalance,
+            "address_taken" => Self::AddressTaken,
+            "invoice_expired" => Self::InvoiceExpired,
+            "invoice_already_paid" => Self::InvoiceAlreadyPaid,
+            "amount_below_minimum" => Self::AmountBelowMinimum,
+            "amount_above_maximum" => Self::AmountAboveMaximum,
+            "invalid_target" => Self::InvalidTarget,
+            "validation_failed" => Self::ValidationFailed,
+            _ => Self::Unknown(s.to_string()),
+        }
     }
 }
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses the `Retry-After` header, given either as delay-seconds or an HTTP date.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Reads `X-RateLimit-<name>`, falling back to the draft-standard `RateLimit-<name>`.
+fn rate_limit_header(headers: &HeaderMap, name: &str) -> Option<u64> {
+    [
+        format!("x-ratelimit-{}", name),
+        format!("ratelimit-{}", name),
+    ]
+    .iter()
+    .find_map(|h| headers.get(h.as_str())?.to_str().ok()?.trim().parse().ok())
+}
@@ -0,0 +1,315 @@
+//! Typed identifiers and API keys.
+//!
+//! Invoice, payment and transaction numbers are separate types so they
+//! cannot be mixed up, and a [`WalletId`] given by the caller is checked
+//! for its `wal_` prefix:
+//!
+//! ```
+//! use lnbot::{InvoiceNumber, WalletId};
+//!
+//! let id: WalletId = "wal_abc123".parse()?;
+//! assert_eq!(id.as_str(), "wal_abc123");
+//! assert!("abc123".parse::<WalletId>().is_err());
+//! assert_eq!(InvoiceNumber::new(7).to_string(), "7");
+//! # Ok::<(), lnbot::ids::ParseIdError>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::secret::Secret;
+
+const WALLET_ID_PREFIX: &str = "wal_";
+
+/// Error returned when a string is not a valid identifier.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid {kind} {value:?}: {reason}")]
+pub struct ParseIdError {
+    kind: &'static str,
+    value: String,
+    reason: &'static str,
+}
+
+macro_rules! number_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(i32);
+
+        impl $name {
+            pub const fn new(number: i32) -> Self {
+                Self(number)
+            }
+
+            /// The number as a plain integer.
+            pub const fn get(self) -> i32 {
+                self.0
+            }
+        }
+
+        impl From<i32> for $name {
+            fn from(number: i32) -> Self {
+                Self(number)
+            }
+        }
+
+        impl From<$name> for i32 {
+            fn from(number: $name) -> Self {
+                number.0
+            }
+        }
+
+        impl PartialEq<i32> for $name {
+            fn eq(&self, other: &i32) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl PartialEq<$name> for i32 {
+            fn eq(&self, other: &$name) -> bool {
+                *self == other.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+    };
+}
+
+number_type!(
+    /// The number of an invoice within its wallet.
+    InvoiceNumber
+);
+
+number_type!(
+    /// The number of a payment within its wallet.
+    PaymentNumber
+);
+
+number_type!(
+    /// The number of a transaction within its wallet.
+    TxNumber
+);
+
+macro_rules! string_type {
+    ($name:ident) => {
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl PartialEq<$name> for &str {
+            fn eq(&self, other: &$name) -> bool {
+                *self == other.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+    };
+}
+
+/// A wallet ID, such as `wal_abc123`.
+///
+/// IDs parsed from strings must start with `wal_`, followed by letters,
+/// digits, `_` or `-`. IDs returned by the API are taken as they are.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WalletId(String);
+
+string_type!(WalletId);
+
+impl WalletId {
+    /// Checks `id` and wraps it.
+    pub fn new(id: impl Into<String>) -> Result<Self, ParseIdError> {
+        let id = id.into();
+        let reason = match id.strip_prefix(WALLET_ID_PREFIX) {
+            None => "expected a `wal_` prefix",
+            Some("") => "nothing follows the `wal_` prefix",
+            Some(rest)
+                if !rest
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-') =>
+            {
+                "contains characters other than letters, digits, `_` and `-`"
+            }
+            Some(_) => return Ok(Self(id)),
+        };
+        Err(ParseIdError {
+            kind: "wallet ID",
+            value: id,
+            reason,
+        })
+    }
+}
+
+impl FromStr for WalletId {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for WalletId {
+    type Error = ParseIdError;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        Self::new(id)
+    }
+}
+
+impl TryFrom<&str> for WalletId {
+    type Error = ParseIdError;
+
+    fn try_from(id: &str) -> Result<Self, Self::Error> {
+        Self::new(id)
+    }
+}
+
+impl TryFrom<&String> for WalletId {
+    type Error = ParseIdError;
+
+    fn try_from(id: &String) -> Result<Self, Self::Error> {
+        Self::new(id.as_str())
+    }
+}
+
+impl From<&WalletId> for WalletId {
+    fn from(id: &WalletId) -> Self {
+        id.clone()
+    }
+}
+
+/// The ID of a webhook endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WebhookId(String);
+
+string_type!(WebhookId);
+
+impl From<String> for WebhookId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl From<&str> for WebhookId {
+    fn from(id: &str) -> Self {
+        Self(id.to_string())
+    }
+}
+
+impl From<&WebhookId> for WebhookId {
+    fn from(id: &WebhookId) -> Self {
+        id.clone()
+    }
+}
+
+macro_rules! key_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(Secret<String>);
+
+        impl $name {
+            /// Returns the key itself. Avoid logging it.
+            pub fn expose_secret(&self) -> &str {
+                self.0.expose_secret()
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(key: String) -> Self {
+                Self(key.into())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(key: &str) -> Self {
+                Self(key.into())
+            }
+        }
+
+        impl From<Secret<String>> for $name {
+            fn from(key: Secret<String>) -> Self {
+                Self(key)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(
+                    f,
+                    concat!(stringify!($name), "(\"{}***\")"),
+                    key_prefix(self.expose_secret())
+                )
+            }
+        }
+    };
+}
+
+key_type!(
+    /// An API key for an account (`uk_...`) or a wallet (`wk_...`).
+    ///
+    /// `Debug` shows only the key's prefix.
+    ApiKey
+);
+
+key_type!(
+    /// A wallet key (`wk_...`), which grants access to a single wallet.
+    ///
+    /// `Debug` shows only the key's prefix.
+    WalletKey
+);
+
+impl From<WalletKey> for ApiKey {
+    fn from(key: WalletKey) -> Self {
+        Self(key.0)
+    }
+}
+
+/// The part of a key up to and including its first `_`, if that is short.
+fn key_prefix(key: &str) -> &str {
+    match key.find('_') {
+        Some(i) if i < 4 => &key[..=i],
+        _ => "",
+    }
+}
@@ -0,0 +1,548 @@
+//! Client for L402-paywalled HTTP APIs, and with the `tower` feature, a
+//! middleware for selling access to your own.
+//!
+//! [`L402Client`] sends ordinary HTTP requests. When a server answers
+//! `402 Payment Required` with an L402 challenge, it checks the invoice's
+//! price against the configured limits, pays it through
+//! [`L402Resource::pay`](crate::resources::L402Resource::pay) and repeats
+//! the request with the returned `Authorization` header. Tokens are cached
+//! per origin, so later requests to the same API are not paid again, and
+//! concurrent requests challenged by the same origin share one payment.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), lnbot::LnBotError> {
+//! use lnbot::{L402Client, LnBot};
+//!
+//! let w = LnBot::new("uk_...").wallet("wal_...")?;
+//! let client = L402Client::new(w, 100).host_max_price("api.example.com", 500);
+//! let resp = client.get("https://api.example.com/v1/data").await?;
+//! println!("{}", resp.text().await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::{Client, Request, Response, StatusCode, Url};
+
+use crate::amount::Sats;
+use crate::bolt11::{Bolt11Error, Bolt11Invoice};
+use crate::client::{Wallet, USER_AGENT};
+use crate::errors::LnBotError;
+use crate::macaroon::{Macaroon, MacaroonError};
+use crate::preimage::{ParseHashError, Preimage};
+use crate::types::PayL402Request;
+
+#[cfg(feature = "tower")]
+mod tower;
+#[cfg(feature = "tower")]
+pub use self::tower::{L402Layer, L402Price, L402Service, L402Verified};
+
+/// An HTTP client that pays L402 challenges from a wallet, within price limits.
+///
+/// Cheap to clone; clones share the token cache.
+#[derive(Clone)]
+pub struct L402Client {
+    wallet: Wallet,
+    http: Client,
+    max_price: Sats,
+    host_max_price: HashMap<String, Sats>,
+    max_fee: Option<Sats>,
+    token_ttl: Option<Duration>,
+    tokens: Arc<Mutex<HashMap<String, Token>>>,
+    /// Held while paying for an origin, so concurrent challenges from it
+    /// are paid once.
+    payments: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+struct Token {
+    authorization: HeaderValue,
+    expires_at: Option<Instant>,
+}
+
+impl L402Client {
+    /// Creates a client paying from `wallet`, refusing any challenge priced
+    /// above `max_price`.
+    ///
+    /// Requests go through a separate [`reqwest::Client`], so the wallet's
+    /// API key and default headers are never sent to third-party servers.
+    pub fn new(wallet: Wallet, max_price: impl Into<Sats>) -> Self {
+        Self {
+            wallet,
+            http: Client::builder()
+                .user_agent(USER_AGENT)
+                .build()
+                .expect("failed to build HTTP client"),
+            max_price: max_price.into(),
+            host_max_price: HashMap::new(),
+            max_fee: None,
+            token_ttl: None,
+            tokens: Arc::new(Mutex::new(HashMap::new())),
+            payments: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Overrides the maximum price for challenges from `host`.
+    #[must_use]
+    pub fn host_max_price(mut self, host: impl Into<String>, max_price: impl Into<Sats>) -> Self {
+        self.host_max_price
+            .insert(host.into().to_ascii_lowercase(), max_price.into());
+        self
+    }
+
+    /// Sets the maximum routing fee for each L402 payment.
+    #[must_use]
+    pub fn max_fee(mut self, max_fee: impl Into<Sats>) -> Self {
+        self.max_fee = Some(max_fee.into());
+        self
+    }
+
+    /// Discards cached tokens after `ttl`. By default a token is kept until
+    /// the server rejects it with a new challenge, or until a
+    /// `<service>_valid_until` caveat of its macaroon passes.
+    #[must_use]
+    pub fn token_ttl(mut self, ttl: Duration) -> Self {
+        self.token_ttl = Some(ttl);
+        self
+    }
+
+    /// Overrides the [`reqwest::Client`] used for requests to paywalled APIs.
+    #[must_use]
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.http = client;
+        self
+    }
+
+    /// Sends a `GET` request, paying a challenge if one is returned.
+    pub async fn get(&self, url: &str) -> Result<Response, LnBotError> {
+        self.execute(self.http.get(url).build()?).await
+    }
+
+    /// Sends `request`, paying a challenge if one is returned.
+    ///
+    /// Responses other than a `402` with an L402 challenge are returned as
+    /// is, whatever their status. A `402` is also returned as is when the
+    /// request body is a stream that cannot be sent a second time.
+    pub async fn execute(&self, request: Request) -> Result<Response, LnBotError> {
+        self.send(request, None).await
+    }
+
+    /// Sends `request` like [`execute`](Self::execute), refusing a
+    /// challenge priced above `max_price` instead of the configured limits.
+    pub async fn execute_with_max_price(
+        &self,
+        request: Request,
+        max_price: impl Into<Sats>,
+    ) -> Result<Response, LnBotError> {
+        self.send(request, Some(max_price.into())).await
+    }
+
+    /// Forgets all cached tokens.
+    pub fn clear_tokens(&self) {
+        self.tokens().clear();
+    }
+
+    async fn send(
+        &self,
+        mut request: Request,
+        max_price: Option<Sats>,
+    ) -> Result<Response, LnBotError> {
+        let origin = request.url().origin().ascii_serialization();
+        let retry = request.try_clone();
+        let sent = self.cached(&origin);
+        if let Some(authorization) = &sent {
+            request
+                .headers_mut()
+                .insert(AUTHORIZATION, authorization.clone());
+        }
+
+        let resp = self.http.execute(request).await?;
+        if resp.status() != StatusCode::PAYMENT_REQUIRED {
+            return Ok(resp);
+        }
+        let Some(mut retry) = retry else {
+            return Ok(resp);
+        };
+        let Some(challenge) = find_challenge(&resp) else {
+            return Ok(resp);
+        };
+
+        let lock = self.payment_lock(&origin);
+        let paying = lock.lock().await;
+        let authorization = match self.cached(&origin) {
+            // Another request paid for this origin while this one waited.
+            Some(authorization) if sent.as_ref() != Some(&authorization) => authorization,
+            _ => {
+                self.tokens().remove(&origin);
+                let authorization = self.pay(retry.url(), challenge, max_price).await?;
+                let expires_at = self
+                    .token_ttl
+                    .and_then(|ttl| Instant::now().checked_add(ttl))
+                    .into_iter()
+                    .chain(macaroon_expiry(&authorization))
+                    .min();
+                self.tokens().insert(
+                    origin,
+                    Token {
+                        authorization: authorization.clone(),
+                        expires_at,
+                    },
+                );
+                authorization
+            }
+        };
+        drop(paying);
+        retry.headers_mut().insert(AUTHORIZATION, authorization);
+        Ok(self.http.execute(retry).await?)
+    }
+
+    async fn pay(
+        &self,
+        url: &Url,
+        challenge: String,
+        max_price: Option<Sats>,
+    ) -> Result<HeaderValue, LnBotError> {
+        let invoice = L402Challenge::parse(&challenge)?.decode_invoice()?;
+        let price = invoice
+            .amount()
+            .map(|msats| msats.to_sats_ceil())
+            .ok_or_else(|| LnBotError::L402("challenge invoice has no amount".into()))?;
+        let max_price = max_price.unwrap_or_else(|| {
+            url.host_str()
+                .and_then(|host| self.host_max_price.get(&host.to_ascii_lowercase()))
+                .copied()
+                .unwrap_or(self.max_price)
+        });
+        if price > max_price {
+            return Err(LnBotError::L402PriceExceeded { price, max_price });
+        }
+
+        let paid = self
+            .wallet
+            .l402()
+            .pay(&PayL402Request {
+                www_authenticate: challenge,
+                max_fee: self.max_fee,
+                reference: None,
+                wait: Some(true),
+                timeout: None,
+            })
+            .await?;
+        let authorization = paid.authorization.ok_or_else(|| {
+            LnBotError::L402(format!(
+                "payment {} returned no authorization (status: {})",
+                paid.payment_number, paid.status
+            ))
+        })?;
+        let mut authorization = HeaderValue::from_str(authorization.expose_secret())
+            .map_err(|_| LnBotError::L402("authorization is not a valid header value".into()))?;
+        authorization.set_sensitive(true);
+        Ok(authorization)
+    }
+
+    fn cached(&self, origin: &str) -> Option<HeaderValue> {
+        let mut tokens = self.tokens();
+        let token = tokens.get(origin)?;
+        if token.expires_at.is_some_and(|at| at <= Instant::now()) {
+            tokens.remove(origin);
+            return None;
+        }
+        Some(token.authorization.clone())
+    }
+
+    fn payment_lock(&self, origin: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut payments = self
+            .payments
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        payments.entry(origin.to_string()).or_default().clone()
+    }
+
+    fn tokens(&self) -> std::sync::MutexGuard<'_, HashMap<String, Token>> {
+        self.tokens
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl fmt::Debug for L402Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("L402Client")
+            .field("max_price", &self.max_price)
+            .field("host_max_price", &self.host_max_price)
+            .field("max_fee", &self.max_fee)
+            .field("token_ttl", &self.token_ttl)
+            .finish()
+    }
+}
+
+/// Returns when the earliest `<service>_valid_until` caveat of a token's
+/// macaroons passes, if it has one.
+fn macaroon_expiry(authorization: &HeaderValue) -> Option<Instant> {
+    let token = L402Token::parse(authorization.to_str().ok()?).ok()?;
+    let valid_until = token
+        .decode_macaroons()
+        .ok()?
+        .iter()
+        .filter_map(Macaroon::valid_until)
+        .min()?;
+    let remaining = valid_until
+        .duration_since(SystemTime::now())
+        .unwrap_or_default();
+    Instant::now().checked_add(remaining)
+}
+
+/// Returns the first `WWW-Authenticate` value using the `L402` (or legacy
+/// `LSAT`) scheme.
+fn find_challenge(resp: &Response) -> Option<String> {
+    resp.headers()
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| strip_scheme(v).is_some())
+        .map(str::to_string)
+}
+
+/// Strips an `L402` or legacy `LSAT` scheme, returning the credentials.
+pub(crate) fn strip_scheme(header: &str) -> Option<&str> {
+    let header = header.trim();
+    let (scheme, rest) = header
+        .split_once(char::is_whitespace)
+        .unwrap_or((header, ""));
+    (scheme.eq_ignore_ascii_case("L402") || scheme.eq_ignore_ascii_case("LSAT"))
+        .then(|| rest.trim())
+}
+
+/// Error returned when an L402 header cannot be parsed, or a caveat cannot
+/// be built.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum L402ParseError {
+    #[error("not an L402 header")]
+    Scheme,
+
+    #[error("missing {0}")]
+    MissingParameter(&'static str),
+
+    #[error("invalid macaroon: {0}")]
+    Macaroon(#[from] MacaroonError),
+
+    #[error("invalid preimage: {0}")]
+    Preimage(#[from] ParseHashError),
+
+    #[error("invalid caveat key {0:?}: it must be non-empty and must not contain '='")]
+    CaveatKey(String),
+}
+
+/// A parsed `WWW-Authenticate: L402 macaroon="...", invoice="..."` challenge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L402Challenge {
+    /// The base64 macaroon to present once the invoice is paid.
+    pub macaroon: String,
+    /// The BOLT11 invoice to pay.
+    pub invoice: String,
+}
+
+impl L402Challenge {
+    /// Parses a `WWW-Authenticate` header value. Also accepts the legacy
+    /// `LSAT` scheme and `token=` in place of `macaroon=`.
+    pub fn parse(header: &str) -> Result<Self, L402ParseError> {
+        let params = strip_scheme(header).ok_or(L402ParseError::Scheme)?;
+        let param = |names: &[&str]| {
+            params.split(',').find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                let key = key.trim();
+                names
+                    .iter()
+                    .any(|name| key.eq_ignore_ascii_case(name))
+                    .then(|| value.trim().trim_matches('"').to_string())
+            })
+        };
+        Ok(Self {
+            macaroon: param(&["macaroon", "token"])
+                .ok_or(L402ParseError::MissingParameter("macaroon"))?,
+            invoice: param(&["invoice"]).ok_or(L402ParseError::MissingParameter("invoice"))?,
+        })
+    }
+
+    /// Decodes [`macaroon`](Self::macaroon).
+    pub fn decode_macaroon(&self) -> Result<Macaroon, MacaroonError> {
+        Macaroon::decode(&self.macaroon)
+    }
+
+    /// Decodes [`invoice`](Self::invoice).
+    pub fn decode_invoice(&self) -> Result<Bolt11Invoice, Bolt11Error> {
+        Bolt11Invoice::parse(&self.invoice)
+    }
+}
+
+impl FromStr for L402Challenge {
+    type Err = L402ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// A parsed `Authorization: L402 <macaroon>:<preimage>` token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L402Token {
+    /// The base64 macaroons, usually exactly one.
+    pub macaroons: Vec<String>,
+    /// The preimage proving the challenge invoice was paid.
+    pub preimage: Preimage,
+}
+
+impl L402Token {
+    /// Parses an `Authorization` header value. Also accepts the legacy `LSAT`
+    /// scheme and several comma-separated macaroons.
+    pub fn parse(header: &str) -> Result<Self, L402ParseError> {
+        let credentials = strip_scheme(header).ok_or(L402ParseError::Scheme)?;
+        let (macaroons, preimage) = credentials
+            .rsplit_once(':')
+            .ok_or(L402ParseError::MissingParameter("preimage"))?;
+        let macaroons: Vec<String> = macaroons
+            .split(',')
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(str::to_string)
+            .collect();
+        if macaroons.is_empty() {
+            return Err(L402ParseError::MissingParameter("macaroon"));
+        }
+        Ok(Self {
+            macaroons,
+            preimage: preimage.parse()?,
+        })
+    }
+
+    /// Decodes every macaroon.
+    pub fn decode_macaroons(&self) -> Result<Vec<Macaroon>, MacaroonError> {
+        self.macaroons.iter().map(|m| Macaroon::decode(m)).collect()
+    }
+
+    /// Checks offline that the preimage unlocks the payment hash in the first
+    /// macaroon's identifier. Returns `Ok(false)` when the identifier carries
+    /// no payment hash.
+    pub fn verify_preimage(&self) -> Result<bool, L402ParseError> {
+        let macaroon = self
+            .macaroons
+            .first()
+            .ok_or(L402ParseError::MissingParameter("macaroon"))?;
+        let macaroon = Macaroon::decode(macaroon)?;
+        Ok(macaroon
+            .payment_hash()
+            .is_some_and(|hash| self.preimage.verify(&hash)))
+    }
+}
+
+impl FromStr for L402Token {
+    type Err = L402ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for L402Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L402 {}:{}", self.macaroons.join(","), self.preimage)
+    }
+}
+
+/// Builder for [`CreateL402ChallengeRequest::caveats`](crate::CreateL402ChallengeRequest::caveats).
+///
+/// Caveats are `key=value` conditions. The service helpers follow the
+/// conventions of Lightning Labs' Aperture proxy.
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use lnbot::l402::Caveats;
+///
+/// let caveats = Caveats::new()
+///     .services(&[("api", 0)])
+///     .capabilities("api", &["read"])
+///     .valid_until("api", SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+///     .condition("tier", "premium")
+///     .build()?;
+/// assert_eq!(caveats, [
+///     "services=api:0",
+///     "api_capabilities=read",
+///     "api_valid_until=1700000000",
+///     "tier=premium",
+/// ]);
+/// # Ok::<(), lnbot::l402::L402ParseError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Caveats {
+    conditions: Vec<String>,
+    invalid_key: Option<String>,
+}
+
+impl Caveats {
+    /// Creates an empty list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a `key=value` condition.
+    ///
+    /// A `key` that is empty or contains `=` makes [`build`](Self::build)
+    /// fail.
+    #[must_use]
+    pub fn condition(mut self, key: &str, value: impl fmt::Display) -> Self {
+        if key.is_empty() || key.contains('=') {
+            self.invalid_key.get_or_insert_with(|| key.to_string());
+        } else {
+            self.conditions.push(format!("{}={}", key, value));
+        }
+        self
+    }
+
+    /// Restricts the token to services, each with a pricing tier.
+    #[must_use]
+    pub fn services(self, services: &[(&str, u32)]) -> Self {
+        let value = services
+            .iter()
+            .map(|(name, tier)| format!("{}:{}", name, tier))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.condition("services", value)
+    }
+
+    /// Restricts the token to the given capabilities of `service`.
+    #[must_use]
+    pub fn capabilities(self, service: &str, capabilities: &[&str]) -> Self {
+        self.condition(&format!("{}_capabilities", service), capabilities.join(","))
+    }
+
+    /// Makes the token valid for `service` until `at`.
+    #[must_use]
+    pub fn valid_until(self, service: &str, at: SystemTime) -> Self {
+        let secs = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.condition(&format!("{}_valid_until", service), secs)
+    }
+
+    /// Returns the caveats, or an error naming the first invalid key.
+    pub fn build(self) -> Result<Vec<String>, L402ParseError> {
+        match self.invalid_key {
+            Some(key) => Err(L402ParseError::CaveatKey(key)),
+            None => Ok(self.conditions),
+        }
+    }
+}
+
+impl TryFrom<Caveats> for Vec<String> {
+    type Error = L402ParseError;
+
+    fn try_from(caveats: Caveats) -> Result<Self, Self::Error> {
+        caveats.build()
+    }
+}
@@ -0,0 +1,274 @@
+//! [tower] middleware, enabled with the `tower` feature.
+
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use ::tower::{Layer, Service};
+use futures_util::future::BoxFuture;
+use http::header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use http::{Request, Response, StatusCode};
+
+use crate::amount::Sats;
+use crate::client::Wallet;
+use crate::preimage::PaymentHash;
+use crate::secret::Secret;
+use crate::types::{CreateL402ChallengeRequest, VerifyL402Request};
+
+/// The price and terms of access to a route.
+#[derive(Debug, Clone)]
+pub struct L402Price {
+    amount: Sats,
+    description: Option<String>,
+    expiry_seconds: Option<i32>,
+    caveats: Vec<String>,
+}
+
+impl L402Price {
+    /// Charges `amount` sats per token.
+    pub fn new(amount: impl Into<Sats>) -> Self {
+        Self {
+            amount: amount.into(),
+            description: None,
+            expiry_seconds: None,
+            caveats: Vec::new(),
+        }
+    }
+
+    /// Sets the description of the challenge invoice.
+    #[must_use]
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets how long the challenge stays payable.
+    #[must_use]
+    pub fn expiry_seconds(mut self, seconds: i32) -> Self {
+        self.expiry_seconds = Some(seconds);
+        self
+    }
+
+    /// Adds a caveat to issued tokens. Tokens presented for this route must
+    /// carry every caveat it was priced with.
+    #[must_use]
+    pub fn caveat(mut self, caveat: impl Into<String>) -> Self {
+        self.caveats.push(caveat.into());
+        self
+    }
+
+    fn challenge_request(&self) -> CreateL402ChallengeRequest {
+        CreateL402ChallengeRequest {
+            amount: self.amount,
+            description: self.description.clone(),
+            expiry_seconds: self.expiry_seconds,
+            caveats: (!self.caveats.is_empty()).then(|| self.caveats.clone()),
+        }
+    }
+}
+
+/// Request extension added by [`L402Service`] once a token is verified.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct L402Verified {
+    /// Hash of the payment that bought the token.
+    pub payment_hash: Option<PaymentHash>,
+    /// Caveats the token carries.
+    pub caveats: Vec<String>,
+}
+
+/// [`Layer`] that puts routes behind an L402 paywall.
+///
+/// Requests without a valid `Authorization: L402 ...` header are answered
+/// with `402 Payment Required` and a fresh challenge from
+/// [`create_challenge`](crate::resources::L402Resource::create_challenge).
+/// Tokens are checked with [`verify`](crate::resources::L402Resource::verify),
+/// and the result is available to handlers as an [`L402Verified`] extension.
+/// Tokens the API rejects, even with an error, are answered with a new
+/// challenge. If the API cannot be reached or fails with a server error,
+/// requests are answered with `503 Service Unavailable`.
+///
+/// Prices apply by path prefix, matched on whole segments: `/premium` covers
+/// `/premium` and `/premium/...` but not `/premiumfoo`. The longest matching
+/// prefix wins, and requests matching no route are charged the default
+/// price. Since a token is not tied to a route, give routes with different
+/// prices distinct caveats so that a cheap token cannot unlock an expensive
+/// route.
+///
+/// ```no_run
+/// use lnbot::l402::{L402Layer, L402Price, L402Service};
+/// use lnbot::Wallet;
+/// use tower::Layer;
+///
+/// fn paywalled<S>(service: S, w: Wallet) -> L402Service<S> {
+///     L402Layer::new(w, L402Price::new(10))
+///         .route("/premium", L402Price::new(100).caveat("tier=premium"))
+///         .free("/health")
+///         .layer(service)
+/// }
+/// ```
+#[derive(Clone)]
+pub struct L402Layer {
+    config: Arc<Config>,
+}
+
+#[derive(Clone)]
+struct Config {
+    wallet: Wallet,
+    default: Option<L402Price>,
+    routes: Vec<(String, Option<L402Price>)>,
+}
+
+impl L402Layer {
+    /// Charges `price` for every request, unless a route overrides it.
+    pub fn new(wallet: Wallet, price: L402Price) -> Self {
+        Self::with_default(wallet, Some(price))
+    }
+
+    /// Leaves requests free, unless a route sets a price.
+    pub fn free_by_default(wallet: Wallet) -> Self {
+        Self::with_default(wallet, None)
+    }
+
+    fn with_default(wallet: Wallet, default: Option<L402Price>) -> Self {
+        Self {
+            config: Arc::new(Config {
+                wallet,
+                default,
+                routes: Vec::new(),
+            }),
+        }
+    }
+
+    /// Charges `price` for `prefix` and the paths below it.
+    #[must_use]
+    pub fn route(self, prefix: impl Into<String>, price: L402Price) -> Self {
+        self.with_route(prefix.into(), Some(price))
+    }
+
+    /// Leaves `prefix` and the paths below it free.
+    #[must_use]
+    pub fn free(self, prefix: impl Into<String>) -> Self {
+        self.with_route(prefix.into(), None)
+    }
+
+    fn with_route(mut self, prefix: String, price: Option<L402Price>) -> Self {
+        Arc::make_mut(&mut self.config).routes.push((prefix, price));
+        self
+    }
+}
+
+impl Config {
+    fn price(&self, path: &str) -> Option<&L402Price> {
+        self.routes
+            .iter()
+            .filter(|(prefix, _)| covers(prefix, path))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default.as_ref(), |(_, price)| price.as_ref())
+    }
+}
+
+/// Whether `prefix` is `path` or one of its parent segments.
+fn covers(prefix: &str, path: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'))
+}
+
+impl<S> Layer<S> for L402Layer {
+    type Service = L402Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        L402Service {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Service produced by [`L402Layer`].
+#[derive(Clone)]
+pub struct L402Service<S> {
+    inner: S,
+    config: Arc<Config>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for L402Service<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        // The clone may not be ready; call the instance that was polled.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+        Box::pin(async move {
+            let Some(price) = config.price(req.uri().path()) else {
+                return inner.call(req).await;
+            };
+            let authorization = req
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .filter(|v| super::strip_scheme(v).is_some())
+                .map(Secret::from);
+
+            if let Some(authorization) = authorization {
+                let verified = config
+                    .wallet
+                    .l402()
+                    .verify(&VerifyL402Request { authorization })
+                    .await;
+                match verified {
+                    Ok(v) if v.valid => {
+                        let caveats = v.caveats.unwrap_or_default();
+                        if price.caveats.iter().all(|c| caveats.contains(c)) {
+                            req.extensions_mut().insert(L402Verified {
+                                payment_hash: v.payment_hash.and_then(|h| h.parse().ok()),
+                                caveats,
+                            });
+                            return inner.call(req).await;
+                        }
+                    }
+                    Err(e) if e.is_transient() => {
+                        return Ok(status(StatusCode::SERVICE_UNAVAILABLE))
+                    }
+                    // The API rejected the token itself: challenge as if it were missing.
+                    Ok(_) | Err(_) => {}
+                }
+            }
+
+            let challenge = config
+                .wallet
+                .l402()
+                .create_challenge(&price.challenge_request())
+                .await;
+            let header = challenge
+                .ok()
+                .and_then(|c| HeaderValue::from_str(&c.www_authenticate).ok());
+            Ok(match header {
+                Some(header) => {
+                    let mut resp = status(StatusCode::PAYMENT_REQUIRED);
+                    resp.headers_mut().insert(WWW_AUTHENTICATE, header);
+                    resp
+                }
+                None => status(StatusCode::SERVICE_UNAVAILABLE),
+            })
+        })
+    }
+}
+
+fn status<B: Default>(status: StatusCode) -> Response<B> {
+    let mut resp = Response::new(B::default());
+    *resp.status_mut() = status;
+    resp
+}
@@ -13,18 +13,46 @@
 //! use lnbot::{LnBot, CreateInvoiceRequest};
 //!
 //! let client = LnBot::new("uk_...");
-//! let w = client.wallet("wal_...");
+//! let w = client.wallet("wal_...")?;
 //! let invoice = w.invoices().create(&CreateInvoiceRequest::new(1000)).await?;
 //! println!("{}", invoice.bolt11);
 //! # Ok(())
 //! # }
 //! ```
 
+pub mod amount;
+pub mod approval;
+pub mod bolt11;
+pub mod builder;
 pub mod client;
 pub mod errors;
+pub mod ids;
+pub mod l402;
+pub mod macaroon;
+pub mod pagination;
+pub mod policy;
+pub mod preimage;
 pub mod resources;
+pub mod retry;
+mod rfc3339;
+pub mod secret;
+pub mod sse;
+pub mod timestamp;
 pub mod types;
+pub mod webhooks;
 
-pub use client::{LnBot, Wallet};
-pub use errors::LnBotError;
+pub use amount::{MilliSats, ParseAmountError, Sats};
+pub use bolt11::{Bolt11Error, Bolt11Invoice};
+pub use builder::LnBotBuilder;
+pub use client::{LnBot, Wallet, WalletHandle};
+pub use errors::{ApiError, ErrorCode, LnBotError};
+pub use ids::{ApiKey, InvoiceNumber, PaymentNumber, TxNumber, WalletId, WalletKey, WebhookId};
+pub use l402::L402Client;
+pub use pagination::{ListAllParams, Paginator};
+pub use policy::{PolicyViolation, SpendingPolicy};
+pub use preimage::{PaymentHash, Preimage};
+pub use retry::{Jitter, ReconnectPolicy, RetryPolicy};
+pub use secret::Secret;
+pub use sse::{ConnectionState, SseEvent, StreamEvent};
+pub use timestamp::{DateTime, ParseTimestampError, Timestamp};
 pub use types::*;
@@ -0,0 +1,390 @@
+//! Offline macaroon decoding.
+//!
+//! L402 tokens are [macaroons](https://research.google/pubs/pub41892/):
+//! bearer credentials carrying an identifier, a list of caveats restricting
+//! their use, and a chained HMAC signature. [`Macaroon::decode`] reads both
+//! the binary V2 format and the legacy V1 format, from standard or URL-safe
+//! base64, so tokens can be inspected without calling the API. It does not
+//! check the signature, which requires the minting server's root key.
+//!
+//! # Examples
+//!
+//! ```
+//! use lnbot::macaroon::Macaroon;
+//!
+//! let mac = Macaroon::decode("AgEIbG9jYXRpb24CAmlkAAIOc2VydmljZXM9YXBpOjAAAAYgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA")?;
+//! assert_eq!(mac.location.as_deref(), Some("location"));
+//! assert_eq!(mac.caveats[0].condition(), Some(("services", "api:0")));
+//! # Ok::<(), lnbot::macaroon::MacaroonError>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+
+use crate::preimage::PaymentHash;
+
+const LENIENT: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const STANDARD_LENIENT: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, LENIENT);
+const URL_SAFE_LENIENT: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, LENIENT);
+
+const V2: u8 = 2;
+const FIELD_EOS: u8 = 0;
+const FIELD_LOCATION: u8 = 1;
+const FIELD_IDENTIFIER: u8 = 2;
+const FIELD_VID: u8 = 4;
+const FIELD_SIGNATURE: u8 = 6;
+
+/// Error returned when a macaroon cannot be decoded.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum MacaroonError {
+    #[error("invalid base64")]
+    InvalidBase64,
+
+    #[error("unsupported format (first byte {0:#04x})")]
+    UnsupportedFormat(u8),
+
+    #[error("unexpected end of data")]
+    Truncated,
+
+    #[error("unexpected field {0}")]
+    UnexpectedField(String),
+
+    #[error("missing identifier")]
+    MissingIdentifier,
+
+    #[error("signature must be 32 bytes, got {0}")]
+    InvalidSignature(usize),
+
+    #[error("{0} bytes of trailing data")]
+    TrailingData(usize),
+}
+
+/// A decoded macaroon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Macaroon {
+    /// Hint to where the macaroon can be used. Not signed.
+    pub location: Option<String>,
+    /// Identifier chosen by the minting server.
+    pub identifier: Vec<u8>,
+    /// Caveats restricting the macaroon's use, in order.
+    pub caveats: Vec<Caveat>,
+    /// Signature over the identifier and all caveats.
+    pub signature: [u8; 32],
+}
+
+/// A caveat attached to a [`Macaroon`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Caveat {
+    /// Location of the discharging service, for third-party caveats.
+    pub location: Option<String>,
+    /// The condition, or for third-party caveats, its identifier.
+    pub identifier: Vec<u8>,
+    /// Verification ID, set only on third-party caveats.
+    pub verification_id: Option<Vec<u8>>,
+}
+
+impl Caveat {
+    /// Whether the caveat is checked by the minting server itself.
+    pub fn is_first_party(&self) -> bool {
+        self.verification_id.is_none()
+    }
+
+    /// Returns the condition as text, if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.identifier).ok()
+    }
+
+    /// Splits a first-party `key=value` condition.
+    pub fn condition(&self) -> Option<(&str, &str)> {
+        if !self.is_first_party() {
+            return None;
+        }
+        let (key, value) = self.as_str()?.split_once('=')?;
+        Some((key.trim(), value.trim()))
+    }
+}
+
+impl fmt::Display for Caveat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_str() {
+            Some(s) => f.write_str(s),
+            None => f.write_str(&hex::encode(&self.identifier)),
+        }
+    }
+}
+
+impl Macaroon {
+    /// Decodes a base64 macaroon, standard or URL-safe, with or without padding.
+    pub fn decode(s: &str) -> Result<Self, MacaroonError> {
+        let s = s.trim();
+        let engine = if s.contains(['-', '_']) {
+            URL_SAFE_LENIENT
+        } else {
+            STANDARD_LENIENT
+        };
+        let bytes = engine.decode(s).map_err(|_| MacaroonError::InvalidBase64)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Decodes a macaroon from its binary form.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MacaroonError> {
+        match bytes.first() {
+            Some(&V2) => decode_v2(&bytes[1..]),
+            Some(b) if b.is_ascii_hexdigit() => decode_v1(bytes),
+            Some(&b) => Err(MacaroonError::UnsupportedFormat(b)),
+            None => Err(MacaroonError::Truncated),
+        }
+    }
+
+    /// Encodes the macaroon in the binary V2 format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![V2];
+        if let Some(location) = &self.location {
+            put_field(&mut out, FIELD_LOCATION, location.as_bytes());
+        }
+        put_field(&mut out, FIELD_IDENTIFIER, &self.identifier);
+        out.push(FIELD_EOS);
+        for caveat in &self.caveats {
+            if let Some(location) = &caveat.location {
+                put_field(&mut out, FIELD_LOCATION, location.as_bytes());
+            }
+            put_field(&mut out, FIELD_IDENTIFIER, &caveat.identifier);
+            if let Some(vid) = &caveat.verification_id {
+                put_field(&mut out, FIELD_VID, vid);
+            }
+            out.push(FIELD_EOS);
+        }
+        out.push(FIELD_EOS);
+        put_field(&mut out, FIELD_SIGNATURE, &self.signature);
+        out
+    }
+
+    /// Encodes the macaroon as padded standard base64 of the V2 format.
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.to_bytes())
+    }
+
+    /// Returns the payment hash from an L402 identifier: a big-endian `u16`
+    /// version of zero, the 32-byte payment hash and a 32-byte token ID.
+    pub fn payment_hash(&self) -> Option<PaymentHash> {
+        match self.identifier.as_slice() {
+            [0, 0, rest @ ..] if rest.len() == 64 => {
+                let hash: [u8; 32] = rest[..32].try_into().ok()?;
+                Some(PaymentHash::from_bytes(hash))
+            }
+            _ => None,
+        }
+    }
+
+    /// Iterates over the `key=value` conditions of first-party caveats.
+    pub fn conditions(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.caveats.iter().filter_map(Caveat::condition)
+    }
+
+    /// Returns the earliest expiry set by a `<service>_valid_until` caveat,
+    /// in Unix seconds as minted by Aperture. Unparsable values are ignored.
+    pub fn valid_until(&self) -> Option<SystemTime> {
+        self.conditions()
+            .filter(|(key, _)| key.ends_with("_valid_until"))
+            .filter_map(|(_, value)| value.parse().ok())
+            .filter_map(|secs| UNIX_EPOCH.checked_add(Duration::from_secs(secs)))
+            .min()
+    }
+}
+
+impl FromStr for Macaroon {
+    type Err = MacaroonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::decode(s)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, MacaroonError> {
+        let (&b, rest) = self.data.split_first().ok_or(MacaroonError::Truncated)?;
+        self.data = rest;
+        Ok(b)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    fn varint(&mut self) -> Result<usize, MacaroonError> {
+        let mut value: usize = 0;
+        for shift in (0..usize::BITS).step_by(7) {
+            let b = self.byte()?;
+            value |= usize::from(b & 0x7f)
+                .checked_shl(shift)
+                .ok_or(MacaroonError::Truncated)?;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(MacaroonError::Truncated)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MacaroonError> {
+        if len > self.data.len() {
+            return Err(MacaroonError::Truncated);
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    /// Reads a field of type `ty` if it is next, otherwise leaves the input alone.
+    fn optional(&mut self, ty: u8) -> Result<Option<&'a [u8]>, MacaroonError> {
+        if self.peek() != Some(ty) {
+            return Ok(None);
+        }
+        self.byte()?;
+        let len = self.varint()?;
+        self.take(len).map(Some)
+    }
+
+    fn eos(&mut self) -> Result<(), MacaroonError> {
+        match self.byte()? {
+            FIELD_EOS => Ok(()),
+            other => Err(MacaroonError::UnexpectedField(other.to_string())),
+        }
+    }
+}
+
+fn decode_v2(data: &[u8]) -> Result<Macaroon, MacaroonError> {
+    let mut r = Reader { data };
+    let location = r.optional(FIELD_LOCATION)?.map(utf8_lossy);
+    let identifier = r
+        .optional(FIELD_IDENTIFIER)?
+        .ok_or(MacaroonError::MissingIdentifier)?
+        .to_vec();
+    r.eos()?;
+
+    let mut caveats = Vec::new();
+    while r.peek() != Some(FIELD_EOS) {
+        let location = r.optional(FIELD_LOCATION)?.map(utf8_lossy);
+        let identifier = r
+            .optional(FIELD_IDENTIFIER)?
+            .ok_or(MacaroonError::MissingIdentifier)?
+            .to_vec();
+        let verification_id = r.optional(FIELD_VID)?.map(<[u8]>::to_vec);
+        r.eos()?;
+        caveats.push(Caveat {
+            location,
+            identifier,
+            verification_id,
+        });
+    }
+    r.eos()?;
+
+    let signature = r
+        .optional(FIELD_SIGNATURE)?
+        .ok_or(MacaroonError::InvalidSignature(0))?;
+    let signature = signature
+        .try_into()
+        .map_err(|_| MacaroonError::InvalidSignature(signature.len()))?;
+    if !r.data.is_empty() {
+        return Err(MacaroonError::TrailingData(r.data.len()));
+    }
+    Ok(Macaroon {
+        location,
+        identifier,
+        caveats,
+        signature,
+    })
+}
+
+/// Decodes the V1 format: packets of a 4-digit hex length followed by
+/// `key value\n`, where the length covers the whole packet.
+fn decode_v1(mut data: &[u8]) -> Result<Macaroon, MacaroonError> {
+    let mut location = None;
+    let mut identifier = None;
+    let mut caveats: Vec<Caveat> = Vec::new();
+    let mut signature = None;
+
+    while !data.is_empty() {
+        let len = data
+            .get(..4)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| usize::from_str_radix(h, 16).ok())
+            .ok_or(MacaroonError::Truncated)?;
+        if len < 6 || len > data.len() {
+            return Err(MacaroonError::Truncated);
+        }
+        let (packet, rest) = data.split_at(len);
+        data = rest;
+        let packet = packet[4..]
+            .strip_suffix(b"\n")
+            .ok_or(MacaroonError::Truncated)?;
+        let space = packet
+            .iter()
+            .position(|&b| b == b' ')
+            .ok_or(MacaroonError::Truncated)?;
+        let (key, value) = (&packet[..space], &packet[space + 1..]);
+
+        match key {
+            b"location" => location = Some(utf8_lossy(value)),
+            b"identifier" => identifier = Some(value.to_vec()),
+            b"cid" => caveats.push(Caveat {
+                location: None,
+                identifier: value.to_vec(),
+                verification_id: None,
+            }),
+            b"vid" | b"cl" => {
+                let caveat = caveats
+                    .last_mut()
+                    .ok_or_else(|| MacaroonError::UnexpectedField(utf8_lossy(key)))?;
+                if key == b"vid" {
+                    caveat.verification_id = Some(value.to_vec());
+                } else {
+                    caveat.location = Some(utf8_lossy(value));
+                }
+            }
+            b"signature" => {
+                signature = Some(
+                    value
+                        .try_into()
+                        .map_err(|_| MacaroonError::InvalidSignature(value.len()))?,
+                )
+            }
+            _ => return Err(MacaroonError::UnexpectedField(utf8_lossy(key))),
+        }
+    }
+
+    Ok(Macaroon {
+        location,
+        identifier: identifier.ok_or(MacaroonError::MissingIdentifier)?,
+        caveats,
+        signature: signature.ok_or(MacaroonError::InvalidSignature(0))?,
+    })
+}
+
+fn put_field(out: &mut Vec<u8>, ty: u8, data: &[u8]) {
+    out.push(ty);
+    let mut len = data.len();
+    while len >= 0x80 {
+        out.push((len as u8 & 0x7f) | 0x80);
+        len >>= 7;
+    }
+    out.push(len as u8);
+    out.extend_from_slice(data);
+}
+
+fn utf8_lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
@@ -0,0 +1,232 @@
+//! Lazily paginated listings.
+//!
+//! The `list_all` and `iter` methods on the invoices, payments and
+//! transactions resources return a [`Paginator`], a [`Stream`] that fetches
+//! pages with the `after` cursor as it is consumed.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+
+use futures_core::Stream;
+
+use crate::errors::LnBotError;
+use crate::timestamp::ApiTime;
+use crate::types::{InvoiceResponse, ListParams, PaymentResponse, TransactionResponse};
+
+/// Options for [`Paginator`]. Listings are returned newest first.
+///
+/// # Examples
+///
+/// ```
+/// use lnbot::ListAllParams;
+///
+/// // Everything newer than number 120, fetched 50 at a time, at most 500 items.
+/// let params = ListAllParams::default()
+///     .page_size(50)
+///     .until_number(120)
+///     .max_items(500);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ListAllParams {
+    /// Items requested per page. Uses the server's default when `None`.
+    pub page_size: Option<i32>,
+    /// Cursor to start after, typically a previous [`Paginator::cursor`].
+    pub after: Option<i32>,
+    /// Stop after yielding this many items.
+    pub max_items: Option<usize>,
+    /// Stop at the first item whose number is at or below this one.
+    pub until_number: Option<i32>,
+    /// Stop at the first item created at or before this time.
+    pub since: Option<SystemTime>,
+}
+
+impl ListAllParams {
+    #[must_use]
+    pub fn page_size(mut self, page_size: i32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    #[must_use]
+    pub fn after(mut self, after: i32) -> Self {
+        self.after = Some(after);
+        self
+    }
+
+    #[must_use]
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    #[must_use]
+    pub fn until_number(mut self, number: i32) -> Self {
+        self.until_number = Some(number);
+        self
+    }
+
+    #[must_use]
+    pub fn since(mut self, time: SystemTime) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    fn stops_at<T: PageItem>(&self, item: &T) -> bool {
+        if matches!(self.until_number, Some(n) if item.number() <= n) {
+            return true;
+        }
+        match (self.since, item.created_at()) {
+            (Some(since), Some(created)) => created <= since,
+            _ => false,
+        }
+    }
+}
+
+/// An item of a paginated listing.
+pub trait PageItem {
+    /// The item's number, used as the `after` cursor.
+    fn number(&self) -> i32;
+    /// The item's creation time, if known.
+    fn created_at(&self) -> Option<SystemTime>;
+}
+
+macro_rules! impl_page_item {
+    ($($ty:ty),*) => {$(
+        impl PageItem for $ty {
+            fn number(&self) -> i32 {
+                self.number.get()
+            }
+
+            fn created_at(&self) -> Option<SystemTime> {
+                self.created_at.as_ref().map(ApiTime::system_time)
+            }
+        }
+    )*};
+}
+
+impl_page_item!(InvoiceResponse, PaymentResponse, TransactionResponse);
+
+type PageFuture<T> = Pin<Box<dyn Future<Output = Result<Vec<T>, LnBotError>> + Send + Sync>>;
+type FetchPage<T> = Box<dyn Fn(ListParams) -> PageFuture<T> + Send + Sync>;
+
+/// A [`Stream`] over every item of a listing, fetching pages on demand.
+///
+/// The stream ends when the listing is exhausted, a bound from
+/// [`ListAllParams`] is reached, or a request fails. In every case
+/// [`cursor`](Paginator::cursor) tells where it stopped, so a later job can
+/// resume with [`ListAllParams::after`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn example(w: lnbot::Wallet) -> Result<(), lnbot::LnBotError> {
+/// use futures_util::StreamExt;
+/// use lnbot::ListAllParams;
+///
+/// let mut invoices = w.invoices().list_all(ListAllParams::default().page_size(100));
+/// while let Some(invoice) = invoices.next().await {
+///     println!("{}", invoice?.number);
+/// }
+/// let resume_from = invoices.cursor();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Paginator<T> {
+    fetch: FetchPage<T>,
+    params: ListAllParams,
+    cursor: Option<i32>,
+    buffer: VecDeque<T>,
+    pending: Option<PageFuture<T>>,
+    yielded: usize,
+    last_page: bool,
+    done: bool,
+}
+
+impl<T> Paginator<T> {
+    pub(crate) fn new<F>(params: ListAllParams, fetch: F) -> Self
+    where
+        F: Fn(ListParams) -> PageFuture<T> + Send + Sync + 'static,
+    {
+        Self {
+            fetch: Box::new(fetch),
+            cursor: params.after,
+            params,
+            buffer: VecDeque::new(),
+            pending: None,
+            yielded: 0,
+            last_page: false,
+            done: false,
+        }
+    }
+
+    /// The number of the last item yielded, or the starting cursor when
+    /// nothing has been yielded yet. Pass it to [`ListAllParams::after`] to
+    /// continue from here.
+    pub fn cursor(&self) -> Option<i32> {
+        self.cursor
+    }
+
+    fn finish(&mut self) {
+        self.done = true;
+        self.buffer.clear();
+        self.pending = None;
+    }
+}
+
+// No field is ever pinned in place: pages are buffered by value and the
+// in-flight request is boxed.
+impl<T> Unpin for Paginator<T> {}
+
+impl<T: PageItem> Stream for Paginator<T> {
+    type Item = Result<T, LnBotError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.done || this.params.max_items.is_some_and(|max| this.yielded >= max) {
+                this.finish();
+                return Poll::Ready(None);
+            }
+
+            if let Some(item) = this.buffer.pop_front() {
+                if this.params.stops_at(&item) {
+                    this.finish();
+                    return Poll::Ready(None);
+                }
+                this.cursor = Some(item.number());
+                this.yielded += 1;
+                return Poll::Ready(Some(Ok(item)));
+            }
+
+            if this.last_page {
+                this.finish();
+                return Poll::Ready(None);
+            }
+
+            let page = ListParams {
+                limit: this.params.page_size,
+                after: this.cursor,
+            };
+            let fetch = &this.fetch;
+            let pending = this.pending.get_or_insert_with(|| fetch(page));
+            match pending.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => {
+                    this.finish();
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Ready(Ok(items)) => {
+                    this.pending = None;
+                    this.last_page = items.is_empty()
+                        || this.params.page_size.is_some_and(|size| {
+                            usize::try_from(size).is_ok_and(|size| items.len() < size)
+                        });
+                    this.buffer.extend(items);
+                }
+            }
+        }
+    }
+}
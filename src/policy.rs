@@ -0,0 +1,468 @@
+//! Client-side spending guardrails.
+//!
+//! A [`SpendingPolicy`] attached to a wallet handle with
+//! [`Wallet::with_spending_policy`](crate::Wallet::with_spending_policy) is
+//! checked by [`PaymentsResource::create`](crate::resources::PaymentsResource::create)
+//! and [`L402Resource::pay`](crate::resources::L402Resource::pay) before any
+//! request is sent. A payment breaking a rule fails with
+//! [`LnBotError::PolicyViolation`].
+//!
+//! Checks run offline: amounts and payees are read from the BOLT11 invoice,
+//! and Lightning address domains from the target itself. A requested amount
+//! that differs from the invoice's is refused. Budgets count each
+//! payment's amount plus its `max_fee`, and are tracked by a [`BudgetStore`],
+//! in memory by default.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), lnbot::LnBotError> {
+//! use lnbot::policy::SpendingPolicy;
+//! use lnbot::{CreatePaymentRequest, LnBot, LnBotError};
+//!
+//! let policy = SpendingPolicy::new()
+//!     .max_per_payment(1_000)
+//!     .hourly_budget(5_000)
+//!     .daily_budget(20_000)
+//!     .max_fee_ratio(0.01)
+//!     .deny_domain("casino.example");
+//! let w = LnBot::new("uk_...").wallet("wal_...")?.with_spending_policy(policy);
+//!
+//! match w.payments().create(&CreatePaymentRequest::new("alice@ln.bot").amount(5_000)).await {
+//!     Err(LnBotError::PolicyViolation(v)) => eprintln!("blocked: {}", v),
+//!     other => println!("{:?}", other),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use futures_util::future::BoxFuture;
+use futures_util::lock::Mutex as AsyncMutex;
+
+use crate::amount::Sats;
+use crate::bolt11::Bolt11Invoice;
+use crate::errors::LnBotError;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A rule broken by a payment.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum PolicyViolation {
+    #[error("amount of {amount} exceeds the per-payment limit of {limit}")]
+    AmountAboveLimit { amount: Sats, limit: Sats },
+
+    #[error("{amount} would exceed the budget of {limit} per {window:?} ({spent} spent)")]
+    BudgetExceeded {
+        window: Duration,
+        spent: Sats,
+        amount: Sats,
+        limit: Sats,
+    },
+
+    #[error("max fee of {max_fee} is more than {ratio} of {amount}")]
+    FeeRatioExceeded {
+        max_fee: Sats,
+        amount: Sats,
+        ratio: f64,
+    },
+
+    #[error("a max fee is required")]
+    MaxFeeRequired,
+
+    #[error("the payment amount could not be determined")]
+    UnknownAmount,
+
+    #[error("requested amount of {requested} differs from the invoice amount of {invoice}")]
+    AmountMismatch { requested: Sats, invoice: Sats },
+
+    #[error("target {0} is denied")]
+    TargetDenied(String),
+
+    #[error("target {0} is not in the allow list")]
+    TargetNotAllowed(String),
+}
+
+/// A payment counted against budgets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spend {
+    /// Unique ID, used to release the spend if the payment fails.
+    pub id: u64,
+    /// Amount plus fee cap.
+    pub amount: Sats,
+    /// When the payment was attempted.
+    pub at: SystemTime,
+}
+
+/// Storage for spends counted against rolling budgets.
+///
+/// Implement it to keep budgets in a database or share them between
+/// processes. Within a process, a [`SpendingPolicy`] serializes the check
+/// and the [`record`](BudgetStore::record) so concurrent payments cannot
+/// both pass the same budget; across processes, the store must provide
+/// that guarantee itself if needed.
+pub trait BudgetStore: Send + Sync + 'static {
+    /// Returns the total amount of spends made at or after `since`.
+    fn spent_since(&self, since: SystemTime) -> BoxFuture<'_, Result<Sats, LnBotError>>;
+
+    /// Records a spend, before the payment is sent.
+    fn record(&self, spend: Spend) -> BoxFuture<'_, Result<(), LnBotError>>;
+
+    /// Removes a recorded spend whose payment failed.
+    fn release(&self, id: u64) -> BoxFuture<'_, Result<(), LnBotError>>;
+}
+
+/// In-memory [`BudgetStore`]. Spends are forgotten once they are older
+/// than every budget window queried so far, and when the process exits.
+#[derive(Debug, Default)]
+pub struct MemoryBudgetStore {
+    inner: Mutex<MemoryState>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    spends: VecDeque<Spend>,
+    lookback: Duration,
+}
+
+impl MemoryBudgetStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl BudgetStore for MemoryBudgetStore {
+    fn spent_since(&self, since: SystemTime) -> BoxFuture<'_, Result<Sats, LnBotError>> {
+        let mut state = self.state();
+        let lookback = SystemTime::now().duration_since(since).unwrap_or_default();
+        state.lookback = state.lookback.max(lookback);
+        let spent = state
+            .spends
+            .iter()
+            .filter(|s| s.at >= since)
+            .map(|s| s.amount)
+            .fold(Sats::ZERO, Sats::saturating_add);
+        Box::pin(async move { Ok(spent) })
+    }
+
+    fn record(&self, spend: Spend) -> BoxFuture<'_, Result<(), LnBotError>> {
+        let mut state = self.state();
+        if let Some(cutoff) = spend.at.checked_sub(state.lookback) {
+            while state.spends.front().is_some_and(|s| s.at < cutoff) {
+                state.spends.pop_front();
+            }
+        }
+        state.spends.push_back(spend);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn release(&self, id: u64) -> BoxFuture<'_, Result<(), LnBotError>> {
+        self.state().spends.retain(|s| s.id != id);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Limits on what a wallet handle may spend. See the [module docs](self).
+///
+/// Deny lists take precedence over allow lists. Once any allow rule is set,
+/// only targets matching one are accepted; targets whose payee cannot be
+/// determined offline, such as LNURLs, are then refused.
+#[derive(Clone)]
+pub struct SpendingPolicy {
+    max_per_payment: Option<Sats>,
+    budgets: Vec<(Duration, Sats)>,
+    max_fee_ratio: Option<f64>,
+    allow_domains: HashSet<String>,
+    deny_domains: HashSet<String>,
+    allow_nodes: HashSet<String>,
+    deny_nodes: HashSet<String>,
+    store: Arc<dyn BudgetStore>,
+    lock: Arc<AsyncMutex<()>>,
+}
+
+impl Default for SpendingPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpendingPolicy {
+    /// Creates a policy without any limits, backed by a [`MemoryBudgetStore`].
+    pub fn new() -> Self {
+        Self {
+            max_per_payment: None,
+            budgets: Vec::new(),
+            max_fee_ratio: None,
+            allow_domains: HashSet::new(),
+            deny_domains: HashSet::new(),
+            allow_nodes: HashSet::new(),
+            deny_nodes: HashSet::new(),
+            store: Arc::new(MemoryBudgetStore::new()),
+            lock: Arc::new(AsyncMutex::new(())),
+        }
+    }
+
+    /// Refuses single payments above `sats`.
+    #[must_use]
+    pub fn max_per_payment(mut self, sats: impl Into<Sats>) -> Self {
+        self.max_per_payment = Some(sats.into());
+        self
+    }
+
+    /// Limits spending to `sats` over any rolling hour.
+    #[must_use]
+    pub fn hourly_budget(self, sats: impl Into<Sats>) -> Self {
+        self.budget(HOUR, sats)
+    }
+
+    /// Limits spending to `sats` over any rolling 24 hours.
+    #[must_use]
+    pub fn daily_budget(self, sats: impl Into<Sats>) -> Self {
+        self.budget(DAY, sats)
+    }
+
+    /// Limits spending to `sats` over any rolling `window`.
+    #[must_use]
+    pub fn budget(mut self, window: Duration, sats: impl Into<Sats>) -> Self {
+        self.budgets.push((window, sats.into()));
+        self
+    }
+
+    /// Requires every payment to set a `max_fee` of at most `ratio` times
+    /// its amount, e.g. `0.01` for 1%.
+    #[must_use]
+    pub fn max_fee_ratio(mut self, ratio: f64) -> Self {
+        self.max_fee_ratio = Some(ratio);
+        self
+    }
+
+    /// Allows payments to Lightning addresses at `domain`.
+    #[must_use]
+    pub fn allow_domain(mut self, domain: impl Into<String>) -> Self {
+        self.allow_domains
+            .insert(domain.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Refuses payments to Lightning addresses at `domain`.
+    #[must_use]
+    pub fn deny_domain(mut self, domain: impl Into<String>) -> Self {
+        self.deny_domains.insert(domain.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Allows payments to invoices issued by the node with this hex public key.
+    #[must_use]
+    pub fn allow_node(mut self, pubkey: impl Into<String>) -> Self {
+        self.allow_nodes.insert(pubkey.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Refuses payments to invoices issued by the node with this hex public key.
+    #[must_use]
+    pub fn deny_node(mut self, pubkey: impl Into<String>) -> Self {
+        self.deny_nodes.insert(pubkey.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Keeps budget state in `store` instead of in memory.
+    #[must_use]
+    pub fn budget_store(mut self, store: impl BudgetStore) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Checks a payment to `target` and records it against budgets.
+    ///
+    /// An amount encoded in a BOLT11 target is what the payment charges, so
+    /// it is used, and a different requested `amount` is refused. Otherwise
+    /// `amount` is used, if any. The returned [`Reservation`] must be
+    /// released if the payment fails.
+    pub(crate) async fn reserve(
+        &self,
+        target: &str,
+        amount: Option<Sats>,
+        max_fee: Option<Sats>,
+    ) -> Result<Reservation, LnBotError> {
+        let target = Target::classify(target);
+        self.check_target(&target)?;
+
+        let amount = match (amount, target.amount) {
+            (Some(requested), Some(invoice)) if requested != invoice => {
+                return Err(PolicyViolation::AmountMismatch { requested, invoice }.into());
+            }
+            (amount, None) => amount,
+            (_, invoice) => invoice,
+        };
+        let needs_amount = self.max_per_payment.is_some()
+            || self.max_fee_ratio.is_some()
+            || !self.budgets.is_empty();
+        if amount.is_none() && needs_amount {
+            return Err(PolicyViolation::UnknownAmount.into());
+        }
+        let amount = amount.unwrap_or_default().max(Sats::ZERO);
+
+        if let Some(limit) = self.max_per_payment {
+            if amount > limit {
+                return Err(PolicyViolation::AmountAboveLimit { amount, limit }.into());
+            }
+        }
+        if let Some(ratio) = self.max_fee_ratio {
+            let max_fee = max_fee.ok_or(PolicyViolation::MaxFeeRequired)?;
+            if max_fee.get() as f64 > amount.get() as f64 * ratio {
+                return Err(PolicyViolation::FeeRatioExceeded {
+                    max_fee,
+                    amount,
+                    ratio,
+                }
+                .into());
+            }
+        }
+        if self.budgets.is_empty() {
+            return Ok(Reservation::none());
+        }
+
+        let cost = amount.saturating_add(max_fee.unwrap_or_default());
+        let _guard = self.lock.lock().await;
+        let now = SystemTime::now();
+        for &(window, limit) in &self.budgets {
+            let since = now.checked_sub(window).unwrap_or(SystemTime::UNIX_EPOCH);
+            let spent = self.store.spent_since(since).await?;
+            if spent.saturating_add(cost) > limit {
+                return Err(PolicyViolation::BudgetExceeded {
+                    window,
+                    spent,
+                    amount: cost,
+                    limit,
+                }
+                .into());
+            }
+        }
+        let id = fastrand::u64(..);
+        self.store
+            .record(Spend {
+                id,
+                amount: cost,
+                at: now,
+            })
+            .await?;
+        Ok(Reservation {
+            store: Some(self.store.clone()),
+            id,
+        })
+    }
+
+    fn check_target(&self, target: &Target) -> Result<(), PolicyViolation> {
+        let (denied, allowed) = match &target.payee {
+            Payee::Domain(domain) => (
+                self.deny_domains.contains(domain),
+                self.allow_domains.contains(domain),
+            ),
+            Payee::Node(pubkey) => (
+                self.deny_nodes.contains(pubkey),
+                self.allow_nodes.contains(pubkey),
+            ),
+            Payee::Unknown => (false, false),
+        };
+        if denied {
+            return Err(PolicyViolation::TargetDenied(target.payee.to_string()));
+        }
+        let has_allow_list = !self.allow_domains.is_empty() || !self.allow_nodes.is_empty();
+        if has_allow_list && !allowed {
+            return Err(PolicyViolation::TargetNotAllowed(target.payee.to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SpendingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpendingPolicy")
+            .field("max_per_payment", &self.max_per_payment)
+            .field("budgets", &self.budgets)
+            .field("max_fee_ratio", &self.max_fee_ratio)
+            .field("allow_domains", &self.allow_domains)
+            .field("deny_domains", &self.deny_domains)
+            .field("allow_nodes", &self.allow_nodes)
+            .field("deny_nodes", &self.deny_nodes)
+            .finish()
+    }
+}
+
+/// A spend recorded against budgets, to release if the payment fails.
+pub(crate) struct Reservation {
+    store: Option<Arc<dyn BudgetStore>>,
+    id: u64,
+}
+
+impl Reservation {
+    pub(crate) fn none() -> Self {
+        Self { store: None, id: 0 }
+    }
+
+    /// Releases the spend. Failures are ignored: a budget that stays
+    /// charged errs on the safe side.
+    pub(crate) async fn release(self) {
+        if let Some(store) = self.store {
+            let _ = store.release(self.id).await;
+        }
+    }
+}
+
+struct Target {
+    payee: Payee,
+    amount: Option<Sats>,
+}
+
+enum Payee {
+    Domain(String),
+    Node(String),
+    Unknown,
+}
+
+impl fmt::Display for Payee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Domain(domain) => f.write_str(domain),
+            Self::Node(pubkey) => f.write_str(pubkey),
+            Self::Unknown => f.write_str("(unknown payee)"),
+        }
+    }
+}
+
+impl Target {
+    fn classify(target: &str) -> Self {
+        let target = target.trim();
+        let target = match target.get(..10) {
+            Some(scheme) if scheme.eq_ignore_ascii_case("lightning:") => &target[10..],
+            _ => target,
+        };
+        if let Ok(invoice) = Bolt11Invoice::parse(target) {
+            return Self {
+                payee: Payee::Node(hex::encode(invoice.payee_pubkey)),
+                amount: invoice.amount().map(|msats| msats.to_sats_ceil()),
+            };
+        }
+        let payee = match target.rsplit_once('@') {
+            Some((_, domain)) if !domain.is_empty() => Payee::Domain(domain.to_ascii_lowercase()),
+            _ => Payee::Unknown,
+        };
+        Self {
+            payee,
+            amount: None,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Payment hashes and preimages.
+//!
+//! A Lightning payment is proven by its preimage: the 32 bytes whose SHA-256
+//! digest is the payment hash committed to in the invoice. [`Preimage::verify`]
+//! checks that relation locally, without trusting the API.
+//!
+//! # Examples
+//!
+//! ```
+//! use lnbot::{PaymentHash, Preimage};
+//!
+//! let preimage: Preimage = "0000000000000000000000000000000000000000000000000000000000000000".parse()?;
+//! let hash: PaymentHash = "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925".parse()?;
+//! assert!(preimage.verify(&hash));
+//! # Ok::<(), lnbot::preimage::ParseHashError>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+/// Error returned when a payment hash or preimage is not 32 bytes of hex.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ParseHashError {
+    #[error("invalid hex: {0}")]
+    InvalidHex(String),
+
+    #[error("expected 32 bytes, got {0}")]
+    InvalidLength(usize),
+}
+
+macro_rules! hash32 {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name([u8; 32]);
+
+        impl $name {
+            /// Wraps raw bytes.
+            pub const fn from_bytes(bytes: [u8; 32]) -> Self {
+                Self(bytes)
+            }
+
+            /// Returns the raw bytes.
+            pub const fn as_bytes(&self) -> &[u8; 32] {
+                &self.0
+            }
+
+            /// Returns the lowercase hex encoding.
+            pub fn to_hex(&self) -> String {
+                hex::encode(self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseHashError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let bytes = hex::decode(s.trim())
+                    .map_err(|e| ParseHashError::InvalidHex(e.to_string()))?;
+                let bytes: [u8; 32] = bytes
+                    .try_into()
+                    .map_err(|b: Vec<u8>| ParseHashError::InvalidLength(b.len()))?;
+                Ok(Self(bytes))
+            }
+        }
+
+        impl From<[u8; 32]> for $name {
+            fn from(bytes: [u8; 32]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.to_hex())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.to_hex())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+hash32!(
+    /// The SHA-256 hash identifying a Lightning payment.
+    PaymentHash
+);
+
+hash32!(
+    /// The secret revealed to the payer when a payment settles.
+    ///
+    /// It proves payment, and completes an L402 token, so its `Debug` output
+    /// is redacted. `Display` and [`to_hex`](Self::to_hex) show the value.
+    Preimage
+);
+
+impl fmt::Debug for PaymentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PaymentHash({})", self.to_hex())
+    }
+}
+
+impl fmt::Debug for Preimage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Preimage([REDACTED])")
+    }
+}
+
+impl Preimage {
+    /// Computes the payment hash this preimage unlocks.
+    pub fn payment_hash(&self) -> PaymentHash {
+        PaymentHash(Sha256::digest(self.0).into())
+    }
+
+    /// Returns `true` if this preimage hashes to `hash`.
+    pub fn verify(&self, hash: &PaymentHash) -> bool {
+        self.payment_hash() == *hash
+    }
+}
@@ -1,14 +1,16 @@
 use crate::client::LnBot;
 use crate::errors::LnBotError;
 use crate::types::*;
+use std::sync::Arc;
 
 /// Wallet-scoped Lightning address operations.
-pub struct AddressesResource<'a> {
-    pub(crate) client: &'a LnBot,
-    pub(crate) prefix: &'a str,
+#[derive(Clone)]
+pub struct AddressesResource {
+    pub(crate) client: LnBot,
+    pub(crate) prefix: Arc<str>,
 }
 
-impl AddressesResource<'_> {
+impl AddressesResource {
     /// Creates or claims a Lightning address.
     pub async fn create(&self, req: &CreateAddressRequest) -> Result<AddressResponse, LnBotError> {
         self.client
@@ -3,11 +3,12 @@ use crate::errors::LnBotError;
 use crate::types::*;
 
 /// Operations for backing up wallet credentials.
-pub struct BackupResource<'a> {
-    pub(crate) client: &'a LnBot,
+#[derive(Clone)]
+pub struct BackupResource {
+    pub(crate) client: LnBot,
 }
 
-impl BackupResource<'_> {
+impl BackupResource {
     /// Returns the wallet's recovery passphrase.
     pub async fn recovery(&self) -> Result<RecoveryBackupResponse, LnBotError> {
         self.client
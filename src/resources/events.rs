@@ -1,50 +1,128 @@
-use crate::client::{check_status, LnBot};
+use crate::client::LnBot;
 use crate::errors::LnBotError;
-use crate::types::WalletEvent;
+use crate::retry::ReconnectPolicy;
+use crate::sse::{ConnectionState, SseDecoder, StreamEvent};
+use crate::types::{TypedWalletEvent, WalletEvent};
 use futures_core::Stream;
 use futures_util::StreamExt;
 use std::pin::Pin;
+use std::sync::Arc;
 
 /// Wallet-scoped real-time event stream.
-pub struct EventsResource<'a> {
-    pub(crate) client: &'a LnBot,
-    pub(crate) prefix: &'a str,
+#[derive(Clone)]
+pub struct EventsResource {
+    pub(crate) client: LnBot,
+    pub(crate) prefix: Arc<str>,
 }
 
-impl EventsResource<'_> {
+impl EventsResource {
     /// Opens an SSE stream of all wallet events.
     pub fn stream(
         &self,
-    ) -> Pin<Box<dyn Stream<Item = Result<WalletEvent, LnBotError>> + Send + '_>> {
-        let url = format!("{}{}/events", self.client.base_url, self.prefix);
+    ) -> Pin<Box<dyn Stream<Item = Result<WalletEvent, LnBotError>> + Send + Sync>> {
+        let url = self.client.url(&format!("{}/events", self.prefix));
+        let client = self.client.clone();
         Box::pin(async_stream::try_stream! {
-            let mut req = self.client.http.get(&url).header("Accept", "text/event-stream");
-            if let Some(ref key) = self.client.api_key {
-                req = req.bearer_auth(key);
+            let mut events = Box::pin(client.sse_events(&url).await?);
+            while let Some(event) = events.next().await {
+                let event = event?;
+                if event.data.trim().is_empty() {
+                    continue;
+                }
+                let event: WalletEvent = serde_json::from_str(&event.data)?;
+                yield event;
             }
+        })
+    }
 
-            let resp = check_status(req.send().await?).await?;
-
-            let mut buffer = String::new();
-            let mut stream = resp.bytes_stream();
-
-            while let Some(chunk) = stream.next().await {
-                let chunk = chunk?;
-                buffer.push_str(&String::from_utf8_lossy(&chunk));
-
-                while let Some(pos) = buffer.find('\n') {
-                    let line = buffer[..pos].to_string();
-                    buffer.drain(..=pos);
-
-                    if let Some(value) = line.strip_prefix("data:") {
-                        let raw = value.trim();
-                        if !raw.is_empty() {
-                            let event: WalletEvent = serde_json::from_str(raw)?;
-                            yield event;
+    /// Opens an SSE stream of all wallet events that reconnects on its own.
+    ///
+    /// When the connection drops, the stream waits according to `policy`
+    /// (honouring the server's `retry:` hint) and reconnects with a
+    /// `Last-Event-ID` header, so that events missed in between are
+    /// replayed. Connection changes are reported as
+    /// [`StreamEvent::State`] items alongside the events.
+    ///
+    /// Transient failures (transport errors, idle timeouts, HTTP 429 and 5xx)
+    /// trigger a reconnect. Any other error, or exhausting
+    /// `policy.max_retries`, yields [`ConnectionState::GaveUp`] followed by
+    /// the error, and ends the stream. An event whose payload cannot be
+    /// parsed is yielded as an error without closing the connection.
+    pub fn stream_reconnecting(
+        &self,
+        policy: ReconnectPolicy,
+    ) -> Pin<Box<dyn Stream<Item = Result<StreamEvent<WalletEvent>, LnBotError>> + Send + Sync>>
+    {
+        let url = self.client.url(&format!("{}/events", self.prefix));
+        let client = self.client.clone();
+        Box::pin(async_stream::stream! {
+            let mut decoder = SseDecoder::new();
+            let mut attempt = 0;
+            loop {
+                let error = match client.sse_bytes(&url, decoder.last_event_id()).await {
+                    Ok(bytes) => {
+                        attempt = 0;
+                        yield Ok(StreamEvent::State(ConnectionState::Connected));
+                        decoder.reset();
+                        let mut bytes = Box::pin(bytes);
+                        let mut error = None;
+                        while let Some(chunk) = bytes.next().await {
+                            let chunk = match chunk {
+                                Ok(chunk) => chunk,
+                                Err(e) => {
+                                    error = Some(e);
+                                    break;
+                                }
+                            };
+                            for event in decoder.feed(&chunk) {
+                                if event.data.trim().is_empty() {
+                                    continue;
+                                }
+                                yield serde_json::from_str::<WalletEvent>(&event.data)
+                                    .map(StreamEvent::Event)
+                                    .map_err(LnBotError::from);
+                            }
                         }
+                        error
                     }
+                    Err(e) => Some(e),
+                };
+
+                attempt += 1;
+                let exhausted = policy.max_retries.is_some_and(|max| attempt > max);
+                if exhausted || error.as_ref().is_some_and(|e| !e.is_transient()) {
+                    yield Ok(StreamEvent::State(ConnectionState::GaveUp));
+                    if let Some(e) = error {
+                        yield Err(e);
+                    }
+                    break;
                 }
+
+                let delay = policy.delay_for(attempt, decoder.retry());
+                yield Ok(StreamEvent::State(ConnectionState::Reconnecting { attempt, delay }));
+                tokio::time::sleep(delay).await;
             }
         })
     }
+
+    /// Like [`stream`](EventsResource::stream), with payloads parsed into
+    /// [`WalletEventKind`](crate::WalletEventKind).
+    pub fn typed_stream(
+        &self,
+    ) -> Pin<Box<dyn Stream<Item = Result<TypedWalletEvent, LnBotError>> + Send + Sync>> {
+        Box::pin(self.stream().map(|event| Ok(event?.into_typed()?)))
+    }
+
+    /// Like [`stream_reconnecting`](EventsResource::stream_reconnecting), with
+    /// payloads parsed into [`WalletEventKind`](crate::WalletEventKind).
+    pub fn typed_stream_reconnecting(
+        &self,
+        policy: ReconnectPolicy,
+    ) -> Pin<Box<dyn Stream<Item = Result<StreamEvent<TypedWalletEvent>, LnBotError>> + Send + Sync>>
+    {
+        Box::pin(self.stream_reconnecting(policy).map(|item| match item? {
+            StreamEvent::Event(event) => Ok(StreamEvent::Event(event.into_typed()?)),
+            StreamEvent::State(state) => Ok(StreamEvent::State(state)),
+        }))
+    }
 }
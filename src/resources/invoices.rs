@@ -1,19 +1,29 @@
-use crate::client::{check_status, LnBot};
+use super::POLL_INTERVAL;
+use crate::client::LnBot;
 use crate::errors::LnBotError;
+use crate::ids::InvoiceNumber;
+use crate::pagination::{ListAllParams, Paginator};
+use crate::sse::{SseEvent, DEFAULT_EVENT_TYPE};
+use crate::timestamp::ApiTime;
 use crate::types::*;
 use futures_core::Stream;
 use futures_util::StreamExt;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::time::Instant;
 
 /// Wallet-scoped invoice operations.
-pub struct InvoicesResource<'a> {
-    pub(crate) client: &'a LnBot,
-    pub(crate) prefix: &'a str,
+#[derive(Clone)]
+pub struct InvoicesResource {
+    pub(crate) client: LnBot,
+    pub(crate) prefix: Arc<str>,
 }
 
-impl InvoicesResource<'_> {
+impl InvoicesResource {
     /// Creates a new invoice.
     pub async fn create(&self, req: &CreateInvoiceRequest) -> Result<InvoiceResponse, LnBotError> {
+        req.validate()?;
         self.client
             .post(&format!("{}/invoices", self.prefix), Some(req))
             .await
@@ -26,27 +36,140 @@ impl InvoicesResource<'_> {
             .await
     }
 
-    /// Gets an invoice by its number.
-    pub async fn get(&self, number: i32) -> Result<InvoiceResponse, LnBotError> {
-        self.client
-            .get(&format!("{}/invoices/{}", self.prefix, number))
-            .await
+    /// Streams every invoice, newest first, fetching pages lazily.
+    pub fn list_all(&self, params: ListAllParams) -> Paginator<InvoiceResponse> {
+        let client = self.client.clone();
+        let path = format!("{}/invoices", self.prefix);
+        Paginator::new(params, move |page| {
+            let client = client.clone();
+            let path = path.clone();
+            Box::pin(async move { client.get_with_params(&path, &page).await })
+        })
+    }
+
+    /// Streams every invoice with the default [`ListAllParams`].
+    pub fn iter(&self) -> Paginator<InvoiceResponse> {
+        self.list_all(ListAllParams::default())
+    }
+
+    /// Gets an invoice by its number or payment hash.
+    pub async fn get(&self, invoice: impl Into<InvoiceRef>) -> Result<InvoiceResponse, LnBotError> {
+        self.get_ref(&invoice.into()).await
     }
 
-    /// Returns a specific invoice by its payment hash.
+    /// Returns a specific invoice by its payment hash. Same as [`get`](Self::get)
+    /// with a hash.
     pub async fn get_by_hash(&self, payment_hash: &str) -> Result<InvoiceResponse, LnBotError> {
+        self.get(payment_hash).await
+    }
+
+    /// Waits until an invoice is settled or expired and returns its final state.
+    ///
+    /// Follows the invoice over SSE, checking it once more after subscribing
+    /// in case it settled before the stream opened, and falls back to polling
+    /// if the stream drops. Once the invoice's `expires_at` has passed, it is fetched one
+    /// last time without waiting for the rest of `timeout`. If it is still
+    /// not settled or expired then, or when `timeout` elapses first,
+    /// [`LnBotError::InvoiceTimeout`] carries the last known state.
+    pub async fn wait(
+        &self,
+        invoice: impl Into<InvoiceRef>,
+        timeout: Duration,
+    ) -> Result<InvoiceResponse, LnBotError> {
+        let invoice = invoice.into();
+        let deadline = Instant::now() + timeout;
+        let mut last = self.get_ref(&invoice).await?;
+        if last.status.is_terminal() {
+            return Ok(last);
+        }
+
+        let expires_at = last
+            .expires_at
+            .as_ref()
+            .map(|at| Instant::now() + at.time_left(SystemTime::now()));
+        let stop = expires_at.map_or(deadline, |at| at.min(deadline));
+
+        let remaining = stop.saturating_duration_since(Instant::now());
+        let secs = i32::try_from(remaining.as_secs_f64().ceil() as u64)
+            .unwrap_or(i32::MAX)
+            .max(1);
+        let path = format!("{}/invoices/{}/events", self.prefix, invoice);
+        let dropped = match tokio::time::timeout_at(stop, self.subscribe(&path, Some(secs))).await {
+            Err(_) => false,
+            // The stream could not be opened; poll instead.
+            Ok(Err(_)) => true,
+            Ok(Ok(mut events)) => {
+                // An invoice settled before the stream opened sends no event,
+                // so check once now that later changes will be seen.
+                if let Ok(Ok(invoice)) = tokio::time::timeout_at(stop, self.get_ref(&invoice)).await
+                {
+                    last = invoice;
+                    if last.status.is_terminal() {
+                        return Ok(last);
+                    }
+                }
+                loop {
+                    match tokio::time::timeout_at(stop, events.next()).await {
+                        Err(_) => break false,
+                        Ok(Some(Ok(event))) => {
+                            last = event.data;
+                            if last.status.is_terminal() {
+                                return Ok(last);
+                            }
+                        }
+                        // The stream ended or failed without a final event.
+                        Ok(Some(Err(_)) | None) => break true,
+                    }
+                }
+            }
+        };
+
+        if dropped {
+            while Instant::now() < stop {
+                match tokio::time::timeout_at(stop, self.get_ref(&invoice)).await {
+                    Ok(Ok(invoice)) => {
+                        last = invoice;
+                        if last.status.is_terminal() {
+                            return Ok(last);
+                        }
+                    }
+                    Ok(Err(e)) if !e.is_transient() => return Err(e),
+                    _ => {}
+                }
+                tokio::time::sleep_until(stop.min(Instant::now() + POLL_INTERVAL)).await;
+            }
+        }
+
+        if expires_at.is_some_and(|at| at <= deadline) {
+            match tokio::time::timeout_at(deadline, self.get_ref(&invoice)).await {
+                Ok(Ok(invoice)) => {
+                    last = invoice;
+                    if last.status.is_terminal() {
+                        return Ok(last);
+                    }
+                }
+                Ok(Err(e)) if !e.is_transient() => return Err(e),
+                _ => {}
+            }
+        }
+        Err(LnBotError::InvoiceTimeout {
+            invoice: Box::new(last),
+        })
+    }
+
+    async fn get_ref(&self, invoice: &InvoiceRef) -> Result<InvoiceResponse, LnBotError> {
         self.client
-            .get(&format!("{}/invoices/{}", self.prefix, payment_hash))
+            .get(&format!("{}/invoices/{}", self.prefix, invoice))
             .await
     }
 
     /// Returns a stream of real-time events for an invoice via Server-Sent Events.
     pub fn watch(
         &self,
-        number: i32,
+        number: impl Into<InvoiceNumber>,
         timeout: Option<i32>,
-    ) -> Pin<Box<dyn Stream<Item = Result<InvoiceEvent, LnBotError>> + Send + '_>> {
-        let path = format!("{}/invoices/{}/events", self.prefix, number);
+    ) -> Pin<Box<dyn Stream<Item = Result<InvoiceEvent, LnBotError>> + Send + Sync>> {
+        let path = format!("{}/invoices/{}/events", self.prefix, number.into());
         self.sse_stream(&path, timeout)
     }
 
@@ -55,7 +178,7 @@ impl InvoicesResource<'_> {
         &self,
         payment_hash: &str,
         timeout: Option<i32>,
-    ) -> Pin<Box<dyn Stream<Item = Result<InvoiceEvent, LnBotError>> + Send + '_>> {
+    ) -> Pin<Box<dyn Stream<Item = Result<InvoiceEvent, LnBotError>> + Send + Sync>> {
         let path = format!("{}/invoices/{}/events", self.prefix, payment_hash);
         self.sse_stream(&path, timeout)
     }
@@ -64,62 +187,77 @@ impl InvoicesResource<'_> {
         &self,
         path: &str,
         timeout: Option<i32>,
-    ) -> Pin<Box<dyn Stream<Item = Result<InvoiceEvent, LnBotError>> + Send + '_>> {
-        let mut url = format!("{}{}", self.client.base_url, path);
-        if let Some(t) = timeout {
-            url = format!("{}?timeout={}", url, t);
-        }
+    ) -> Pin<Box<dyn Stream<Item = Result<InvoiceEvent, LnBotError>> + Send + Sync>> {
+        let url = self.events_url(path, timeout);
+        let client = self.client.clone();
         Box::pin(async_stream::try_stream! {
-            let mut req = self.client.http.get(&url).header("Accept", "text/event-stream");
-            if let Some(ref key) = self.client.api_key {
-                req = req.bearer_auth(key);
-            }
-
-            let resp = check_status(req.send().await?).await?;
-
-            let mut event_type = String::new();
-            let mut buffer = String::new();
-            let mut stream = resp.bytes_stream();
-
-            while let Some(chunk) = stream.next().await {
-                let chunk = chunk?;
-                buffer.push_str(&String::from_utf8_lossy(&chunk));
-
-                while let Some(pos) = buffer.find('\n') {
-                    let line = buffer[..pos].to_string();
-                    buffer.drain(..=pos);
-
-                    if let Some(value) = line.strip_prefix("event:") {
-                        event_type = value.trim().to_string();
-                    } else if let Some(value) = line.strip_prefix("data:") {
-                        let raw = value.trim();
-                        if !raw.is_empty() && !event_type.is_empty() {
-                            let data: InvoiceResponse = serde_json::from_str(raw)?;
-                            yield InvoiceEvent {
-                                event: InvoiceEventType::from(event_type.as_str()),
-                                data,
-                            };
-                            event_type.clear();
-                        }
-                    }
-                }
+            let mut events = invoice_events(client.sse_events(&url).await?);
+            while let Some(event) = events.next().await {
+                yield event?;
             }
         })
     }
+
+    /// Like [`sse_stream`](Self::sse_stream), but connects before returning,
+    /// so no event sent afterwards is missed.
+    async fn subscribe(
+        &self,
+        path: &str,
+        timeout: Option<i32>,
+    ) -> Result<
+        Pin<Box<dyn Stream<Item = Result<InvoiceEvent, LnBotError>> + Send + Sync>>,
+        LnBotError,
+    > {
+        let events = self
+            .client
+            .sse_events(&self.events_url(path, timeout))
+            .await?;
+        Ok(invoice_events(events))
+    }
+
+    fn events_url(&self, path: &str, timeout: Option<i32>) -> String {
+        let url = self.client.url(path);
+        match timeout {
+            Some(t) => format!("{}?timeout={}", url, t),
+            None => url,
+        }
+    }
+}
+
+fn invoice_events(
+    events: impl Stream<Item = Result<SseEvent, LnBotError>> + Send + Sync + 'static,
+) -> Pin<Box<dyn Stream<Item = Result<InvoiceEvent, LnBotError>> + Send + Sync>> {
+    Box::pin(async_stream::try_stream! {
+        let mut events = Box::pin(events);
+        while let Some(event) = events.next().await {
+            let event = event?;
+            // Only typed events carry a state change.
+            if event.event == DEFAULT_EVENT_TYPE || event.data.trim().is_empty() {
+                continue;
+            }
+            let data: InvoiceResponse = serde_json::from_str(&event.data)?;
+            yield InvoiceEvent {
+                event: InvoiceEventType::from(event.event.as_str()),
+                data,
+            };
+        }
+    })
 }
 
 /// Public invoice operations (no auth required).
-pub struct PublicInvoicesResource<'a> {
-    pub(crate) client: &'a LnBot,
+#[derive(Clone)]
+pub struct PublicInvoicesResource {
+    pub(crate) client: LnBot,
 }
 
-impl PublicInvoicesResource<'_> {
+impl PublicInvoicesResource {
     /// Creates an invoice for a specific wallet by its ID.
     /// No authentication required. Rate limited by IP.
     pub async fn create_for_wallet(
         &self,
         req: &CreateInvoiceForWalletRequest,
     ) -> Result<AddressInvoiceResponse, LnBotError> {
+        req.validate()?;
         self.client.post("/v1/invoices/for-wallet", Some(req)).await
     }
 
@@ -129,6 +267,7 @@ impl PublicInvoicesResource<'_> {
         &self,
         req: &CreateInvoiceForAddressRequest,
     ) -> Result<AddressInvoiceResponse, LnBotError> {
+        req.validate()?;
         self.client
             .post("/v1/invoices/for-address", Some(req))
             .await
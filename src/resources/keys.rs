@@ -3,11 +3,12 @@ use crate::errors::LnBotError;
 use crate::types::*;
 
 /// Operations on API keys.
-pub struct KeysResource<'a> {
-    pub(crate) client: &'a LnBot,
+#[derive(Clone)]
+pub struct KeysResource {
+    pub(crate) client: LnBot,
 }
 
-impl KeysResource<'_> {
+impl KeysResource {
     /// Rotates the API key in the given slot (1 = primary, 2 = secondary).
     pub async fn rotate(&self, slot: i32) -> Result<RotateApiKeyResponse, LnBotError> {
         self.client
@@ -1,22 +1,32 @@
+use super::PaymentsResource;
+use crate::approval::{self, ApprovalHandler, PaymentKind, PaymentPreview};
 use crate::client::LnBot;
 use crate::errors::LnBotError;
+use crate::l402::L402Challenge;
+use crate::policy::{Reservation, SpendingPolicy};
 use crate::types::{
     CreateL402ChallengeRequest, L402ChallengeResponse, L402PayResponse, PayL402Request,
     VerifyL402Request, VerifyL402Response,
 };
+use std::borrow::Cow;
+use std::sync::Arc;
 
 /// Wallet-scoped L402 paywall operations.
-pub struct L402Resource<'a> {
-    pub(crate) client: &'a LnBot,
-    pub(crate) prefix: &'a str,
+#[derive(Clone)]
+pub struct L402Resource {
+    pub(crate) client: LnBot,
+    pub(crate) prefix: Arc<str>,
+    pub(crate) policy: Option<Arc<SpendingPolicy>>,
+    pub(crate) approval: Option<Arc<dyn ApprovalHandler>>,
 }
 
-impl L402Resource<'_> {
+impl L402Resource {
     /// Creates an L402 challenge (invoice + macaroon) for paywall authentication.
     pub async fn create_challenge(
         &self,
         req: &CreateL402ChallengeRequest,
     ) -> Result<L402ChallengeResponse, LnBotError> {
+        req.validate()?;
         self.client
             .post(&format!("{}/l402/challenges", self.prefix), Some(req))
             .await
@@ -28,14 +38,74 @@ impl L402Resource<'_> {
         req: &VerifyL402Request,
     ) -> Result<VerifyL402Response, LnBotError> {
         self.client
-            .post(&format!("{}/l402/verify", self.prefix), Some(req))
+            .post_idempotent(&format!("{}/l402/verify", self.prefix), Some(req))
             .await
     }
 
     /// Pays an L402 challenge and returns a ready-to-use Authorization header.
+    ///
+    /// With an [`ApprovalHandler`] attached to the wallet handle, the
+    /// challenge's invoice is first resolved and submitted for approval. With
+    /// a [`SpendingPolicy`], it is then checked against the policy.
     pub async fn pay(&self, req: &PayL402Request) -> Result<L402PayResponse, LnBotError> {
-        self.client
+        let req = match &self.approval {
+            Some(handler) => Cow::Owned(self.approve(handler.as_ref(), req).await?),
+            None => Cow::Borrowed(req),
+        };
+        let req = req.as_ref();
+        req.validate()?;
+        let reservation = match &self.policy {
+            Some(policy) => {
+                let invoice = L402Challenge::parse(&req.www_authenticate)?.invoice;
+                policy.reserve(&invoice, None, req.max_fee).await?
+            }
+            None => Reservation::none(),
+        };
+        let result: Result<L402PayResponse, LnBotError> = self
+            .client
             .post(&format!("{}/l402/pay", self.prefix), Some(req))
-            .await
+            .await;
+        let failed = match &result {
+            Ok(paid) => paid.status == "failed",
+            Err(e) => !e.outcome_unknown(),
+        };
+        if failed {
+            reservation.release().await;
+        }
+        result
+    }
+
+    async fn approve(
+        &self,
+        handler: &dyn ApprovalHandler,
+        req: &PayL402Request,
+    ) -> Result<PayL402Request, LnBotError> {
+        let invoice = L402Challenge::parse(&req.www_authenticate)?.invoice;
+        let payments = PaymentsResource {
+            client: self.client.clone(),
+            prefix: self.prefix.clone(),
+            policy: None,
+            approval: None,
+        };
+        let destination = payments.resolve(&invoice).await?;
+        let preview = PaymentPreview {
+            kind: PaymentKind::L402,
+            target: invoice,
+            amount: destination.amount,
+            max_fee: req.max_fee,
+            reference: req.reference.clone(),
+            destination,
+        };
+        let mut req = req.clone();
+        if let Some(changes) = approval::review(handler, preview).await? {
+            if changes.amount.is_some() {
+                return Err(LnBotError::ApprovalDenied {
+                    reason: "the amount of an L402 payment cannot be changed".into(),
+                });
+            }
+            req.max_fee = changes.max_fee.or(req.max_fee);
+            req.reference = changes.reference.or(req.reference);
+        }
+        Ok(req)
     }
 }
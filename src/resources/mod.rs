@@ -11,6 +11,11 @@ mod backup;
 mod restore;
 mod l402;
 
+use std::time::Duration;
+
+/// Delay between two polls when waiting on a payment or invoice without SSE.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 pub use wallets::WalletsResource;
 pub use wallet_key::WalletKeyResource;
 pub use keys::KeysResource;
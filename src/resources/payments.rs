@@ -1,22 +1,246 @@
-use crate::client::{check_status, LnBot};
+use super::POLL_INTERVAL;
+use crate::amount::Sats;
+use crate::approval::{self, ApprovalHandler, PaymentKind, PaymentPreview};
+use crate::client::LnBot;
 use crate::errors::LnBotError;
+use crate::ids::PaymentNumber;
+use crate::pagination::{ListAllParams, Paginator};
+use crate::policy::{Reservation, SpendingPolicy};
+use crate::sse::{SseEvent, DEFAULT_EVENT_TYPE};
+use crate::timestamp::ApiTime;
 use crate::types::*;
 use futures_core::Stream;
 use futures_util::StreamExt;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+/// How far the API's clock may run behind ours when recovering a payment
+/// by its creation time.
+const RECOVERY_CLOCK_SKEW: Duration = Duration::from_secs(60);
 
 /// Wallet-scoped payment operations.
-pub struct PaymentsResource<'a> {
-    pub(crate) client: &'a LnBot,
-    pub(crate) prefix: &'a str,
+#[derive(Clone)]
+pub struct PaymentsResource {
+    pub(crate) client: LnBot,
+    pub(crate) prefix: Arc<str>,
+    pub(crate) policy: Option<Arc<SpendingPolicy>>,
+    pub(crate) approval: Option<Arc<dyn ApprovalHandler>>,
 }
 
-impl PaymentsResource<'_> {
+impl PaymentsResource {
     /// Creates a new outgoing payment.
+    ///
+    /// Without an `idempotency_key`, a random UUIDv4 key is generated, so the
+    /// request can safely be retried on transient failures. The key is
+    /// returned in [`PaymentResponse::idempotency_key`]. If the outcome of the
+    /// payment is unknown, the error is
+    /// [`LnBotError::PaymentOutcomeUnknown`], which carries the key; see
+    /// [`create_or_recover`](Self::create_or_recover).
+    ///
+    /// With an [`ApprovalHandler`] attached to the wallet handle, the payment
+    /// is first resolved and submitted for approval. With a
+    /// [`SpendingPolicy`], it is then checked against the policy and fails
+    /// with [`LnBotError::PolicyViolation`] without sending anything.
     pub async fn create(&self, req: &CreatePaymentRequest) -> Result<PaymentResponse, LnBotError> {
-        self.client
-            .post(&format!("{}/payments", self.prefix), Some(req))
+        let (req, reservation) = self.prepare(req).await?;
+        let result = self.send(&req).await;
+        finish(result, reservation).await
+    }
+
+    /// Creates a payment like [`create`](Self::create), recovering from
+    /// failures that leave its outcome unknown.
+    ///
+    /// After an ambiguous failure, the payment is first looked up among the
+    /// 100 most recent payments: by idempotency key where the API reports
+    /// one, and otherwise by `reference`, if the request has one, among
+    /// payments created after this call started.
+    /// Otherwise it is sent again with the same idempotency key, which the
+    /// API deduplicates. Fails with [`LnBotError::PaymentOutcomeUnknown`]
+    /// if the lookup fails or the outcome is still unknown after that. A
+    /// rate-limited request was not processed, so it fails with
+    /// [`LnBotError::RateLimited`] without any recovery.
+    pub async fn create_or_recover(
+        &self,
+        req: &CreatePaymentRequest,
+    ) -> Result<PaymentResponse, LnBotError> {
+        let started = SystemTime::now();
+        let (req, reservation) = self.prepare(req).await?;
+        let result = match self.send(&req).await {
+            Err(e) if e.outcome_unknown() => self.recover(&req, started).await,
+            result => result,
+        };
+        finish(result, reservation).await
+    }
+
+    async fn prepare(
+        &self,
+        req: &CreatePaymentRequest,
+    ) -> Result<(CreatePaymentRequest, Reservation), LnBotError> {
+        let mut req = match &self.approval {
+            Some(handler) => self.approve(handler.as_ref(), req).await?,
+            None => req.clone(),
+        };
+        req.validate()?;
+        if req.idempotency_key.is_none() {
+            req.idempotency_key = Some(new_idempotency_key()?);
+        }
+        let reservation = match &self.policy {
+            Some(policy) => policy.reserve(&req.target, req.amount, req.max_fee).await?,
+            None => Reservation::none(),
+        };
+        Ok((req, reservation))
+    }
+
+    async fn send(&self, req: &CreatePaymentRequest) -> Result<PaymentResponse, LnBotError> {
+        let key = req.idempotency_key.clone().unwrap_or_default();
+        let path = format!("{}/payments", self.prefix);
+        match self.client.post_idempotent(&path, Some(req)).await {
+            Ok(payment) => Ok(with_key(payment, key)),
+            Err(e) if e.outcome_unknown() => Err(LnBotError::PaymentOutcomeUnknown {
+                idempotency_key: key,
+                source: Box::new(e),
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Looks for the payment `req` may have created at or after `started`,
+    /// and sends it again if it is not found.
+    async fn recover(
+        &self,
+        req: &CreatePaymentRequest,
+        started: SystemTime,
+    ) -> Result<PaymentResponse, LnBotError> {
+        let key = req.idempotency_key.clone().unwrap_or_default();
+        let earliest = started
+            .checked_sub(RECOVERY_CLOCK_SKEW)
+            .unwrap_or(UNIX_EPOCH);
+        let payments = self
+            .list(&ListParams::default().limit(100))
             .await
+            .map_err(|e| LnBotError::PaymentOutcomeUnknown {
+                idempotency_key: key.clone(),
+                source: Box::new(e),
+            })?;
+        let found = payments.into_iter().find(|p| match &p.idempotency_key {
+            Some(k) => *k == key,
+            None => {
+                req.reference.is_some()
+                    && p.reference == req.reference
+                    && p.created_at
+                        .as_ref()
+                        .is_some_and(|at| at.system_time() >= earliest)
+            }
+        });
+        match found {
+            Some(payment) => Ok(with_key(payment, key)),
+            None => self.send(req).await,
+        }
+    }
+
+    async fn approve(
+        &self,
+        handler: &dyn ApprovalHandler,
+        req: &CreatePaymentRequest,
+    ) -> Result<CreatePaymentRequest, LnBotError> {
+        let destination = self.resolve(&req.target).await?;
+        // An amount fixed by the destination is what the payment charges.
+        let fixed = destination.amount;
+        check_fixed_amount(req.amount, fixed)?;
+        let preview = PaymentPreview {
+            kind: PaymentKind::Payment,
+            target: req.target.clone(),
+            amount: fixed.or(req.amount),
+            max_fee: req.max_fee,
+            reference: req.reference.clone(),
+            destination,
+        };
+        let mut req = req.clone();
+        if let Some(changes) = approval::review(handler, preview).await? {
+            check_fixed_amount(changes.amount, fixed)?;
+            req.amount = changes.amount.or(req.amount);
+            req.max_fee = changes.max_fee.or(req.max_fee);
+            req.reference = changes.reference.or(req.reference);
+        }
+        Ok(req)
+    }
+
+    /// Creates a payment and waits until it settles or fails.
+    ///
+    /// Follows the payment over SSE, checking it once with [`get`](Self::get)
+    /// after subscribing in case it finished before the stream opened, and
+    /// falls back to polling if the stream drops before a final event. A `Failed` payment is
+    /// returned as `Ok`; check its `status`. When `timeout` elapses first,
+    /// [`LnBotError::PaymentTimeout`] carries the last known state.
+    pub async fn pay_and_wait(
+        &self,
+        req: &CreatePaymentRequest,
+        timeout: Duration,
+    ) -> Result<PaymentResponse, LnBotError> {
+        let deadline = Instant::now() + timeout;
+        let mut last = self.create(req).await?;
+        if last.status.is_terminal() {
+            return Ok(last);
+        }
+        let number = last.number;
+        let timed_out = |payment| LnBotError::PaymentTimeout {
+            payment: Box::new(payment),
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let secs = i32::try_from(remaining.as_secs_f64().ceil() as u64)
+            .unwrap_or(i32::MAX)
+            .max(1);
+        let path = format!("{}/payments/{}/events", self.prefix, number);
+        match tokio::time::timeout_at(deadline, self.subscribe(&path, Some(secs))).await {
+            Err(_) => return Err(timed_out(last)),
+            Ok(Ok(mut events)) => {
+                // A payment that finished before the stream opened sends no
+                // event, so check once now that later changes will be seen.
+                if let Ok(Ok(payment)) = tokio::time::timeout_at(deadline, self.get(number)).await {
+                    last = payment;
+                    if last.status.is_terminal() {
+                        return Ok(last);
+                    }
+                }
+                loop {
+                    match tokio::time::timeout_at(deadline, events.next()).await {
+                        Err(_) => return Err(timed_out(last)),
+                        Ok(Some(Ok(event))) => {
+                            last = event.data;
+                            if last.status.is_terminal() {
+                                return Ok(last);
+                            }
+                        }
+                        // The stream ended or failed without a final event.
+                        Ok(Some(Err(_)) | None) => break,
+                    }
+                }
+            }
+            // The stream could not be opened; poll instead.
+            Ok(Err(_)) => {}
+        }
+
+        loop {
+            match tokio::time::timeout_at(deadline, self.get(number)).await {
+                Err(_) => return Err(timed_out(last)),
+                Ok(Ok(payment)) => {
+                    last = payment;
+                    if last.status.is_terminal() {
+                        return Ok(last);
+                    }
+                }
+                Ok(Err(e)) if e.is_transient() => {}
+                Ok(Err(e)) => return Err(e),
+            }
+            if Instant::now() + POLL_INTERVAL >= deadline {
+                tokio::time::sleep_until(deadline).await;
+                return Err(timed_out(last));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
     }
 
     /// Lists payments with optional pagination.
@@ -26,8 +250,28 @@ impl PaymentsResource<'_> {
             .await
     }
 
+    /// Streams every payment, newest first, fetching pages lazily.
+    pub fn list_all(&self, params: ListAllParams) -> Paginator<PaymentResponse> {
+        let client = self.client.clone();
+        let path = format!("{}/payments", self.prefix);
+        Paginator::new(params, move |page| {
+            let client = client.clone();
+            let path = path.clone();
+            Box::pin(async move { client.get_with_params(&path, &page).await })
+        })
+    }
+
+    /// Streams every payment with the default [`ListAllParams`].
+    pub fn iter(&self) -> Paginator<PaymentResponse> {
+        self.list_all(ListAllParams::default())
+    }
+
     /// Gets a payment by its number.
-    pub async fn get(&self, number: i32) -> Result<PaymentResponse, LnBotError> {
+    pub async fn get(
+        &self,
+        number: impl Into<PaymentNumber>,
+    ) -> Result<PaymentResponse, LnBotError> {
+        let number = number.into();
         self.client
             .get(&format!("{}/payments/{}", self.prefix, number))
             .await
@@ -53,10 +297,10 @@ impl PaymentsResource<'_> {
     /// Returns a stream of real-time events for a payment via Server-Sent Events.
     pub fn watch(
         &self,
-        number: i32,
+        number: impl Into<PaymentNumber>,
         timeout: Option<i32>,
-    ) -> Pin<Box<dyn Stream<Item = Result<PaymentEvent, LnBotError>> + Send + '_>> {
-        let path = format!("{}/payments/{}/events", self.prefix, number);
+    ) -> Pin<Box<dyn Stream<Item = Result<PaymentEvent, LnBotError>> + Send + Sync>> {
+        let path = format!("{}/payments/{}/events", self.prefix, number.into());
         self.sse_stream(&path, timeout)
     }
 
@@ -65,7 +309,7 @@ impl PaymentsResource<'_> {
         &self,
         payment_hash: &str,
         timeout: Option<i32>,
-    ) -> Pin<Box<dyn Stream<Item = Result<PaymentEvent, LnBotError>> + Send + '_>> {
+    ) -> Pin<Box<dyn Stream<Item = Result<PaymentEvent, LnBotError>> + Send + Sync>> {
         let path = format!("{}/payments/{}/events", self.prefix, payment_hash);
         self.sse_stream(&path, timeout)
     }
@@ -74,46 +318,111 @@ impl PaymentsResource<'_> {
         &self,
         path: &str,
         timeout: Option<i32>,
-    ) -> Pin<Box<dyn Stream<Item = Result<PaymentEvent, LnBotError>> + Send + '_>> {
-        let mut url = format!("{}{}", self.client.base_url, path);
-        if let Some(t) = timeout {
-            url = format!("{}?timeout={}", url, t);
-        }
+    ) -> Pin<Box<dyn Stream<Item = Result<PaymentEvent, LnBotError>> + Send + Sync>> {
+        let url = self.events_url(path, timeout);
+        let client = self.client.clone();
         Box::pin(async_stream::try_stream! {
-            let mut req = self.client.http.get(&url).header("Accept", "text/event-stream");
-            if let Some(ref key) = self.client.api_key {
-                req = req.bearer_auth(key);
+            let mut events = payment_events(client.sse_events(&url).await?);
+            while let Some(event) = events.next().await {
+                yield event?;
             }
+        })
+    }
 
-            let resp = check_status(req.send().await?).await?;
-
-            let mut event_type = String::new();
-            let mut buffer = String::new();
-            let mut stream = resp.bytes_stream();
-
-            while let Some(chunk) = stream.next().await {
-                let chunk = chunk?;
-                buffer.push_str(&String::from_utf8_lossy(&chunk));
-
-                while let Some(pos) = buffer.find('\n') {
-                    let line = buffer[..pos].to_string();
-                    buffer.drain(..=pos);
-
-                    if let Some(value) = line.strip_prefix("event:") {
-                        event_type = value.trim().to_string();
-                    } else if let Some(value) = line.strip_prefix("data:") {
-                        let raw = value.trim();
-                        if !raw.is_empty() && !event_type.is_empty() {
-                            let data: PaymentResponse = serde_json::from_str(raw)?;
-                            yield PaymentEvent {
-                                event: PaymentEventType::from(event_type.as_str()),
-                                data,
-                            };
-                            event_type.clear();
-                        }
-                    }
-                }
+    /// Like [`sse_stream`](Self::sse_stream), but connects before returning,
+    /// so no event sent afterwards is missed.
+    async fn subscribe(
+        &self,
+        path: &str,
+        timeout: Option<i32>,
+    ) -> Result<
+        Pin<Box<dyn Stream<Item = Result<PaymentEvent, LnBotError>> + Send + Sync>>,
+        LnBotError,
+    > {
+        let events = self
+            .client
+            .sse_events(&self.events_url(path, timeout))
+            .await?;
+        Ok(payment_events(events))
+    }
+
+    fn events_url(&self, path: &str, timeout: Option<i32>) -> String {
+        let url = self.client.url(path);
+        match timeout {
+            Some(t) => format!("{}?timeout={}", url, t),
+            None => url,
+        }
+    }
+}
+
+fn payment_events(
+    events: impl Stream<Item = Result<SseEvent, LnBotError>> + Send + Sync + 'static,
+) -> Pin<Box<dyn Stream<Item = Result<PaymentEvent, LnBotError>> + Send + Sync>> {
+    Box::pin(async_stream::try_stream! {
+        let mut events = Box::pin(events);
+        while let Some(event) = events.next().await {
+            let event = event?;
+            // Only typed events carry a state change.
+            if event.event == DEFAULT_EVENT_TYPE || event.data.trim().is_empty() {
+                continue;
             }
-        })
+            let data: PaymentResponse = serde_json::from_str(&event.data)?;
+            yield PaymentEvent {
+                event: PaymentEventType::from(event.event.as_str()),
+                data,
+            };
+        }
+    })
+}
+
+/// Releases the reservation unless the payment may have been sent.
+async fn finish(
+    result: Result<PaymentResponse, LnBotError>,
+    reservation: Reservation,
+) -> Result<PaymentResponse, LnBotError> {
+    let failed = match &result {
+        Ok(payment) => payment.status == PaymentStatus::Failed,
+        Err(e) => !e.outcome_unknown(),
+    };
+    if failed {
+        reservation.release().await;
+    }
+    result
+}
+
+/// Denies a payment whose `amount` differs from the amount its destination fixes.
+fn check_fixed_amount(amount: Option<Sats>, fixed: Option<Sats>) -> Result<(), LnBotError> {
+    match (amount, fixed) {
+        (Some(amount), Some(fixed)) if amount != fixed => Err(LnBotError::ApprovalDenied {
+            reason: format!(
+                "requested amount of {} differs from the invoice amount of {}",
+                amount, fixed
+            ),
+        }),
+        _ => Ok(()),
     }
 }
+
+fn with_key(mut payment: PaymentResponse, key: String) -> PaymentResponse {
+    payment.idempotency_key.get_or_insert(key);
+    payment
+}
+
+/// Generates a random (version 4) UUID from the operating system's secure
+/// random number generator.
+fn new_idempotency_key() -> Result<String, LnBotError> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| LnBotError::Config(format!("cannot generate an idempotency key: {}", e)))?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
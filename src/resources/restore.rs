@@ -3,11 +3,12 @@ use crate::errors::LnBotError;
 use crate::types::*;
 
 /// Operations for restoring wallet credentials.
-pub struct RestoreResource<'a> {
-    pub(crate) client: &'a LnBot,
+#[derive(Clone)]
+pub struct RestoreResource {
+    pub(crate) client: LnBot,
 }
 
-impl RestoreResource<'_> {
+impl RestoreResource {
     /// Restores a wallet from a recovery passphrase.
     pub async fn recovery(
         &self,
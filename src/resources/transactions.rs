@@ -1,18 +1,37 @@
 use crate::client::LnBot;
 use crate::errors::LnBotError;
+use crate::pagination::{ListAllParams, Paginator};
 use crate::types::*;
+use std::sync::Arc;
 
 /// Wallet-scoped transaction operations.
-pub struct TransactionsResource<'a> {
-    pub(crate) client: &'a LnBot,
-    pub(crate) prefix: &'a str,
+#[derive(Clone)]
+pub struct TransactionsResource {
+    pub(crate) client: LnBot,
+    pub(crate) prefix: Arc<str>,
 }
 
-impl TransactionsResource<'_> {
+impl TransactionsResource {
     /// Lists transactions with optional pagination.
     pub async fn list(&self, params: &ListParams) -> Result<Vec<TransactionResponse>, LnBotError> {
         self.client
             .get_with_params(&format!("{}/transactions", self.prefix), params)
             .await
     }
+
+    /// Streams every transaction, newest first, fetching pages lazily.
+    pub fn list_all(&self, params: ListAllParams) -> Paginator<TransactionResponse> {
+        let client = self.client.clone();
+        let path = format!("{}/transactions", self.prefix);
+        Paginator::new(params, move |page| {
+            let client = client.clone();
+            let path = path.clone();
+            Box::pin(async move { client.get_with_params(&path, &page).await })
+        })
+    }
+
+    /// Streams every transaction with the default [`ListAllParams`].
+    pub fn iter(&self) -> Paginator<TransactionResponse> {
+        self.list_all(ListAllParams::default())
+    }
 }
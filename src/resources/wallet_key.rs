@@ -1,14 +1,16 @@
 use crate::client::LnBot;
 use crate::errors::LnBotError;
 use crate::types::*;
+use std::sync::Arc;
 
 /// Operations on wallet keys (wk_ keys).
-pub struct WalletKeyResource<'a> {
-    pub(crate) client: &'a LnBot,
-    pub(crate) prefix: &'a str,
+#[derive(Clone)]
+pub struct WalletKeyResource {
+    pub(crate) client: LnBot,
+    pub(crate) prefix: Arc<str>,
 }
 
-impl WalletKeyResource<'_> {
+impl WalletKeyResource {
     /// Creates a new wallet key.
     pub async fn create(&self) -> Result<WalletKeyResponse, LnBotError> {
         self.client
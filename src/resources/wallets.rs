@@ -3,11 +3,12 @@ use crate::errors::LnBotError;
 use crate::types::*;
 
 /// Account-level wallet operations (create, list).
-pub struct WalletsResource<'a> {
-    pub(crate) client: &'a LnBot,
+#[derive(Clone)]
+pub struct WalletsResource {
+    pub(crate) client: LnBot,
 }
 
-impl WalletsResource<'_> {
+impl WalletsResource {
     /// Creates a new wallet.
     pub async fn create(&self) -> Result<CreateWalletResponse, LnBotError> {
         self.client
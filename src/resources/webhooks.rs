@@ -1,14 +1,17 @@
 use crate::client::LnBot;
 use crate::errors::LnBotError;
+use crate::ids::WebhookId;
 use crate::types::*;
+use std::sync::Arc;
 
 /// Wallet-scoped webhook operations.
-pub struct WebhooksResource<'a> {
-    pub(crate) client: &'a LnBot,
-    pub(crate) prefix: &'a str,
+#[derive(Clone)]
+pub struct WebhooksResource {
+    pub(crate) client: LnBot,
+    pub(crate) prefix: Arc<str>,
 }
 
-impl WebhooksResource<'_> {
+impl WebhooksResource {
     /// Creates a new webhook.
     pub async fn create(
         &self,
@@ -27,12 +30,13 @@ impl WebhooksResource<'_> {
     }
 
     /// Deletes a webhook by ID.
-    pub async fn delete(&self, id: &str) -> Result<(), LnBotError> {
+    pub async fn delete(&self, id: impl Into<WebhookId>) -> Result<(), LnBotError> {
+        let id = id.into();
         self.client
             .delete(&format!(
                 "{}/webhooks/{}",
                 self.prefix,
-                urlencoding::encode(id)
+                urlencoding::encode(id.as_str())
             ))
             .await
     }
@@ -0,0 +1,244 @@
+//! Automatic retries for transient failures and dropped event streams.
+
+use std::time::Duration;
+
+/// How much randomness to add to the delay between retries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Jitter {
+    /// Use the exact exponential delay.
+    None,
+    /// Pick a random delay between zero and the exponential delay.
+    #[default]
+    Full,
+    /// Pick a random delay between half and all of the exponential delay.
+    Equal,
+}
+
+/// Controls when and how often failed requests are retried.
+///
+/// Idempotent requests (`GET`, `PATCH`, `DELETE`) are retried whenever the
+/// failure is retryable. `POST` requests are only retried when they are safe
+/// to repeat, e.g. a payment that carries an `idempotency_key`.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use lnbot::{LnBot, RetryPolicy};
+///
+/// let client = LnBot::new("uk_...").with_retry_policy(
+///     RetryPolicy::default()
+///         .max_attempts(5)
+///         .base_delay(Duration::from_millis(100)),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry. Doubles on every subsequent retry.
+    pub base_delay: Duration,
+    /// Upper bound for the delay between two attempts.
+    pub max_delay: Duration,
+    /// Randomness applied to each delay.
+    pub jitter: Jitter,
+    /// HTTP status codes that are considered transient.
+    pub retry_statuses: Vec<u16>,
+    /// Retry when the connection could not be established.
+    pub retry_on_connect: bool,
+    /// Retry when the request timed out.
+    pub retry_on_timeout: bool,
+    /// When set, requests rejected with HTTP 429 are retried after the
+    /// server's `Retry-After` delay, or the usual backoff if that is longer,
+    /// as long as the total time spent waiting stays within this bound and
+    /// `max_rate_limit_retries` is not reached. Otherwise
+    /// [`LnBotError::RateLimited`](crate::LnBotError::RateLimited) is
+    /// returned straight away.
+    pub rate_limit_wait: Option<Duration>,
+    /// How many times a rate-limited request is retried. Counted apart
+    /// from `max_attempts`.
+    pub max_rate_limit_retries: u32,
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts with full jitter, retrying 500/502/503/504,
+    /// connection failures and timeouts.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: Jitter::Full,
+            retry_statuses: vec![500, 502, 503, 504],
+            retry_on_connect: true,
+            retry_on_timeout: true,
+            rate_limit_wait: None,
+            max_rate_limit_retries: 5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries. This is what [`LnBot`](crate::LnBot) uses by default.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    #[must_use]
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    #[must_use]
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    #[must_use]
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    #[must_use]
+    pub fn retry_statuses(mut self, statuses: impl IntoIterator<Item = u16>) -> Self {
+        self.retry_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    #[must_use]
+    pub fn retry_on_connect(mut self, retry: bool) -> Self {
+        self.retry_on_connect = retry;
+        self
+    }
+
+    #[must_use]
+    pub fn retry_on_timeout(mut self, retry: bool) -> Self {
+        self.retry_on_timeout = retry;
+        self
+    }
+
+    /// Waits out HTTP 429 responses for up to `max_wait` in total, within
+    /// `max_rate_limit_retries`, before giving up with
+    /// [`LnBotError::RateLimited`](crate::LnBotError::RateLimited).
+    #[must_use]
+    pub fn wait_on_rate_limit(mut self, max_wait: Duration) -> Self {
+        self.rate_limit_wait = Some(max_wait);
+        self
+    }
+
+    #[must_use]
+    pub fn max_rate_limit_retries(mut self, retries: u32) -> Self {
+        self.max_rate_limit_retries = retries;
+        self
+    }
+
+    /// Returns the delay to wait before the given retry (1 = first retry).
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        backoff(self.base_delay, self.max_delay, self.jitter, retry)
+    }
+
+    pub(crate) fn retries_status(&self, status: u16) -> bool {
+        self.retry_statuses.contains(&status)
+    }
+
+    pub(crate) fn retries_error(&self, err: &reqwest::Error) -> bool {
+        (self.retry_on_connect && err.is_connect()) || (self.retry_on_timeout && err.is_timeout())
+    }
+}
+
+/// Controls how a dropped event stream is re-established.
+///
+/// The server's `retry:` hint, when present, replaces `base_delay`.
+/// Consecutive failures back off exponentially up to `max_delay`; the
+/// counter resets once a connection succeeds.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use lnbot::ReconnectPolicy;
+///
+/// let policy = ReconnectPolicy::default()
+///     .max_retries(10)
+///     .max_delay(Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Consecutive failed reconnection attempts before giving up.
+    /// `None` keeps trying forever.
+    pub max_retries: Option<u32>,
+    /// Delay before the first reconnection attempt, unless the server sent a `retry:` hint.
+    pub base_delay: Duration,
+    /// Upper bound for the delay between two attempts.
+    pub max_delay: Duration,
+    /// Randomness applied to each delay.
+    pub jitter: Jitter,
+}
+
+impl Default for ReconnectPolicy {
+    /// Retries forever, starting at one second and backing off to 30 seconds.
+    fn default() -> Self {
+        Self {
+            max_retries: None,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: Jitter::Equal,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    #[must_use]
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    #[must_use]
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    #[must_use]
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    #[must_use]
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Returns the delay before the given reconnection attempt (1 = first),
+    /// starting from the server's `retry:` hint when one was received.
+    pub fn delay_for(&self, attempt: u32, server_retry: Option<Duration>) -> Duration {
+        let base = server_retry.unwrap_or(self.base_delay);
+        backoff(base, self.max_delay.max(base), self.jitter, attempt)
+    }
+}
+
+fn backoff(base: Duration, max: Duration, jitter: Jitter, attempt: u32) -> Duration {
+    let exp = 2u32.saturating_pow(attempt.saturating_sub(1));
+    let delay = base.saturating_mul(exp).min(max);
+    match jitter {
+        Jitter::None => delay,
+        Jitter::Full => delay.mul_f64(fastrand::f64()),
+        Jitter::Equal => delay / 2 + (delay / 2).mul_f64(fastrand::f64()),
+    }
+}
@@ -0,0 +1,114 @@
+//! A wrapper that keeps credentials out of logs.
+//!
+//! Keys, passphrases and webhook secrets returned by the API are wrapped in
+//! [`Secret`], whose `Debug` and `Display` never show the value. Reading it
+//! takes an explicit [`expose_secret`](Secret::expose_secret) call. With the
+//! `zeroize` feature, the value is also wiped from memory when dropped.
+//!
+//! ```
+//! use lnbot::Secret;
+//!
+//! let passphrase = Secret::new(String::from("correct horse battery staple"));
+//! assert_eq!(format!("{:?}", passphrase), "Secret([REDACTED])");
+//! assert_eq!(passphrase.to_string(), "[REDACTED]");
+//! assert_eq!(passphrase.expose_secret(), "correct horse battery staple");
+//! ```
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A value that can be held in a [`Secret`].
+///
+/// Implemented for `String` and `Vec<u8>`.
+pub trait SecretValue: private::Sealed {
+    /// Overwrites the value in place. Does nothing without the `zeroize` feature.
+    #[doc(hidden)]
+    fn wipe(&mut self);
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for String {}
+    impl Sealed for Vec<u8> {}
+}
+
+impl SecretValue for String {
+    fn wipe(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+impl SecretValue for Vec<u8> {
+    fn wipe(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+/// A credential whose `Debug` and `Display` output is redacted.
+///
+/// Serializes as the plain value, so it can be sent to and read from the API.
+/// Equality compares the values.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret<T: SecretValue = String>(T);
+
+impl<T: SecretValue> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Returns the secret value. Avoid logging it.
+    pub fn expose_secret(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: SecretValue> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Secret<Vec<u8>> {
+    fn from(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: SecretValue> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<T: SecretValue> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<T: SecretValue + Serialize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: SecretValue + Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
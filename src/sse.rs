@@ -0,0 +1,224 @@
+//! Server-Sent Events decoding.
+//!
+//! Implements the [WHATWG event stream] interpretation rules: `event:`,
+//! `data:`, `id:` and `retry:` fields, comment lines, multi-line `data:`
+//! fields and all three line endings (`\r\n`, `\n`, `\r`). Input is
+//! consumed as raw bytes, so multi-byte UTF-8 sequences split across chunks
+//! are decoded correctly.
+//!
+//! Most users never need this module directly: the `watch` and `stream`
+//! methods on the resources decode their events with it. It is public for
+//! consuming other SSE endpoints with the same rules.
+//!
+//! [WHATWG event stream]: https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation
+//!
+//! # Examples
+//!
+//! ```
+//! use lnbot::sse::SseDecoder;
+//!
+//! let mut decoder = SseDecoder::new();
+//! let events = decoder.feed(b"event: settled\ndata: {\"number\":1}\n\n");
+//! assert_eq!(events[0].event, "settled");
+//! assert_eq!(events[0].data, "{\"number\":1}");
+//! ```
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::StreamExt;
+
+/// Event type used when an event has no `event:` field.
+pub const DEFAULT_EVENT_TYPE: &str = "message";
+
+/// A single dispatched Server-Sent Event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SseEvent {
+    /// The event type, or `"message"` when the event had no `event:` field.
+    pub event: String,
+    /// The event payload. Multiple `data:` lines are joined with `\n`.
+    pub data: String,
+    /// The last event ID seen on the stream, if any.
+    pub id: Option<String>,
+    /// The reconnection delay most recently requested by the server, if any.
+    pub retry: Option<Duration>,
+}
+
+/// Incremental decoder turning raw SSE bytes into [`SseEvent`]s.
+#[derive(Debug, Clone, Default)]
+pub struct SseDecoder {
+    line: Vec<u8>,
+    skip_lf: bool,
+    started: bool,
+    event_type: String,
+    data: String,
+    last_event_id: String,
+    retry: Option<Duration>,
+}
+
+impl SseDecoder {
+    /// Creates a decoder at the start of a stream.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a chunk of bytes and returns the events completed by it.
+    ///
+    /// Incomplete lines and events are buffered until a later chunk
+    /// completes them.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            if self.skip_lf {
+                self.skip_lf = false;
+                if byte == b'\n' {
+                    continue;
+                }
+            }
+            match byte {
+                b'\r' | b'\n' => {
+                    self.skip_lf = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    if let Some(event) = self.process_line(&line) {
+                        events.push(event);
+                    }
+                }
+                _ => self.line.push(byte),
+            }
+        }
+        events
+    }
+
+    /// The last event ID received, to be sent as `Last-Event-ID` when reconnecting.
+    pub fn last_event_id(&self) -> Option<&str> {
+        (!self.last_event_id.is_empty()).then_some(self.last_event_id.as_str())
+    }
+
+    /// The reconnection delay most recently requested with a `retry:` field.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Discards any partially received line or event while keeping the last
+    /// event ID and reconnection delay, as required when a dropped
+    /// connection is re-established.
+    pub fn reset(&mut self) {
+        self.line.clear();
+        self.skip_lf = false;
+        self.started = false;
+        self.event_type.clear();
+        self.data.clear();
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        // Lines are split on ASCII bytes only, so a complete line never ends
+        // in the middle of a multi-byte sequence.
+        let decoded = String::from_utf8_lossy(line);
+        let mut line: &str = &decoded;
+        if !self.started {
+            self.started = true;
+            line = line.strip_prefix('\u{feff}').unwrap_or(line);
+        }
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event_type = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = value.to_string(),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(Duration::from_millis(ms));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event_type = std::mem::take(&mut self.event_type);
+        let mut data = std::mem::take(&mut self.data);
+        if data.is_empty() {
+            return None;
+        }
+        data.pop();
+        Some(SseEvent {
+            event: if event_type.is_empty() {
+                DEFAULT_EVENT_TYPE.to_string()
+            } else {
+                event_type
+            },
+            data,
+            id: self.last_event_id().map(str::to_string),
+            retry: self.retry,
+        })
+    }
+}
+
+/// The state of a reconnecting event stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionState {
+    /// A connection was (re-)established.
+    Connected,
+    /// The connection dropped; another attempt follows after `delay`.
+    Reconnecting {
+        /// Consecutive attempts since the last successful connection (1 = first).
+        attempt: u32,
+        delay: Duration,
+    },
+    /// The stream stopped reconnecting and is about to end.
+    GaveUp,
+}
+
+/// An item of a reconnecting event stream: either an event or a change in
+/// the connection state.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum StreamEvent<T> {
+    Event(T),
+    State(ConnectionState),
+}
+
+/// Decodes a stream of byte chunks into a stream of [`SseEvent`]s.
+///
+/// Errors from the underlying stream are passed through and end the stream.
+/// An event still incomplete when the input ends is discarded.
+pub fn decode<S, E>(bytes: S) -> impl Stream<Item = Result<SseEvent, E>> + Send + Sync
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + Sync,
+    E: Send + Sync,
+{
+    async_stream::stream! {
+        let mut decoder = SseDecoder::new();
+        let mut bytes = Box::pin(bytes);
+        while let Some(chunk) = bytes.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    for event in decoder.feed(&chunk) {
+                        yield Ok(event);
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    break;
+                }
+            }
+        }
+    }
+}
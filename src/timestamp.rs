@@ -0,0 +1,236 @@
+//! Timestamps returned by the API.
+//!
+//! Timestamp fields of responses, such as `created_at` or `expires_at`, are
+//! of type [`DateTime`]: `chrono::DateTime<Utc>` with the `chrono` feature,
+//! otherwise `time::OffsetDateTime` with the `time` feature, and
+//! [`Timestamp`] without either. A field the API sends in a form that
+//! cannot be parsed reads as `None` rather than failing the whole response.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::rfc3339;
+
+/// The type of timestamp fields in responses.
+#[cfg(feature = "chrono")]
+pub type DateTime = chrono::DateTime<chrono::Utc>;
+
+/// The type of timestamp fields in responses.
+#[cfg(all(feature = "time", not(feature = "chrono")))]
+pub type DateTime = ::time::OffsetDateTime;
+
+/// The type of timestamp fields in responses. Becomes a `chrono` or `time`
+/// type with the feature of that name.
+#[cfg(not(any(feature = "chrono", feature = "time")))]
+pub type DateTime = Timestamp;
+
+/// An RFC 3339 timestamp, such as a `created_at` or `expires_at` field.
+///
+/// Keeps the string sent by the API, returned by [`as_str`](Self::as_str)
+/// and [`Display`](fmt::Display), along with the instant it denotes.
+/// Timestamps compare by instant. With the `chrono` or `time` feature, they
+/// convert to `chrono::DateTime<Utc>` or `time::OffsetDateTime`, and
+/// response fields use those types instead; see [`DateTime`].
+///
+/// ```
+/// use lnbot::Timestamp;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let t: Timestamp = "2024-01-01T02:00:00+02:00".parse().unwrap();
+/// assert_eq!(t.to_system_time(), UNIX_EPOCH + Duration::from_secs(1_704_067_200));
+/// assert_eq!(t, "2024-01-01T00:00:00Z".parse().unwrap());
+/// ```
+#[derive(Clone)]
+pub struct Timestamp {
+    raw: String,
+    time: SystemTime,
+}
+
+/// Error returned when a string is not an RFC 3339 timestamp.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid RFC 3339 timestamp {0:?}")]
+pub struct ParseTimestampError(String);
+
+impl Timestamp {
+    /// The instant this timestamp denotes.
+    pub fn to_system_time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// The timestamp as sent by the API.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// Whether this instant is at or before `now`.
+    pub fn is_past(&self, now: SystemTime) -> bool {
+        self.time <= now
+    }
+
+    /// Time left from `now` until this instant, or zero if it has passed.
+    pub fn duration_from(&self, now: SystemTime) -> Duration {
+        self.time.duration_since(now).unwrap_or_default()
+    }
+
+    /// Converts to a `chrono` date and time in UTC.
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> chrono::DateTime<chrono::Utc> {
+        self.time.into()
+    }
+
+    /// Converts to a `time` date and time in UTC.
+    #[cfg(feature = "time")]
+    pub fn to_offset_date_time(&self) -> ::time::OffsetDateTime {
+        self.time.into()
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = ParseTimestampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let time = rfc3339::parse(s).ok_or_else(|| ParseTimestampError(s.to_string()))?;
+        Ok(Self {
+            raw: s.to_string(),
+            time,
+        })
+    }
+}
+
+impl fmt::Debug for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.raw, f)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl PartialEq for Timestamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.time == other.time
+    }
+}
+
+impl Eq for Timestamp {}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time.cmp(&other.time)
+    }
+}
+
+impl Hash for Timestamp {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.time.hash(state);
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(t: Timestamp) -> Self {
+        t.time
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::Utc> {
+    fn from(t: Timestamp) -> Self {
+        t.to_chrono()
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<Timestamp> for ::time::OffsetDateTime {
+    fn from(t: Timestamp) -> Self {
+        t.to_offset_date_time()
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A type timestamp fields can be read into.
+pub(crate) trait ApiTime: Sized {
+    /// Parses an RFC 3339 timestamp, or returns `None` if it is malformed.
+    fn parse_rfc3339(s: &str) -> Option<Self>;
+
+    /// The instant this timestamp denotes.
+    fn system_time(&self) -> SystemTime;
+
+    /// Whether this instant is at or before `now`.
+    fn is_at_or_before(&self, now: SystemTime) -> bool {
+        self.system_time() <= now
+    }
+
+    /// Time left from `now` until this instant, or zero if it has passed.
+    fn time_left(&self, now: SystemTime) -> Duration {
+        self.system_time().duration_since(now).unwrap_or_default()
+    }
+}
+
+impl ApiTime for Timestamp {
+    fn parse_rfc3339(s: &str) -> Option<Self> {
+        s.parse().ok()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.time
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl ApiTime for chrono::DateTime<chrono::Utc> {
+    fn parse_rfc3339(s: &str) -> Option<Self> {
+        rfc3339::parse(s).map(Into::into)
+    }
+
+    fn system_time(&self) -> SystemTime {
+        (*self).into()
+    }
+}
+
+#[cfg(feature = "time")]
+impl ApiTime for ::time::OffsetDateTime {
+    fn parse_rfc3339(s: &str) -> Option<Self> {
+        rfc3339::parse(s).map(Into::into)
+    }
+
+    fn system_time(&self) -> SystemTime {
+        (*self).into()
+    }
+}
+
+/// Deserializes an optional timestamp field, reading a malformed one as
+/// `None`.
+pub(crate) fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ApiTime,
+{
+    let s = Option::<String>::deserialize(deserializer)?;
+    Ok(s.as_deref().and_then(T::parse_rfc3339))
+}
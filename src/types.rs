@@ -1,7 +1,17 @@
 //! Request and response types for the LnBot API.
 
+use crate::amount::Sats;
+use crate::bolt11::{Bolt11Error, Bolt11Invoice};
+use crate::errors::LnBotError;
+use crate::ids::{ApiKey, InvoiceNumber, PaymentNumber, TxNumber, WalletId, WalletKey, WebhookId};
+use crate::l402::{L402ParseError, L402Token};
+use crate::macaroon::{Macaroon, MacaroonError};
+use crate::preimage::{PaymentHash, Preimage};
+use crate::secret::Secret;
+use crate::timestamp::{ApiTime, DateTime};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
 
 // ---------------------------------------------------------------------------
 // Account
@@ -13,9 +23,9 @@ use std::collections::HashMap;
 #[non_exhaustive]
 pub struct RegisterResponse {
     pub user_id: String,
-    pub primary_key: String,
-    pub secondary_key: String,
-    pub recovery_passphrase: String,
+    pub primary_key: ApiKey,
+    pub secondary_key: ApiKey,
+    pub recovery_passphrase: Secret<String>,
 }
 
 /// Response from the `/v1/me` identity endpoint.
@@ -25,7 +35,7 @@ pub struct RegisterResponse {
 pub struct MeResponse {
     pub user_id: String,
     pub key_name: Option<String>,
-    pub wallet_id: Option<String>,
+    pub wallet_id: Option<WalletId>,
 }
 
 // ---------------------------------------------------------------------------
@@ -37,11 +47,11 @@ pub struct MeResponse {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct WalletResponse {
-    pub wallet_id: String,
+    pub wallet_id: WalletId,
     pub name: String,
-    pub balance: i64,
-    pub on_hold: i64,
-    pub available: i64,
+    pub balance: Sats,
+    pub on_hold: Sats,
+    pub available: Sats,
 }
 
 /// Parameters for updating a wallet.
@@ -62,7 +72,7 @@ impl UpdateWalletRequest {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct CreateWalletResponse {
-    pub wallet_id: String,
+    pub wallet_id: WalletId,
     pub name: String,
     pub address: String,
 }
@@ -72,7 +82,7 @@ pub struct CreateWalletResponse {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct WalletListItem {
-    pub wallet_id: String,
+    pub wallet_id: WalletId,
     pub name: String,
 }
 
@@ -85,7 +95,7 @@ pub struct WalletListItem {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct WalletKeyResponse {
-    pub key: String,
+    pub key: WalletKey,
 }
 
 /// Response from getting wallet key info.
@@ -94,8 +104,10 @@ pub struct WalletKeyResponse {
 #[non_exhaustive]
 pub struct WalletKeyInfoResponse {
     pub hint: String,
-    pub created_at: Option<String>,
-    pub last_used_at: Option<String>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub created_at: Option<DateTime>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub last_used_at: Option<DateTime>,
 }
 
 // ---------------------------------------------------------------------------
@@ -107,7 +119,7 @@ pub struct WalletKeyInfoResponse {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct RotateApiKeyResponse {
-    pub key: String,
+    pub key: ApiKey,
     pub name: String,
 }
 
@@ -127,11 +139,65 @@ pub enum InvoiceStatus {
     Unknown,
 }
 
+impl InvoiceStatus {
+    /// Returns `true` for `Settled` and `Expired`, the states an invoice never leaves.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Settled | Self::Expired)
+    }
+}
+
+/// Identifies an invoice either by its number or by its payment hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvoiceRef {
+    Number(InvoiceNumber),
+    Hash(String),
+}
+
+impl From<InvoiceNumber> for InvoiceRef {
+    fn from(number: InvoiceNumber) -> Self {
+        Self::Number(number)
+    }
+}
+
+impl From<i32> for InvoiceRef {
+    fn from(number: i32) -> Self {
+        Self::Number(number.into())
+    }
+}
+
+impl From<&str> for InvoiceRef {
+    fn from(payment_hash: &str) -> Self {
+        Self::Hash(payment_hash.to_string())
+    }
+}
+
+impl From<String> for InvoiceRef {
+    fn from(payment_hash: String) -> Self {
+        Self::Hash(payment_hash)
+    }
+}
+
+impl From<PaymentHash> for InvoiceRef {
+    fn from(payment_hash: PaymentHash) -> Self {
+        Self::Hash(payment_hash.to_hex())
+    }
+}
+
+impl std::fmt::Display for InvoiceRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{}", number),
+            Self::Hash(hash) => f.write_str(hash),
+        }
+    }
+}
+
 /// Parameters for creating a new invoice.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateInvoiceRequest {
-    pub amount: i64,
+    pub amount: Sats,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reference: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -140,9 +206,9 @@ pub struct CreateInvoiceRequest {
 
 impl CreateInvoiceRequest {
     /// Creates a new invoice request for the given amount in sats.
-    pub fn new(amount: i64) -> Self {
+    pub fn new(amount: impl Into<Sats>) -> Self {
         Self {
-            amount,
+            amount: amount.into(),
             reference: None,
             memo: None,
         }
@@ -161,6 +227,10 @@ impl CreateInvoiceRequest {
         self.reference = Some(reference.into());
         self
     }
+
+    pub(crate) fn validate(&self) -> Result<(), LnBotError> {
+        positive("amount", self.amount)
+    }
 }
 
 /// An invoice returned by the API.
@@ -168,25 +238,59 @@ impl CreateInvoiceRequest {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct InvoiceResponse {
-    pub number: i32,
+    pub number: InvoiceNumber,
     pub status: InvoiceStatus,
-    pub amount: i64,
+    pub amount: Sats,
     pub bolt11: String,
     pub reference: Option<String>,
     pub memo: Option<String>,
     pub preimage: Option<String>,
-    pub tx_number: Option<i32>,
-    pub created_at: Option<String>,
-    pub settled_at: Option<String>,
-    pub expires_at: Option<String>,
+    pub tx_number: Option<TxNumber>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub created_at: Option<DateTime>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub settled_at: Option<DateTime>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub expires_at: Option<DateTime>,
+}
+
+impl InvoiceResponse {
+    /// Decodes [`bolt11`](Self::bolt11) locally, without calling the API.
+    pub fn decode_bolt11(&self) -> Result<Bolt11Invoice, Bolt11Error> {
+        self.bolt11.parse()
+    }
+
+    /// Checks that [`preimage`](Self::preimage) hashes to the payment hash in
+    /// [`bolt11`](Self::bolt11). Returns `Ok(false)` when there is no preimage yet.
+    pub fn verify_preimage(&self) -> Result<bool, LnBotError> {
+        verify_preimage(self.preimage.as_deref(), || {
+            Ok(self.decode_bolt11()?.payment_hash)
+        })
+    }
+
+    /// Whether the invoice expired at or before `now`. Invoices without an
+    /// expiry time never expire.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .as_ref()
+            .is_some_and(|at| at.is_at_or_before(now))
+    }
+
+    /// Time left until the invoice expires, zero once it has, or `None`
+    /// without an expiry time.
+    pub fn time_to_expiry(&self) -> Option<Duration> {
+        self.expires_at
+            .as_ref()
+            .map(|at| at.time_left(SystemTime::now()))
+    }
 }
 
 /// Parameters for creating an invoice for a specific wallet (unauthenticated).
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateInvoiceForWalletRequest {
-    pub wallet_id: String,
-    pub amount: i64,
+    pub wallet_id: WalletId,
+    pub amount: Sats,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reference: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -194,10 +298,10 @@ pub struct CreateInvoiceForWalletRequest {
 }
 
 impl CreateInvoiceForWalletRequest {
-    pub fn new(wallet_id: impl Into<String>, amount: i64) -> Self {
+    pub fn new(wallet_id: impl Into<WalletId>, amount: impl Into<Sats>) -> Self {
         Self {
             wallet_id: wallet_id.into(),
-            amount,
+            amount: amount.into(),
             reference: None,
             comment: None,
         }
@@ -214,6 +318,10 @@ impl CreateInvoiceForWalletRequest {
         self.comment = Some(comment.into());
         self
     }
+
+    pub(crate) fn validate(&self) -> Result<(), LnBotError> {
+        positive("amount", self.amount)
+    }
 }
 
 /// Parameters for creating an invoice for a Lightning address (unauthenticated).
@@ -221,7 +329,7 @@ impl CreateInvoiceForWalletRequest {
 #[serde(rename_all = "camelCase")]
 pub struct CreateInvoiceForAddressRequest {
     pub address: String,
-    pub amount: i64,
+    pub amount: Sats,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tag: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -229,10 +337,10 @@ pub struct CreateInvoiceForAddressRequest {
 }
 
 impl CreateInvoiceForAddressRequest {
-    pub fn new(address: impl Into<String>, amount: i64) -> Self {
+    pub fn new(address: impl Into<String>, amount: impl Into<Sats>) -> Self {
         Self {
             address: address.into(),
-            amount,
+            amount: amount.into(),
             tag: None,
             comment: None,
         }
@@ -249,6 +357,10 @@ impl CreateInvoiceForAddressRequest {
         self.comment = Some(comment.into());
         self
     }
+
+    pub(crate) fn validate(&self) -> Result<(), LnBotError> {
+        positive("amount", self.amount)
+    }
 }
 
 /// An invoice created via wallet ID or Lightning address.
@@ -257,8 +369,32 @@ impl CreateInvoiceForAddressRequest {
 #[non_exhaustive]
 pub struct AddressInvoiceResponse {
     pub bolt11: String,
-    pub amount: i64,
-    pub expires_at: Option<String>,
+    pub amount: Sats,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub expires_at: Option<DateTime>,
+}
+
+impl AddressInvoiceResponse {
+    /// Decodes [`bolt11`](Self::bolt11) locally, without calling the API.
+    pub fn decode_bolt11(&self) -> Result<Bolt11Invoice, Bolt11Error> {
+        self.bolt11.parse()
+    }
+
+    /// Whether the invoice expired at or before `now`. Invoices without an
+    /// expiry time never expire.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .as_ref()
+            .is_some_and(|at| at.is_at_or_before(now))
+    }
+
+    /// Time left until the invoice expires, zero once it has, or `None`
+    /// without an expiry time.
+    pub fn time_to_expiry(&self) -> Option<Duration> {
+        self.expires_at
+            .as_ref()
+            .map(|at| at.time_left(SystemTime::now()))
+    }
 }
 
 /// Pagination parameters for list endpoints.
@@ -326,17 +462,24 @@ pub enum PaymentStatus {
     Unknown,
 }
 
+impl PaymentStatus {
+    /// Returns `true` for `Settled` and `Failed`, the states a payment never leaves.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Settled | Self::Failed)
+    }
+}
+
 /// Parameters for creating a new payment.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreatePaymentRequest {
     pub target: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub amount: Option<i64>,
+    pub amount: Option<Sats>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub idempotency_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub max_fee: Option<i64>,
+    pub max_fee: Option<Sats>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reference: Option<String>,
 }
@@ -353,8 +496,8 @@ impl CreatePaymentRequest {
     }
 
     #[must_use]
-    pub fn amount(mut self, amount: i64) -> Self {
-        self.amount = Some(amount);
+    pub fn amount(mut self, amount: impl Into<Sats>) -> Self {
+        self.amount = Some(amount.into());
         self
     }
 
@@ -365,8 +508,8 @@ impl CreatePaymentRequest {
     }
 
     #[must_use]
-    pub fn max_fee(mut self, fee: i64) -> Self {
-        self.max_fee = Some(fee);
+    pub fn max_fee(mut self, fee: impl Into<Sats>) -> Self {
+        self.max_fee = Some(fee.into());
         self
     }
 
@@ -375,6 +518,16 @@ impl CreatePaymentRequest {
         self.reference = Some(reference.into());
         self
     }
+
+    pub(crate) fn validate(&self) -> Result<(), LnBotError> {
+        if let Some(amount) = self.amount {
+            positive("amount", amount)?;
+        }
+        if let Some(max_fee) = self.max_fee {
+            non_negative("max fee", max_fee)?;
+        }
+        Ok(())
+    }
 }
 
 /// A payment returned by the API.
@@ -382,19 +535,36 @@ impl CreatePaymentRequest {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct PaymentResponse {
-    pub number: i32,
+    pub number: PaymentNumber,
     pub status: PaymentStatus,
-    pub amount: i64,
-    pub max_fee: i64,
-    pub service_fee: i64,
-    pub actual_fee: Option<i64>,
+    pub amount: Sats,
+    pub max_fee: Sats,
+    pub service_fee: Sats,
+    pub actual_fee: Option<Sats>,
     pub address: String,
     pub reference: Option<String>,
     pub preimage: Option<String>,
-    pub tx_number: Option<i32>,
+    pub tx_number: Option<TxNumber>,
     pub failure_reason: Option<String>,
-    pub created_at: Option<String>,
-    pub settled_at: Option<String>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub created_at: Option<DateTime>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub settled_at: Option<DateTime>,
+    /// Idempotency key the payment was created with. Set by
+    /// [`PaymentsResource::create`](crate::resources::PaymentsResource::create),
+    /// which generates one when the request has none.
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+impl PaymentResponse {
+    /// Checks that [`preimage`](Self::preimage) hashes to the payment hash in
+    /// the paid invoice. Returns `Ok(false)` when there is no preimage yet.
+    pub fn verify_preimage(&self, bolt11: &str) -> Result<bool, LnBotError> {
+        verify_preimage(self.preimage.as_deref(), || {
+            Ok(Bolt11Invoice::parse(bolt11)?.payment_hash)
+        })
+    }
 }
 
 /// Response from resolving a payment target.
@@ -405,7 +575,7 @@ pub struct ResolveTargetResponse {
     pub target: String,
     #[serde(rename = "type")]
     pub target_type: String,
-    pub amount: Option<i64>,
+    pub amount: Option<Sats>,
     pub description: Option<String>,
 }
 
@@ -455,19 +625,20 @@ pub struct CreateAddressRequest {
 pub struct AddressResponse {
     pub address: String,
     pub generated: bool,
-    pub cost: i64,
-    pub created_at: Option<String>,
+    pub cost: Sats,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub created_at: Option<DateTime>,
 }
 
 /// Parameters for transferring a Lightning address to another wallet.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TransferAddressRequest {
-    pub target_wallet_key: String,
+    pub target_wallet_key: WalletKey,
 }
 
 impl TransferAddressRequest {
-    pub fn new(target_wallet_key: impl Into<String>) -> Self {
+    pub fn new(target_wallet_key: impl Into<WalletKey>) -> Self {
         Self {
             target_wallet_key: target_wallet_key.into(),
         }
@@ -503,18 +674,30 @@ pub enum TransactionType {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct TransactionResponse {
-    pub number: i32,
+    pub number: TxNumber,
     #[serde(rename = "type")]
     pub tx_type: TransactionType,
-    pub amount: i64,
-    pub balance_after: i64,
-    pub network_fee: i64,
-    pub service_fee: i64,
+    pub amount: Sats,
+    pub balance_after: Sats,
+    pub network_fee: Sats,
+    pub service_fee: Sats,
     pub payment_hash: Option<String>,
     pub preimage: Option<String>,
     pub reference: Option<String>,
     pub note: Option<String>,
-    pub created_at: Option<String>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub created_at: Option<DateTime>,
+}
+
+impl TransactionResponse {
+    /// Checks that [`preimage`](Self::preimage) hashes to
+    /// [`payment_hash`](Self::payment_hash). Returns `Ok(false)` when either is missing.
+    pub fn verify_preimage(&self) -> Result<bool, LnBotError> {
+        match &self.payment_hash {
+            Some(hash) => verify_preimage(self.preimage.as_deref(), || Ok(hash.parse()?)),
+            None => Ok(false),
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -539,10 +722,11 @@ impl CreateWebhookRequest {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct CreateWebhookResponse {
-    pub id: String,
+    pub id: WebhookId,
     pub url: String,
-    pub secret: String,
-    pub created_at: Option<String>,
+    pub secret: Secret<String>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub created_at: Option<DateTime>,
 }
 
 /// A webhook returned by the API.
@@ -550,10 +734,11 @@ pub struct CreateWebhookResponse {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct WebhookResponse {
-    pub id: String,
+    pub id: WebhookId,
     pub url: String,
     pub active: bool,
-    pub created_at: Option<String>,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub created_at: Option<DateTime>,
 }
 
 // ---------------------------------------------------------------------------
@@ -561,15 +746,118 @@ pub struct WebhookResponse {
 // ---------------------------------------------------------------------------
 
 /// A real-time event from the wallet event stream.
+///
+/// Use [`kind`](WalletEvent::kind) or [`into_typed`](WalletEvent::into_typed)
+/// to get a strongly typed payload.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct WalletEvent {
     pub event: String,
-    pub created_at: String,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub created_at: Option<DateTime>,
     pub data: serde_json::Value,
 }
 
+impl WalletEvent {
+    /// Parses the payload according to the event name.
+    pub fn kind(&self) -> Result<WalletEventKind, serde_json::Error> {
+        WalletEventKind::from_parts(&self.event, self.data.clone())
+    }
+
+    /// Converts into a [`TypedWalletEvent`], consuming the raw payload.
+    pub fn into_typed(self) -> Result<TypedWalletEvent, serde_json::Error> {
+        Ok(TypedWalletEvent {
+            kind: WalletEventKind::from_parts(&self.event, self.data)?,
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// The typed payload of a wallet event, keyed by event name.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum WalletEventKind {
+    /// `invoice.created`
+    InvoiceCreated(InvoiceResponse),
+    /// `invoice.settled`
+    InvoiceSettled(InvoiceResponse),
+    /// `invoice.expired`
+    InvoiceExpired(InvoiceResponse),
+    /// `payment.created`
+    PaymentCreated(PaymentResponse),
+    /// `payment.settled`
+    PaymentSettled(PaymentResponse),
+    /// `payment.failed`
+    PaymentFailed(PaymentResponse),
+    /// `address.created`
+    AddressCreated(AddressResponse),
+    /// `address.deleted`
+    AddressDeleted(AddressResponse),
+    /// `address.transferred`
+    AddressTransferred(TransferAddressResponse),
+    /// An event this version of the SDK does not know about.
+    Unknown {
+        event: String,
+        data: serde_json::Value,
+    },
+}
+
+impl WalletEventKind {
+    /// Builds a typed payload from an event name and its raw JSON data.
+    /// Unrecognised names produce [`WalletEventKind::Unknown`].
+    pub fn from_parts(event: &str, data: serde_json::Value) -> Result<Self, serde_json::Error> {
+        use serde_json::from_value;
+        Ok(match event {
+            "invoice.created" => Self::InvoiceCreated(from_value(data)?),
+            "invoice.settled" => Self::InvoiceSettled(from_value(data)?),
+            "invoice.expired" => Self::InvoiceExpired(from_value(data)?),
+            "payment.created" => Self::PaymentCreated(from_value(data)?),
+            "payment.settled" => Self::PaymentSettled(from_value(data)?),
+            "payment.failed" => Self::PaymentFailed(from_value(data)?),
+            "address.created" => Self::AddressCreated(from_value(data)?),
+            "address.deleted" => Self::AddressDeleted(from_value(data)?),
+            "address.transferred" => Self::AddressTransferred(from_value(data)?),
+            other => Self::Unknown {
+                event: other.to_string(),
+                data,
+            },
+        })
+    }
+
+    /// Returns the event name, e.g. `"invoice.settled"`.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::InvoiceCreated(_) => "invoice.created",
+            Self::InvoiceSettled(_) => "invoice.settled",
+            Self::InvoiceExpired(_) => "invoice.expired",
+            Self::PaymentCreated(_) => "payment.created",
+            Self::PaymentSettled(_) => "payment.settled",
+            Self::PaymentFailed(_) => "payment.failed",
+            Self::AddressCreated(_) => "address.created",
+            Self::AddressDeleted(_) => "address.deleted",
+            Self::AddressTransferred(_) => "address.transferred",
+            Self::Unknown { event, .. } => event,
+        }
+    }
+}
+
+/// A wallet event with a strongly typed payload.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct TypedWalletEvent {
+    pub kind: WalletEventKind,
+    pub created_at: Option<DateTime>,
+}
+
+impl TryFrom<WalletEvent> for TypedWalletEvent {
+    type Error = serde_json::Error;
+
+    fn try_from(event: WalletEvent) -> Result<Self, Self::Error> {
+        event.into_typed()
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Backup / Restore
 // ---------------------------------------------------------------------------
@@ -578,17 +866,17 @@ pub struct WalletEvent {
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 #[non_exhaustive]
 pub struct RecoveryBackupResponse {
-    pub passphrase: String,
+    pub passphrase: Secret<String>,
 }
 
 /// Parameters for restoring a wallet from a recovery passphrase.
 #[derive(Debug, Clone, Serialize)]
 pub struct RecoveryRestoreRequest {
-    pub passphrase: String,
+    pub passphrase: Secret<String>,
 }
 
 impl RecoveryRestoreRequest {
-    pub fn new(passphrase: impl Into<String>) -> Self {
+    pub fn new(passphrase: impl Into<Secret<String>>) -> Self {
         Self {
             passphrase: passphrase.into(),
         }
@@ -600,10 +888,10 @@ impl RecoveryRestoreRequest {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct RecoveryRestoreResponse {
-    pub wallet_id: String,
+    pub wallet_id: WalletId,
     pub name: String,
-    pub primary_key: String,
-    pub secondary_key: String,
+    pub primary_key: ApiKey,
+    pub secondary_key: ApiKey,
 }
 
 /// Response from beginning a passkey backup flow.
@@ -645,10 +933,10 @@ pub struct RestorePasskeyCompleteRequest {
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct RestorePasskeyCompleteResponse {
-    pub wallet_id: String,
+    pub wallet_id: WalletId,
     pub name: String,
-    pub primary_key: String,
-    pub secondary_key: String,
+    pub primary_key: ApiKey,
+    pub secondary_key: ApiKey,
 }
 
 // ---------------------------------------------------------------------------
@@ -658,7 +946,7 @@ pub struct RestorePasskeyCompleteResponse {
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateL402ChallengeRequest {
-    pub amount: i64,
+    pub amount: Sats,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -667,20 +955,42 @@ pub struct CreateL402ChallengeRequest {
     pub caveats: Option<Vec<String>>,
 }
 
+impl CreateL402ChallengeRequest {
+    pub(crate) fn validate(&self) -> Result<(), LnBotError> {
+        positive("amount", self.amount)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct L402ChallengeResponse {
     pub macaroon: String,
     pub invoice: String,
     pub payment_hash: String,
-    pub expires_at: String,
+    #[serde(default, deserialize_with = "crate::timestamp::lenient")]
+    pub expires_at: Option<DateTime>,
     pub www_authenticate: String,
 }
 
+impl L402ChallengeResponse {
+    /// Decodes [`macaroon`](Self::macaroon) locally, without calling the API.
+    pub fn decode_macaroon(&self) -> Result<Macaroon, MacaroonError> {
+        Macaroon::decode(&self.macaroon)
+    }
+
+    /// Whether the challenge expired at or before `now`. Challenges
+    /// without an expiry time never expire.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .as_ref()
+            .is_some_and(|at| at.is_at_or_before(now))
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VerifyL402Request {
-    pub authorization: String,
+    pub authorization: Secret<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -697,7 +1007,7 @@ pub struct VerifyL402Response {
 pub struct PayL402Request {
     pub www_authenticate: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub max_fee: Option<i64>,
+    pub max_fee: Option<Sats>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reference: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -706,14 +1016,66 @@ pub struct PayL402Request {
     pub timeout: Option<i32>,
 }
 
+impl PayL402Request {
+    pub(crate) fn validate(&self) -> Result<(), LnBotError> {
+        match self.max_fee {
+            Some(max_fee) => non_negative("max fee", max_fee),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct L402PayResponse {
-    pub authorization: Option<String>,
+    pub authorization: Option<Secret<String>>,
     pub payment_hash: String,
     pub preimage: Option<String>,
-    pub amount: i64,
-    pub fee: Option<i64>,
-    pub payment_number: i32,
+    pub amount: Sats,
+    pub fee: Option<Sats>,
+    pub payment_number: PaymentNumber,
     pub status: String,
 }
+
+impl L402PayResponse {
+    /// Parses [`authorization`](Self::authorization), if present.
+    pub fn token(&self) -> Option<Result<L402Token, L402ParseError>> {
+        self.authorization
+            .as_ref()
+            .map(|authorization| L402Token::parse(authorization.expose_secret()))
+    }
+
+    /// Checks that [`preimage`](Self::preimage) hashes to
+    /// [`payment_hash`](Self::payment_hash). Returns `Ok(false)` when there is no preimage.
+    pub fn verify_preimage(&self) -> Result<bool, LnBotError> {
+        verify_preimage(self.preimage.as_deref(), || {
+            Ok(self.payment_hash.parse()?)
+        })
+    }
+}
+
+fn verify_preimage(
+    preimage: Option<&str>,
+    payment_hash: impl FnOnce() -> Result<PaymentHash, LnBotError>,
+) -> Result<bool, LnBotError> {
+    match preimage {
+        Some(preimage) => Ok(preimage.parse::<Preimage>()?.verify(&payment_hash()?)),
+        None => Ok(false),
+    }
+}
+
+fn positive(field: &'static str, amount: Sats) -> Result<(), LnBotError> {
+    if amount.is_positive() {
+        Ok(())
+    } else {
+        Err(LnBotError::InvalidAmount { field, amount })
+    }
+}
+
+fn non_negative(field: &'static str, amount: Sats) -> Result<(), LnBotError> {
+    if amount.is_negative() {
+        Err(LnBotError::InvalidAmount { field, amount })
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,479 @@
+//! Verification and handling of incoming webhook deliveries.
+//!
+//! By default, a delivery is expected to carry two headers:
+//!
+//! - [`TIMESTAMP_HEADER`]: the Unix time, in seconds, at which it was sent.
+//! - [`SIGNATURE_HEADER`]: `v1=<hex>`, the HMAC-SHA256 of
+//!   `"{timestamp}.{raw body}"` keyed with the webhook's secret from
+//!   [`CreateWebhookResponse`](crate::CreateWebhookResponse). Several
+//!   comma-separated signatures may be present while secrets are rotated.
+//!
+//! The OpenAPI description does not specify these names or the signed
+//! string, so they are the SDK's defaults rather than a documented contract.
+//! If your deliveries use different ones, describe them with a
+//! [`SignatureFormat`] and pass it to [`SignatureFormat::verify`] or
+//! [`WebhookReceiver::format`].
+//!
+//! [`verify`] checks the signature in constant time and rejects deliveries
+//! whose timestamp is outside the tolerance, so a captured request cannot be
+//! replayed later.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::{Duration, SystemTime, UNIX_EPOCH};
+//! use lnbot::webhooks::{self, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+//! use lnbot::WalletEventKind;
+//! use reqwest::header::HeaderMap;
+//!
+//! # let secret = "whsec_test";
+//! # let body = br#"{"event":"wallet.renamed","createdAt":"2024-01-01T00:00:00Z","data":{}}"#;
+//! # let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+//! # let mut headers = HeaderMap::new();
+//! # headers.insert(TIMESTAMP_HEADER, now.to_string().parse().unwrap());
+//! # headers.insert(SIGNATURE_HEADER, webhooks::sign(secret, now, body).parse().unwrap());
+//! let event = webhooks::verify(secret, &headers, body, webhooks::DEFAULT_TOLERANCE)?;
+//! if let WalletEventKind::InvoiceSettled(invoice) = event.kind {
+//!     println!("invoice {} paid", invoice.number);
+//! }
+//! # Ok::<(), lnbot::webhooks::WebhookError>(())
+//! ```
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures_util::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+
+use crate::secret::Secret;
+use crate::types::{TypedWalletEvent, WalletEvent};
+
+#[cfg(feature = "axum")]
+mod axum;
+#[cfg(feature = "axum")]
+pub use self::axum::{VerifiedWebhook, WebhookRejection};
+
+/// Header carrying the delivery's Unix timestamp in seconds.
+pub const TIMESTAMP_HEADER: &str = "x-lnbot-timestamp";
+
+/// Header carrying one or more `v1=<hex>` signatures.
+pub const SIGNATURE_HEADER: &str = "x-lnbot-signature";
+
+/// Default maximum difference between a delivery's timestamp and the local clock.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// Default number of recent deliveries remembered by [`WebhookReceiver`].
+pub const DEFAULT_DEDUP_CAPACITY: usize = 1024;
+
+const SCHEME: &str = "v1=";
+
+/// Error returned when a webhook delivery fails verification.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum WebhookError {
+    #[error("missing or malformed {0} header")]
+    MissingHeader(&'static str),
+
+    #[error("timestamp is {0:?} away from the local clock")]
+    OutsideTolerance(Duration),
+
+    #[error("no signature matches")]
+    SignatureMismatch,
+
+    #[error("invalid payload: {0}")]
+    InvalidPayload(#[from] serde_json::Error),
+}
+
+/// Verifies a delivery signed with `secret` and parses its payload.
+///
+/// `body` must be the raw request body, exactly as received.
+pub fn verify(
+    secret: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+    tolerance: Duration,
+) -> Result<TypedWalletEvent, WebhookError> {
+    verify_with_secrets(&[secret], headers, body, tolerance)
+}
+
+/// Like [`verify`], accepting a delivery signed with any of `secrets`.
+/// Useful while rotating from an old secret to a new one.
+pub fn verify_with_secrets<S: AsRef<str>>(
+    secrets: &[S],
+    headers: &HeaderMap,
+    body: &[u8],
+    tolerance: Duration,
+) -> Result<TypedWalletEvent, WebhookError> {
+    SignatureFormat::DEFAULT.verify(secrets, headers, body, tolerance)
+}
+
+/// Computes the [`SIGNATURE_HEADER`] value for a delivery. Mostly useful
+/// for testing webhook handlers.
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    SignatureFormat::DEFAULT.sign(secret, timestamp, body)
+}
+
+/// Where a delivery's timestamp and signatures are found, and what is signed.
+///
+/// The signature is the HMAC-SHA256 of `"{timestamp}{separator}{raw body}"`,
+/// hex-encoded after `scheme`. [`SignatureFormat::DEFAULT`] matches
+/// [`TIMESTAMP_HEADER`], [`SIGNATURE_HEADER`], `v1=` and `.`.
+///
+/// ```
+/// use lnbot::webhooks::SignatureFormat;
+///
+/// let format = SignatureFormat::DEFAULT
+///     .timestamp_header("webhook-timestamp")
+///     .signature_header("webhook-signature");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureFormat {
+    timestamp_header: &'static str,
+    signature_header: &'static str,
+    scheme: &'static str,
+    separator: &'static str,
+}
+
+impl Default for SignatureFormat {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl SignatureFormat {
+    /// The SDK's default format.
+    pub const DEFAULT: Self = Self {
+        timestamp_header: TIMESTAMP_HEADER,
+        signature_header: SIGNATURE_HEADER,
+        scheme: SCHEME,
+        separator: ".",
+    };
+
+    /// Sets the header carrying the Unix timestamp in seconds.
+    #[must_use]
+    pub const fn timestamp_header(mut self, name: &'static str) -> Self {
+        self.timestamp_header = name;
+        self
+    }
+
+    /// Sets the header carrying the comma-separated signatures.
+    #[must_use]
+    pub const fn signature_header(mut self, name: &'static str) -> Self {
+        self.signature_header = name;
+        self
+    }
+
+    /// Sets the prefix in front of each hex signature, such as `v1=`.
+    #[must_use]
+    pub const fn scheme(mut self, scheme: &'static str) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// Sets what goes between the timestamp and the body in the signed string.
+    #[must_use]
+    pub const fn separator(mut self, separator: &'static str) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Like [`verify_with_secrets`], reading the delivery in this format.
+    pub fn verify<S: AsRef<str>>(
+        &self,
+        secrets: &[S],
+        headers: &HeaderMap,
+        body: &[u8],
+        tolerance: Duration,
+    ) -> Result<TypedWalletEvent, WebhookError> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .ok_or(WebhookError::MissingHeader(name))
+        };
+        let timestamp: u64 = header(self.timestamp_header)?
+            .trim()
+            .parse()
+            .map_err(|_| WebhookError::MissingHeader(self.timestamp_header))?;
+        let signatures: Vec<Vec<u8>> = header(self.signature_header)?
+            .split(',')
+            .filter_map(|s| s.trim().strip_prefix(self.scheme))
+            .filter_map(|s| hex::decode(s).ok())
+            .collect();
+
+        // A timestamp too large for `SystemTime` can't be within tolerance.
+        let sent = UNIX_EPOCH
+            .checked_add(Duration::from_secs(timestamp))
+            .ok_or(WebhookError::OutsideTolerance(Duration::MAX))?;
+        let now = SystemTime::now();
+        let skew = now
+            .duration_since(sent)
+            .or_else(|_| sent.duration_since(now))
+            .unwrap_or_default();
+        if skew > tolerance {
+            return Err(WebhookError::OutsideTolerance(skew));
+        }
+
+        let matches = secrets.iter().any(|secret| {
+            let mac = self.mac(secret.as_ref(), timestamp, body);
+            signatures
+                .iter()
+                .any(|signature| mac.clone().verify_slice(signature).is_ok())
+        });
+        if !matches {
+            return Err(WebhookError::SignatureMismatch);
+        }
+
+        let event: WalletEvent = serde_json::from_slice(body)?;
+        Ok(event.into_typed()?)
+    }
+
+    /// Computes the signature header value for a delivery in this format.
+    pub fn sign(&self, secret: &str, timestamp: u64, body: &[u8]) -> String {
+        let tag = self.mac(secret, timestamp, body).finalize().into_bytes();
+        format!("{}{}", self.scheme, hex::encode(tag))
+    }
+
+    fn mac(&self, secret: &str, timestamp: u64, body: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any size");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(self.separator.as_bytes());
+        mac.update(body);
+        mac
+    }
+}
+
+type Handler = Arc<dyn Fn(TypedWalletEvent) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// A ready-made webhook endpoint: verifies deliveries, drops redeliveries
+/// and dispatches events to registered callbacks.
+///
+/// [`handle`](WebhookReceiver::handle) works with any HTTP framework. With
+/// the `axum` feature, `into_router` mounts it in one line.
+///
+/// Redeliveries are recognised by their body, which stays the same when a
+/// delivery is retried, among the last [`DEFAULT_DEDUP_CAPACITY`] verified
+/// deliveries (see [`dedup_capacity`](WebhookReceiver::dedup_capacity)).
+/// A delivery whose callbacks panic or are cancelled is forgotten, so its
+/// redelivery is processed again.
+///
+/// # Examples
+///
+/// ```
+/// use lnbot::webhooks::WebhookReceiver;
+/// use lnbot::WalletEventKind;
+///
+/// let receiver = WebhookReceiver::new("whsec_...").on_event(|event| async move {
+///     if let WalletEventKind::PaymentFailed(payment) = event.kind {
+///         eprintln!("payment {} failed", payment.number);
+///     }
+/// });
+/// ```
+#[derive(Clone)]
+pub struct WebhookReceiver {
+    secrets: Vec<Secret<String>>,
+    format: SignatureFormat,
+    tolerance: Duration,
+    seen: Arc<Mutex<Seen>>,
+    handlers: Vec<Handler>,
+}
+
+impl WebhookReceiver {
+    /// Creates a receiver accepting deliveries signed with `secret`.
+    pub fn new(secret: impl Into<Secret<String>>) -> Self {
+        Self {
+            secrets: vec![secret.into()],
+            format: SignatureFormat::DEFAULT,
+            tolerance: DEFAULT_TOLERANCE,
+            seen: Arc::new(Mutex::new(Seen::new(DEFAULT_DEDUP_CAPACITY))),
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Also accepts deliveries signed with `secret`, e.g. while rotating.
+    #[must_use]
+    pub fn secret(mut self, secret: impl Into<Secret<String>>) -> Self {
+        self.secrets.push(secret.into());
+        self
+    }
+
+    /// Sets how deliveries are signed. Defaults to [`SignatureFormat::DEFAULT`].
+    #[must_use]
+    pub fn format(mut self, format: SignatureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the maximum allowed clock difference. Defaults to [`DEFAULT_TOLERANCE`].
+    #[must_use]
+    pub fn tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets how many recent deliveries are remembered to detect redeliveries.
+    #[must_use]
+    pub fn dedup_capacity(mut self, capacity: usize) -> Self {
+        self.seen = Arc::new(Mutex::new(Seen::new(capacity)));
+        self
+    }
+
+    /// Registers a callback run for every new, verified event. Callbacks
+    /// run in registration order before the delivery is acknowledged. If one
+    /// panics, the delivery is forgotten and the panic is propagated, so the
+    /// sender's retry is processed again.
+    #[must_use]
+    pub fn on_event<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(TypedWalletEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.handlers
+            .push(Arc::new(move |event| Box::pin(handler(event))));
+        self
+    }
+
+    /// Verifies a delivery and checks it against recent ones. Returns
+    /// `Ok(None)` for a redelivery.
+    ///
+    /// The delivery is recorded as seen. If processing the event fails, call
+    /// [`forget`](Self::forget) so that its redelivery is accepted.
+    pub fn verify(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Option<TypedWalletEvent>, WebhookError> {
+        let secrets: Vec<&str> = self
+            .secrets
+            .iter()
+            .map(|s| s.expose_secret().as_str())
+            .collect();
+        let event = self
+            .format
+            .verify(&secrets, headers, body, self.tolerance)?;
+        let fresh = self.seen().insert(delivery_key(body));
+        Ok(fresh.then_some(event))
+    }
+
+    /// Forgets a delivery recorded by [`verify`](Self::verify), so that a
+    /// redelivery of the same body is accepted again.
+    pub fn forget(&self, body: &[u8]) {
+        self.seen().remove(&delivery_key(body));
+    }
+
+    /// Processes a delivery end to end and returns the status to respond with:
+    /// `200` once handled or for a redelivery, `401` for a bad signature or
+    /// timestamp, `400` for an unreadable payload.
+    pub async fn handle(&self, headers: &HeaderMap, body: &[u8]) -> StatusCode {
+        match self.verify(headers, body) {
+            Ok(Some(event)) => {
+                // Forgets the delivery if a handler panics or this future is dropped.
+                let mut pending = Pending {
+                    receiver: self,
+                    body,
+                    handled: false,
+                };
+                for handler in &self.handlers {
+                    handler(event.clone()).await;
+                }
+                pending.handled = true;
+                StatusCode::OK
+            }
+            Ok(None) => StatusCode::OK,
+            Err(e) => e.status(),
+        }
+    }
+
+    fn seen(&self) -> std::sync::MutexGuard<'_, Seen> {
+        self.seen
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A delivery being handled, forgotten on drop unless handling completes.
+struct Pending<'a> {
+    receiver: &'a WebhookReceiver,
+    body: &'a [u8],
+    handled: bool,
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if !self.handled {
+            self.receiver.forget(self.body);
+        }
+    }
+}
+
+fn delivery_key(body: &[u8]) -> [u8; 32] {
+    Sha256::digest(body).into()
+}
+
+impl fmt::Debug for WebhookReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookReceiver")
+            .field("secrets", &self.secrets.len())
+            .field("format", &self.format)
+            .field("tolerance", &self.tolerance)
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+impl WebhookError {
+    /// The HTTP status a receiver should answer with.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+/// Bounded set of recently seen delivery keys, evicting the oldest first.
+struct Seen {
+    capacity: usize,
+    order: VecDeque<[u8; 32]>,
+    keys: HashSet<[u8; 32]>,
+}
+
+impl Seen {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::new(),
+            keys: HashSet::new(),
+        }
+    }
+
+    /// Records `key` and returns `true` if it was not seen before.
+    fn insert(&mut self, key: [u8; 32]) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+        if !self.keys.insert(key) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    fn remove(&mut self, key: &[u8; 32]) {
+        if self.keys.remove(key) {
+            self.order.retain(|k| k != key);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! [axum] integration, enabled with the `axum` feature.
+
+use ::axum::body::Bytes;
+use ::axum::extract::{FromRef, FromRequest, Request, State};
+use ::axum::http::{HeaderMap, StatusCode};
+use ::axum::response::{IntoResponse, Response};
+use ::axum::routing::post;
+use ::axum::Router;
+
+use super::{WebhookError, WebhookReceiver};
+use crate::types::TypedWalletEvent;
+
+impl WebhookReceiver {
+    /// Returns a router answering `POST path` with [`handle`](WebhookReceiver::handle).
+    ///
+    /// ```no_run
+    /// use lnbot::webhooks::WebhookReceiver;
+    ///
+    /// let receiver = WebhookReceiver::new("whsec_...").on_event(|event| async move {
+    ///     println!("{:?}", event.kind);
+    /// });
+    /// let app: axum::Router = axum::Router::new().merge(receiver.into_router("/webhooks/lnbot"));
+    /// ```
+    pub fn into_router<S>(self, path: &str) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route(
+                path,
+                post(
+                    |State(receiver): State<WebhookReceiver>, headers: HeaderMap, body: Bytes| async move {
+                        receiver.handle(&headers, &body).await
+                    },
+                ),
+            )
+            .with_state(self)
+    }
+}
+
+/// Extractor yielding a verified, first-time webhook event.
+///
+/// Requires a [`WebhookReceiver`] obtainable from the router state with
+/// [`FromRef`]; its callbacks are not run. Redeliveries are rejected with
+/// `200 OK` so the sender stops retrying them.
+///
+/// The delivery is recorded as seen once extracted, so if your handler then
+/// fails, a redelivery is still rejected. When handling can fail, use
+/// [`WebhookReceiver::handle`], or [`verify`](WebhookReceiver::verify) and
+/// [`forget`](WebhookReceiver::forget) the body on failure.
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use lnbot::webhooks::{VerifiedWebhook, WebhookReceiver};
+///
+/// async fn on_webhook(VerifiedWebhook(event): VerifiedWebhook) {
+///     println!("{:?}", event.kind);
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/webhooks/lnbot", post(on_webhook))
+///     .with_state(WebhookReceiver::new("whsec_..."));
+/// ```
+#[derive(Debug, Clone)]
+pub struct VerifiedWebhook(pub TypedWalletEvent);
+
+/// Rejection returned by the [`VerifiedWebhook`] extractor.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WebhookRejection {
+    /// The delivery failed verification.
+    Invalid(WebhookError),
+    /// The delivery was already processed.
+    Duplicate,
+    /// The request body could not be read.
+    Body,
+}
+
+impl IntoResponse for WebhookRejection {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Invalid(e) => (e.status(), e.to_string()).into_response(),
+            Self::Duplicate => StatusCode::OK.into_response(),
+            Self::Body => StatusCode::BAD_REQUEST.into_response(),
+        }
+    }
+}
+
+impl<S> FromRequest<S> for VerifiedWebhook
+where
+    WebhookReceiver: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = WebhookRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let receiver = WebhookReceiver::from_ref(state);
+        let headers = req.headers().clone();
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|_| WebhookRejection::Body)?;
+        match receiver.verify(&headers, &body) {
+            Ok(Some(event)) => Ok(Self(event)),
+            Ok(None) => Err(WebhookRejection::Duplicate),
+            Err(e) => Err(WebhookRejection::Invalid(e)),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use lnbot::*;
+
+// ---------------------------------------------------------------------------
+// Parsing and display
+// ---------------------------------------------------------------------------
+
+#[test]
+fn parses_sats_with_units_and_suffixes() {
+    let cases = [
+        ("2500", 2_500),
+        ("2500 sats", 2_500),
+        ("1 sat", 1),
+        ("1.5k sats", 1_500),
+        ("1.5K", 1_500),
+        ("2M sats", 2_000_000),
+        ("0.001 BTC", 100_000),
+        ("0.00000001btc", 1),
+        ("21000 msats", 21),
+        ("  -42 sats ", -42),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(input.parse::<Sats>(), Ok(Sats::new(expected)), "{}", input);
+    }
+}
+
+#[test]
+fn parses_millisats() {
+    assert_eq!("1500".parse::<MilliSats>(), Ok(MilliSats::new(1_500)));
+    assert_eq!("1.5 sats".parse::<MilliSats>(), Ok(MilliSats::new(1_500)));
+    assert_eq!("1 msat".parse::<MilliSats>(), Ok(MilliSats::new(1)));
+    assert_eq!(
+        "0.00000000001 BTC".parse::<MilliSats>(),
+        Ok(MilliSats::new(1))
+    );
+}
+
+#[test]
+fn rejects_invalid_amounts() {
+    assert_eq!(
+        "".parse::<Sats>(),
+        Err(ParseAmountError::InvalidNumber("".into()))
+    );
+    assert_eq!(
+        "abc".parse::<Sats>(),
+        Err(ParseAmountError::InvalidNumber("abc".into()))
+    );
+    assert_eq!(
+        "1.2.3".parse::<Sats>(),
+        Err(ParseAmountError::InvalidNumber("1.2.3".into()))
+    );
+    assert_eq!(
+        "5 dollars".parse::<Sats>(),
+        Err(ParseAmountError::UnknownUnit("dollars".into()))
+    );
+    assert_eq!(
+        "5m sats".parse::<Sats>(),
+        Err(ParseAmountError::UnknownUnit("m sats".into()))
+    );
+    assert_eq!(
+        "1.5 sats".parse::<Sats>(),
+        Err(ParseAmountError::TooPrecise)
+    );
+    assert_eq!(
+        "1500 msats".parse::<Sats>(),
+        Err(ParseAmountError::TooPrecise)
+    );
+    assert_eq!(
+        "1.5 msats".parse::<MilliSats>(),
+        Err(ParseAmountError::TooPrecise)
+    );
+    assert_eq!(
+        "100000000000 BTC".parse::<Sats>(),
+        Err(ParseAmountError::OutOfRange)
+    );
+}
+
+#[test]
+fn displays_with_unit() {
+    assert_eq!(Sats::new(1_500).to_string(), "1500 sats");
+    assert_eq!(Sats::new(1).to_string(), "1 sat");
+    assert_eq!(MilliSats::new(1_500).to_string(), "1500 msats");
+    assert_eq!(MilliSats::new(1).to_string(), "1 msat");
+    let s = Sats::new(-7);
+    assert_eq!(s.to_string().parse::<Sats>(), Ok(s));
+}
+
+// ---------------------------------------------------------------------------
+// Arithmetic and conversions
+// ---------------------------------------------------------------------------
+
+#[test]
+fn arithmetic_is_checked() {
+    let a = Sats::new(100);
+    assert_eq!(a.checked_add(Sats::new(50)), Some(Sats::new(150)));
+    assert_eq!(a.checked_sub(Sats::new(150)), Some(Sats::new(-50)));
+    assert_eq!(a.checked_mul(3), Some(Sats::new(300)));
+    assert_eq!(a.checked_div(0), None);
+    assert_eq!(Sats::MAX.checked_add(Sats::new(1)), None);
+    assert_eq!(Sats::MAX.saturating_add(Sats::new(1)), Sats::MAX);
+    assert!(a.is_positive() && !Sats::ZERO.is_positive());
+}
+
+#[test]
+fn converts_between_sats_and_millisats() {
+    assert_eq!(Sats::new(2).to_msats(), Some(MilliSats::new(2_000)));
+    assert_eq!(Sats::MAX.to_msats(), None);
+    assert_eq!(MilliSats::new(1_001).to_sats_floor(), Sats::new(1));
+    assert_eq!(MilliSats::new(1_001).to_sats_ceil(), Sats::new(2));
+    assert_eq!(MilliSats::new(1_000).to_sats_ceil(), Sats::new(1));
+    assert_eq!(MilliSats::new(-1).to_sats_floor(), Sats::new(-1));
+    assert_eq!(i64::from(Sats::new(5)), 5);
+    assert!(Sats::try_from(u64::MAX).is_err());
+}
+
+#[test]
+fn bolt11_amount_is_in_millisats() {
+    // 250,000 sats, from the BOLT11 specification.
+    let invoice = Bolt11Invoice::parse("lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh").unwrap();
+    assert_eq!(invoice.amount(), Some(MilliSats::new(250_000_000)));
+}
+
+// ---------------------------------------------------------------------------
+// Wire format
+// ---------------------------------------------------------------------------
+
+#[test]
+fn serializes_as_plain_integer() {
+    let req = CreateInvoiceRequest::new(1_000);
+    let json = serde_json::to_value(&req).unwrap();
+    assert_eq!(json["amount"], 1_000);
+
+    let w: WalletResponse = serde_json::from_str(
+        r#"{"walletId":"wal_1","name":"w","balance":1000,"onHold":200,"available":800}"#,
+    )
+    .unwrap();
+    assert_eq!(w.available, Sats::new(800));
+    assert_eq!(w.balance.checked_sub(w.on_hold), Some(w.available));
+}
+
+// ---------------------------------------------------------------------------
+// Validation
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn rejects_non_positive_invoice_amount_without_sending() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/v1/wallets/wal_1/invoices")
+        .expect(0)
+        .create_async()
+        .await;
+
+    let w = LnBot::new("key_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap();
+    let err = w
+        .invoices()
+        .create(&CreateInvoiceRequest::new(0))
+        .await
+        .unwrap_err();
+    match err {
+        LnBotError::InvalidAmount { field, amount } => {
+            assert_eq!(field, "amount");
+            assert_eq!(amount, Sats::ZERO);
+        }
+        other => panic!("expected InvalidAmount, got {:?}", other),
+    }
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn rejects_negative_max_fee_without_sending() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/v1/wallets/wal_1/payments")
+        .expect(0)
+        .create_async()
+        .await;
+
+    let w = LnBot::new("key_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap();
+    let err = w
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(10).max_fee(-5))
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Invalid max fee: -5 sats");
+    mock.assert_async().await;
+}
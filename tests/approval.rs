@@ -0,0 +1,370 @@
+use std::time::Duration;
+
+use lnbot::approval::*;
+use lnbot::*;
+use mockito::Matcher;
+
+// 250,000 sats, from the BOLT11 specification.
+const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+
+fn payment_json(amount: i64) -> String {
+    format!(
+        r#"{{"number":1,"status":"pending","amount":{},"maxFee":10,"serviceFee":0,"actualFee":null,"address":"a@ln.bot","reference":null,"preimage":null,"txNumber":null,"failureReason":null,"createdAt":null,"settledAt":null}}"#,
+        amount
+    )
+}
+
+async fn mock_resolve(
+    server: &mut mockito::Server,
+    target_type: &str,
+    amount: Option<i64>,
+) -> mockito::Mock {
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments/resolve")
+        .match_query(Matcher::Any)
+        .with_header("content-type", "application/json")
+        .with_body(
+            serde_json::json!({
+                "target": "resolved",
+                "type": target_type,
+                "amount": amount,
+                "description": null,
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await
+}
+
+async fn mock_payments(
+    server: &mut mockito::Server,
+    body: Matcher,
+    expect: usize,
+) -> mockito::Mock {
+    server
+        .mock("POST", "/v1/wallets/wal_1/payments")
+        .match_body(body)
+        .with_header("content-type", "application/json")
+        .with_body(payment_json(100))
+        .expect(expect)
+        .create_async()
+        .await
+}
+
+fn wallet(server: &mockito::Server, handler: impl ApprovalHandler) -> Wallet {
+    LnBot::new("uk_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap()
+        .with_approval_handler(handler)
+}
+
+fn denied(err: LnBotError) -> String {
+    match err {
+        LnBotError::ApprovalDenied { reason } => reason,
+        other => panic!("expected ApprovalDenied, got {:?}", other),
+    }
+}
+
+fn l402_request() -> PayL402Request {
+    PayL402Request {
+        www_authenticate: format!(r#"L402 macaroon="abc", invoice="{}""#, INVOICE),
+        max_fee: None,
+        reference: None,
+        wait: None,
+        timeout: None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Built-in handlers
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn always_approve_sends_request_unchanged() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "lightning_address", None).await;
+    let mock = mock_payments(
+        &mut server,
+        Matcher::PartialJson(serde_json::json!({"target": "a@ln.bot", "amount": 100})),
+        1,
+    )
+    .await;
+
+    wallet(&server, AlwaysApprove)
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(100))
+        .await
+        .unwrap();
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn deny_above_blocks_before_sending() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "lightning_address", None).await;
+    let mock = mock_payments(&mut server, Matcher::Any, 0).await;
+
+    let err = wallet(&server, DenyAbove(Sats::new(1_000)))
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1_001))
+        .await
+        .unwrap_err();
+    assert_eq!(denied(err), "1001 sats is above the limit of 1000 sats");
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn deny_above_uses_resolved_amount() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "bolt11", Some(250_000)).await;
+    let mock = mock_payments(&mut server, Matcher::Any, 0).await;
+
+    let err = wallet(&server, DenyAbove(Sats::new(1_000)))
+        .payments()
+        .create(&CreatePaymentRequest::new(INVOICE))
+        .await
+        .unwrap_err();
+    assert_eq!(denied(err), "250000 sats is above the limit of 1000 sats");
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn invoice_amount_cannot_be_overridden() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "bolt11", Some(250_000)).await;
+    let mock = mock_payments(&mut server, Matcher::Any, 0).await;
+
+    let err = wallet(&server, DenyAbove(Sats::new(1_000)))
+        .payments()
+        .create(&CreatePaymentRequest::new(INVOICE).amount(1))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        denied(err),
+        "requested amount of 1 sat differs from the invoice amount of 250000 sats"
+    );
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn deny_above_denies_unknown_amount() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "lightning_address", None).await;
+    mock_payments(&mut server, Matcher::Any, 0).await;
+
+    let err = wallet(&server, DenyAbove(Sats::new(1_000)))
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot"))
+        .await
+        .unwrap_err();
+    assert_eq!(denied(err), "the payment amount is unknown");
+}
+
+#[tokio::test]
+async fn above_threshold_only_asks_for_larger_payments() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "lightning_address", None).await;
+    mock_payments(&mut server, Matcher::Any, 1).await;
+    let (handler, mut requests) = ChannelApprovalHandler::new(1);
+    let w = wallet(&server, AboveThreshold::new(1_000, handler));
+
+    w.payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1_000))
+        .await
+        .unwrap();
+    assert!(requests.try_recv().is_err());
+
+    let task = tokio::spawn(async move {
+        let request = requests.recv().await.unwrap();
+        let amount = request.preview().amount;
+        request.deny("too much");
+        amount
+    });
+    let err = w
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1_001))
+        .await
+        .unwrap_err();
+    assert_eq!(denied(err), "too much");
+    assert_eq!(task.await.unwrap(), Some(Sats::new(1_001)));
+}
+
+// ---------------------------------------------------------------------------
+// ChannelApprovalHandler
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn channel_handler_modifies_request() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "lightning_address", None).await;
+    let mock = mock_payments(
+        &mut server,
+        Matcher::PartialJson(serde_json::json!({
+            "target": "a@ln.bot",
+            "amount": 50,
+            "maxFee": 2,
+            "reference": "approved",
+        })),
+        1,
+    )
+    .await;
+    let (handler, mut requests) = ChannelApprovalHandler::new(1);
+
+    let task = tokio::spawn(async move {
+        let request = requests.recv().await.unwrap();
+        let preview = request.preview().clone();
+        request.modify(PaymentChanges::new().amount(50).reference("approved"));
+        preview
+    });
+    wallet(&server, handler)
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(500).max_fee(2))
+        .await
+        .unwrap();
+
+    let preview = task.await.unwrap();
+    assert_eq!(preview.kind, PaymentKind::Payment);
+    assert_eq!(preview.target, "a@ln.bot");
+    assert_eq!(preview.amount, Some(Sats::new(500)));
+    assert_eq!(preview.max_fee, Some(Sats::new(2)));
+    assert_eq!(preview.destination.target_type, "lightning_address");
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn channel_handler_denies_when_receiver_dropped() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "lightning_address", None).await;
+    mock_payments(&mut server, Matcher::Any, 0).await;
+    let (handler, requests) = ChannelApprovalHandler::new(1);
+    drop(requests);
+
+    let err = wallet(&server, handler)
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1))
+        .await
+        .unwrap_err();
+    assert_eq!(denied(err), "approval channel closed");
+}
+
+#[tokio::test]
+async fn channel_handler_denies_when_request_dropped() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "lightning_address", None).await;
+    mock_payments(&mut server, Matcher::Any, 0).await;
+    let (handler, mut requests) = ChannelApprovalHandler::new(1);
+    tokio::spawn(async move { drop(requests.recv().await) });
+
+    let err = wallet(&server, handler)
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1))
+        .await
+        .unwrap_err();
+    assert_eq!(denied(err), "approval channel closed");
+}
+
+#[tokio::test]
+async fn channel_handler_times_out() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "lightning_address", None).await;
+    mock_payments(&mut server, Matcher::Any, 0).await;
+    let (handler, _requests) = ChannelApprovalHandler::new(1);
+
+    let err = wallet(&server, handler.timeout(Duration::from_millis(20)))
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1))
+        .await
+        .unwrap_err();
+    assert_eq!(denied(err), "approval timed out");
+}
+
+// ---------------------------------------------------------------------------
+// Interaction with spending policy and L402
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn policy_checks_the_approved_request() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "lightning_address", None).await;
+    let mock = mock_payments(&mut server, Matcher::Any, 1).await;
+    let (handler, mut requests) = ChannelApprovalHandler::new(1);
+    tokio::spawn(async move {
+        let request = requests.recv().await.unwrap();
+        request.modify(PaymentChanges::new().amount(500));
+    });
+
+    wallet(&server, handler)
+        .with_spending_policy(SpendingPolicy::new().max_per_payment(1_000))
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(5_000))
+        .await
+        .unwrap();
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn l402_pay_is_reviewed() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "bolt11", Some(250_000)).await;
+    let mock = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .expect(0)
+        .create_async()
+        .await;
+    let (handler, mut requests) = ChannelApprovalHandler::new(1);
+    let task = tokio::spawn(async move {
+        let request = requests.recv().await.unwrap();
+        let preview = request.preview().clone();
+        request.deny("no");
+        preview
+    });
+
+    let err = wallet(&server, handler)
+        .l402()
+        .pay(&l402_request())
+        .await
+        .unwrap_err();
+    assert_eq!(denied(err), "no");
+
+    let preview = task.await.unwrap();
+    assert_eq!(preview.kind, PaymentKind::L402);
+    assert_eq!(preview.target, INVOICE);
+    assert_eq!(preview.amount, Some(Sats::new(250_000)));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn l402_amount_cannot_be_modified() {
+    let mut server = mockito::Server::new_async().await;
+    mock_resolve(&mut server, "bolt11", Some(250_000)).await;
+    let mock = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .expect(0)
+        .create_async()
+        .await;
+    let (handler, mut requests) = ChannelApprovalHandler::new(1);
+    tokio::spawn(async move {
+        let request = requests.recv().await.unwrap();
+        request.modify(PaymentChanges::new().amount(1));
+    });
+
+    let err = wallet(&server, handler)
+        .l402()
+        .pay(&l402_request())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        denied(err),
+        "the amount of an L402 payment cannot be changed"
+    );
+    mock.assert_async().await;
+}
+
+#[test]
+fn approval_denied_display() {
+    let e = LnBotError::ApprovalDenied {
+        reason: "no".into(),
+    };
+    assert_eq!(e.to_string(), "Payment denied: no");
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "axum")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use lnbot::webhooks::{self, VerifiedWebhook, WebhookReceiver, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use lnbot::WalletEventKind;
+use tower::ServiceExt;
+
+const SECRET: &str = "whsec_test";
+const BODY: &str = r#"{"event":"payment.failed","createdAt":"2024-01-01T00:00:00Z","data":{"number":4,"status":"failed","amount":50,"maxFee":10,"serviceFee":0,"actualFee":null,"address":"lnbc1...","reference":null,"preimage":null,"txNumber":null,"failureReason":"no route","createdAt":null,"settledAt":null}}"#;
+
+fn delivery(secret: &str, body: &str) -> Request<Body> {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    Request::post("/hook")
+        .header(TIMESTAMP_HEADER, ts)
+        .header(
+            SIGNATURE_HEADER,
+            webhooks::sign(secret, ts, body.as_bytes()),
+        )
+        .body(Body::from(body.to_owned()))
+        .unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// into_router
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn router_dispatches_and_acknowledges() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+    let app: Router = WebhookReceiver::new(SECRET)
+        .on_event(move |event| {
+            let counter = counter.clone();
+            async move {
+                assert!(matches!(event.kind, WalletEventKind::PaymentFailed(_)));
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+        .into_router("/hook");
+
+    let res = app.clone().oneshot(delivery(SECRET, BODY)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = app.clone().oneshot(delivery(SECRET, BODY)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    let res = app.oneshot(delivery("whsec_other", BODY)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+// ---------------------------------------------------------------------------
+// VerifiedWebhook extractor
+// ---------------------------------------------------------------------------
+
+async fn on_webhook(VerifiedWebhook(event): VerifiedWebhook) -> StatusCode {
+    match event.kind {
+        WalletEventKind::PaymentFailed(_) => StatusCode::ACCEPTED,
+        _ => StatusCode::OK,
+    }
+}
+
+fn extractor_app() -> Router {
+    Router::new()
+        .route("/hook", post(on_webhook))
+        .with_state(WebhookReceiver::new(SECRET))
+}
+
+#[tokio::test]
+async fn extractor_passes_verified_event() {
+    let app = extractor_app();
+
+    let res = app.clone().oneshot(delivery(SECRET, BODY)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+    // Redelivery short-circuits with 200 before reaching the handler.
+    let res = app.oneshot(delivery(SECRET, BODY)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn extractor_rejects_invalid_deliveries() {
+    let app = extractor_app();
+
+    let res = app
+        .clone()
+        .oneshot(delivery("whsec_other", BODY))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = app.oneshot(delivery(SECRET, "{}")).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
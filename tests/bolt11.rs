@@ -0,0 +1,465 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use k256::ecdsa::SigningKey;
+use lnbot::bolt11::{Bolt11Error, Bolt11Invoice, Network};
+use lnbot::{InvoiceResponse, LnBotError, PaymentResponse, Preimage};
+use sha2::{Digest, Sha256};
+
+// Vectors and private key from the BOLT11 specification.
+const DONATION: &str = "lnbc1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq9qrsgq357wnc5r2ueh7ck6q93dj32dlqnls087fxdwk8qakdyafkq3yap9us6v52vjjsrvywa6rt52cm9r9zqt8r2t7mlcwspyetp5h2tztugp9lfyql";
+const COFFEE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+const PRIVATE_KEY: &str = "e126f68f7eafcc8b74f54d269fe206be715000f94dac067d1c04a8ca3b2db734";
+const PAYEE: &str = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad";
+const PAYMENT_HASH: &str = "0001020304050607080900010203040506070809000102030405060708090102";
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Test encoder
+// ---------------------------------------------------------------------------
+
+const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn to_words(bytes: &[u8]) -> Vec<u8> {
+    let mut words = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for &b in bytes {
+        acc = (acc << 8) | u32::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            words.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        words.push(((acc << (5 - bits)) & 31) as u8);
+    }
+    words
+}
+
+fn to_bytes_padded(words: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for &w in words {
+        acc = (acc << 5) | u32::from(w);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    if bits > 0 {
+        bytes.push((acc << (8 - bits)) as u8);
+    }
+    bytes
+}
+
+fn int_words(mut value: u64, len: usize) -> Vec<u8> {
+    let mut words = vec![0; len];
+    for w in words.iter_mut().rev() {
+        *w = (value & 31) as u8;
+        value >>= 5;
+    }
+    words
+}
+
+fn field(tag: char, data: Vec<u8>) -> Vec<u8> {
+    let tag = CHARSET.iter().position(|&c| c as char == tag).unwrap() as u8;
+    let mut words = vec![tag];
+    words.extend(int_words(data.len() as u64, 2));
+    words.extend(data);
+    words
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk = 1u32;
+    for &v in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ u32::from(v);
+        for (i, g) in GEN.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn bech32(hrp: &str, words: &[u8]) -> String {
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values.extend(words);
+    values.extend([0; 6]);
+    let chk = polymod(&values) ^ 1;
+    let checksum = (0..6).map(|i| ((chk >> (5 * (5 - i))) & 31) as u8);
+    let data: String = words
+        .iter()
+        .copied()
+        .chain(checksum)
+        .map(|w| CHARSET[w as usize] as char)
+        .collect();
+    format!("{}1{}", hrp, data)
+}
+
+/// Builds and signs an invoice with the specification's private key.
+fn encode(hrp: &str, fields: &[Vec<u8>]) -> String {
+    let mut words = int_words(1_496_314_658, 7);
+    for f in fields {
+        words.extend(f);
+    }
+    let mut message = hrp.as_bytes().to_vec();
+    message.extend(to_bytes_padded(&words));
+    let key = SigningKey::from_slice(&hex(PRIVATE_KEY)).unwrap();
+    let (sig, recid) = key
+        .sign_prehash_recoverable(&Sha256::digest(&message))
+        .unwrap();
+    let mut sig_bytes = sig.to_bytes().to_vec();
+    sig_bytes.push(recid.to_byte());
+    words.extend(to_words(&sig_bytes));
+    bech32(hrp, &words)
+}
+
+fn basic_fields() -> Vec<Vec<u8>> {
+    vec![
+        field('p', to_words(&hex(PAYMENT_HASH))),
+        field('d', to_words(b"test")),
+    ]
+}
+
+// ---------------------------------------------------------------------------
+// Specification vectors
+// ---------------------------------------------------------------------------
+
+#[test]
+fn decodes_donation_invoice() {
+    let invoice = Bolt11Invoice::parse(DONATION).unwrap();
+    assert_eq!(invoice.network, Network::Bitcoin);
+    assert_eq!(invoice.amount_msat, None);
+    assert_eq!(invoice.timestamp, 1_496_314_658);
+    assert_eq!(invoice.payment_hash.to_hex(), PAYMENT_HASH);
+    assert_eq!(invoice.payment_secret, Some([0x11; 32]));
+    assert_eq!(
+        invoice.description.as_deref(),
+        Some("Please consider supporting this project")
+    );
+    assert_eq!(invoice.expiry, Duration::from_secs(3600));
+    assert_eq!(invoice.min_final_cltv_expiry_delta, 18);
+    assert_eq!(invoice.payee_pubkey.to_vec(), hex(PAYEE));
+    assert_eq!(invoice.features.bits().collect::<Vec<_>>(), vec![8, 14]);
+    assert!(invoice.route_hints.is_empty());
+}
+
+#[test]
+fn decodes_amount_and_expiry() {
+    let invoice: Bolt11Invoice = COFFEE.parse().unwrap();
+    assert_eq!(invoice.amount_msat, Some(250_000_000));
+    assert_eq!(invoice.amount_sats(), Some(250_000));
+    assert_eq!(invoice.description.as_deref(), Some("1 cup coffee"));
+    assert_eq!(invoice.expiry, Duration::from_secs(60));
+    assert_eq!(
+        invoice.expires_at(),
+        Some(UNIX_EPOCH + Duration::from_secs(1_496_314_658 + 60))
+    );
+    assert!(invoice.is_expired());
+}
+
+#[test]
+fn accepts_uppercase_and_uri_prefix() {
+    let upper = COFFEE.to_uppercase();
+    assert!(Bolt11Invoice::parse(&upper).is_ok());
+    assert!(Bolt11Invoice::parse(&format!("lightning:{}", COFFEE)).is_ok());
+}
+
+#[test]
+fn decodes_invoice_response_bolt11() {
+    let json = format!(
+        r#"{{"number":1,"status":"pending","amount":250000,"bolt11":"{}","reference":null,"memo":null,"preimage":null,"txNumber":null,"createdAt":null,"settledAt":null,"expiresAt":null}}"#,
+        COFFEE
+    );
+    let invoice: InvoiceResponse = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        invoice.decode_bolt11().unwrap().amount_sats(),
+        Some(250_000)
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Prefixes and amounts
+// ---------------------------------------------------------------------------
+
+#[test]
+fn decodes_networks() {
+    for (hrp, network) in [
+        ("lntb", Network::Testnet),
+        ("lntbs", Network::Signet),
+        ("lnbcrt", Network::Regtest),
+        ("lnsb", Network::Unknown("sb".into())),
+    ] {
+        let invoice = Bolt11Invoice::parse(&encode(hrp, &basic_fields())).unwrap();
+        assert_eq!(invoice.network, network);
+        assert_eq!(invoice.network.prefix(), &hrp[2..]);
+    }
+}
+
+#[test]
+fn decodes_amount_multipliers() {
+    for (hrp, msat) in [
+        ("lnbc2", 200_000_000_000),
+        ("lnbc20m", 2_000_000_000),
+        ("lnbc2500u", 250_000_000),
+        ("lnbc2500n", 250_000),
+        ("lnbc10p", 1),
+        ("lntbs1500n", 150_000),
+    ] {
+        let invoice = Bolt11Invoice::parse(&encode(hrp, &basic_fields())).unwrap();
+        assert_eq!(invoice.amount_msat, Some(msat), "{}", hrp);
+    }
+}
+
+#[test]
+fn rejects_invalid_amounts() {
+    for hrp in ["lnbc1p", "lnbc25x", "lnbc99999999999999999999"] {
+        let err = Bolt11Invoice::parse(&encode(hrp, &basic_fields())).unwrap_err();
+        assert!(matches!(err, Bolt11Error::InvalidAmount(_)), "{}", hrp);
+    }
+}
+
+#[test]
+fn rejects_non_lightning_prefix() {
+    let err = Bolt11Invoice::parse(&encode("bc", &basic_fields())).unwrap_err();
+    assert!(matches!(err, Bolt11Error::InvalidPrefix(_)));
+}
+
+// ---------------------------------------------------------------------------
+// Tagged fields
+// ---------------------------------------------------------------------------
+
+#[test]
+fn decodes_route_hints_cltv_and_description_hash() {
+    let mut hop = hex("029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255");
+    hop.extend(0x0102_0304_0506_0708u64.to_be_bytes());
+    hop.extend(1u32.to_be_bytes());
+    hop.extend(20u32.to_be_bytes());
+    hop.extend(3u16.to_be_bytes());
+    let mut route = hop.clone();
+    route.extend(&hop);
+
+    let fields = vec![
+        field('p', to_words(&hex(PAYMENT_HASH))),
+        field('h', to_words(&[0xab; 32])),
+        field('c', int_words(144, 2)),
+        field('x', int_words(86_400, 4)),
+        field('r', to_words(&route)),
+        field('n', to_words(&hex(PAYEE))),
+    ];
+    let invoice = Bolt11Invoice::parse(&encode("lnbc", &fields)).unwrap();
+
+    assert_eq!(invoice.description, None);
+    assert_eq!(invoice.description_hash, Some([0xab; 32]));
+    assert_eq!(invoice.min_final_cltv_expiry_delta, 144);
+    assert_eq!(invoice.expiry, Duration::from_secs(86_400));
+    assert_eq!(invoice.route_hints.len(), 1);
+    let hops = &invoice.route_hints[0].hops;
+    assert_eq!(hops.len(), 2);
+    assert_eq!(hops[0].short_channel_id, 0x0102_0304_0506_0708);
+    assert_eq!(hops[0].fee_base_msat, 1);
+    assert_eq!(hops[0].fee_proportional_millionths, 20);
+    assert_eq!(hops[0].cltv_expiry_delta, 3);
+}
+
+#[test]
+fn skips_fields_with_unexpected_length_and_unknown_tags() {
+    let fields = vec![
+        field('p', to_words(&[0x55; 20])),
+        field('p', to_words(&hex(PAYMENT_HASH))),
+        field('v', to_words(b"fallback")),
+        field('d', to_words(b"test")),
+    ];
+    let invoice = Bolt11Invoice::parse(&encode("lnbc", &fields)).unwrap();
+    assert_eq!(invoice.payment_hash.to_hex(), PAYMENT_HASH);
+}
+
+#[test]
+fn expiry_beyond_system_time_never_expires() {
+    let mut fields = basic_fields();
+    fields.push(field('x', int_words(u64::MAX, 13)));
+    let invoice = Bolt11Invoice::parse(&encode("lnbc", &fields)).unwrap();
+    assert_eq!(invoice.expiry, Duration::from_secs(u64::MAX));
+    assert_eq!(invoice.expires_at(), None);
+    assert!(!invoice.is_expired());
+}
+
+#[test]
+fn rejects_expiry_and_cltv_wider_than_64_bits() {
+    for (tag, name) in [('x', "expiry"), ('c', "min_final_cltv_expiry_delta")] {
+        let mut fields = basic_fields();
+        let mut words = int_words(u64::MAX, 13);
+        words.insert(0, 1);
+        fields.push(field(tag, words));
+        assert_eq!(
+            Bolt11Invoice::parse(&encode("lnbc", &fields)),
+            Err(Bolt11Error::InvalidField(name))
+        );
+    }
+}
+
+#[test]
+fn rejects_missing_payment_hash() {
+    let fields = vec![field('d', to_words(b"test"))];
+    assert_eq!(
+        Bolt11Invoice::parse(&encode("lnbc", &fields)),
+        Err(Bolt11Error::MissingPaymentHash)
+    );
+}
+
+#[test]
+fn rejects_missing_description() {
+    let fields = vec![field('p', to_words(&hex(PAYMENT_HASH)))];
+    assert_eq!(
+        Bolt11Invoice::parse(&encode("lnbc", &fields)),
+        Err(Bolt11Error::MissingDescription)
+    );
+}
+
+#[test]
+fn rejects_payee_mismatch() {
+    let mut fields = basic_fields();
+    fields.push(field(
+        'n',
+        to_words(&hex(
+            "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
+        )),
+    ));
+    assert_eq!(
+        Bolt11Invoice::parse(&encode("lnbc", &fields)),
+        Err(Bolt11Error::PayeeMismatch)
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Encoding errors
+// ---------------------------------------------------------------------------
+
+#[test]
+fn rejects_bad_checksum() {
+    let mut s = COFFEE.to_string();
+    s.pop();
+    s.push('q');
+    assert_eq!(Bolt11Invoice::parse(&s), Err(Bolt11Error::InvalidChecksum));
+}
+
+#[test]
+fn rejects_mixed_case() {
+    let s = format!("LNBC{}", &COFFEE[4..]);
+    assert_eq!(Bolt11Invoice::parse(&s), Err(Bolt11Error::MixedCase));
+}
+
+#[test]
+fn rejects_invalid_character_and_missing_separator() {
+    assert_eq!(
+        Bolt11Invoice::parse("lnbc1pvjluezb"),
+        Err(Bolt11Error::InvalidCharacter('b'))
+    );
+    assert_eq!(
+        Bolt11Invoice::parse("lnbcpvjluez"),
+        Err(Bolt11Error::MissingSeparator)
+    );
+}
+
+#[test]
+fn rejects_non_ascii_input() {
+    // The checksum covers the raw hrp bytes, so this one is valid bech32
+    // apart from the multibyte character.
+    let invoice = bech32("lnbc10é", &[0; 120]);
+    assert_eq!(
+        Bolt11Invoice::parse(&invoice),
+        Err(Bolt11Error::InvalidCharacter('é'))
+    );
+    assert_eq!(
+        Bolt11Invoice::parse("lightning:lnbc1é"),
+        Err(Bolt11Error::InvalidCharacter('é'))
+    );
+}
+
+#[test]
+fn rejects_tampered_signed_data() {
+    // Re-encode the coffee invoice with a different amount but the original
+    // signature: the checksum is valid, the signature no longer matches.
+    let sep = COFFEE.rfind('1').unwrap();
+    let words: Vec<u8> = COFFEE[sep + 1..COFFEE.len() - 6]
+        .bytes()
+        .map(|c| CHARSET.iter().position(|&x| x == c).unwrap() as u8)
+        .collect();
+    let tampered = bech32("lnbc2400u", &words);
+    let invoice = Bolt11Invoice::parse(&tampered);
+    assert!(
+        matches!(invoice, Err(Bolt11Error::InvalidSignature))
+            || matches!(&invoice, Ok(i) if i.payee_pubkey.to_vec() != hex(PAYEE))
+    );
+}
+
+#[test]
+fn rejects_truncated_data() {
+    assert_eq!(
+        Bolt11Invoice::parse(&bech32("lnbc", &[0; 20])),
+        Err(Bolt11Error::TooShort)
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Preimage verification
+// ---------------------------------------------------------------------------
+
+#[test]
+fn verifies_preimage_against_invoice() {
+    let preimage = [0x42; 32];
+    let fields = vec![
+        field('p', to_words(&Sha256::digest(preimage))),
+        field('d', to_words(b"test")),
+    ];
+    let bolt11 = encode("lnbc10u", &fields);
+
+    let invoice = Bolt11Invoice::parse(&bolt11).unwrap();
+    assert!(Preimage::from_bytes(preimage).verify(&invoice.payment_hash));
+
+    let json = format!(
+        r#"{{"number":1,"status":"settled","amount":1000,"maxFee":10,"serviceFee":0,"actualFee":0,"address":"{}","reference":null,"preimage":"{}","txNumber":1,"failureReason":null,"createdAt":null,"settledAt":null}}"#,
+        bolt11,
+        "42".repeat(32)
+    );
+    let mut payment: PaymentResponse = serde_json::from_str(&json).unwrap();
+    assert!(payment.verify_preimage(&bolt11).unwrap());
+    assert!(!payment.verify_preimage(COFFEE).unwrap());
+
+    payment.preimage = Some("43".repeat(32));
+    assert!(!payment.verify_preimage(&bolt11).unwrap());
+    assert!(matches!(
+        payment.verify_preimage("lnbc1invalid"),
+        Err(LnBotError::Bolt11(_))
+    ));
+}
+
+#[test]
+fn invoice_response_verifies_own_preimage() {
+    let fields = vec![
+        field('p', to_words(&Sha256::digest([0x42; 32]))),
+        field('d', to_words(b"test")),
+    ];
+    let json = format!(
+        r#"{{"number":1,"status":"settled","amount":1000,"bolt11":"{}","reference":null,"memo":null,"preimage":"{}","txNumber":1,"createdAt":null,"settledAt":null,"expiresAt":null}}"#,
+        encode("lnbc10u", &fields),
+        "42".repeat(32)
+    );
+    let mut invoice: InvoiceResponse = serde_json::from_str(&json).unwrap();
+    assert!(invoice.verify_preimage().unwrap());
+
+    invoice.preimage = None;
+    assert!(!invoice.verify_preimage().unwrap());
+}
@@ -0,0 +1,144 @@
+use lnbot::*;
+
+async fn error_for(status: usize, content_type: &str, body: &str) -> LnBotError {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1")
+        .with_status(status)
+        .with_header("content-type", content_type)
+        .with_header("x-request-id", "req_header")
+        .with_body(body)
+        .create_async()
+        .await;
+
+    LnBot::new("key_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap()
+        .get()
+        .await
+        .unwrap_err()
+}
+
+// ---------------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn parses_problem_json() {
+    let err = error_for(
+        400,
+        "application/problem+json",
+        r#"{
+            "type": "https://ln.bot/errors/insufficient-balance",
+            "title": "Insufficient balance",
+            "status": 400,
+            "detail": "Balance of 10 sats is below 100 sats",
+            "requestId": "req_body",
+            "errors": {"amount": ["too large"]}
+        }"#,
+    )
+    .await;
+
+    assert!(matches!(err, LnBotError::BadRequest { .. }));
+    assert_eq!(err.status(), Some(400));
+    assert_eq!(err.code(), Some(&ErrorCode::InsufficientBalance));
+    assert_eq!(err.request_id(), Some("req_body"));
+
+    let api = err.api_error().unwrap();
+    assert_eq!(api.message, "Balance of 10 sats is below 100 sats");
+    assert_eq!(
+        api.details,
+        Some(serde_json::json!({"amount": ["too large"]}))
+    );
+}
+
+#[tokio::test]
+async fn parses_code_and_message() {
+    let err = error_for(
+        409,
+        "application/json",
+        r#"{"code":"address_taken","message":"alice@ln.bot is taken"}"#,
+    )
+    .await;
+
+    assert_eq!(err.status(), Some(409));
+    assert_eq!(err.code(), Some(&ErrorCode::AddressTaken));
+    assert_eq!(err.api_error().unwrap().message, "alice@ln.bot is taken");
+    assert_eq!(err.request_id(), Some("req_header"));
+}
+
+#[tokio::test]
+async fn parses_nested_error_object() {
+    let err = error_for(
+        422,
+        "application/json",
+        r#"{"error":{"code":"AMOUNT_BELOW_MINIMUM","message":"too small","details":{"minimum":1}}}"#,
+    )
+    .await;
+
+    assert!(matches!(err, LnBotError::Api { status: 422, .. }));
+    assert_eq!(err.code(), Some(&ErrorCode::AmountBelowMinimum));
+    let api = err.api_error().unwrap();
+    assert_eq!(api.message, "too small");
+    assert_eq!(api.details, Some(serde_json::json!({"minimum": 1})));
+}
+
+#[tokio::test]
+async fn keeps_unknown_codes() {
+    let err = error_for(
+        400,
+        "application/json",
+        r#"{"code":"brand_new_error","message":"?"}"#,
+    )
+    .await;
+    let code = err.code().unwrap();
+    assert_eq!(code, &ErrorCode::Unknown("brand_new_error".into()));
+    assert_eq!(code.to_string(), "brand_new_error");
+}
+
+#[tokio::test]
+async fn message_only_body_has_no_code() {
+    let err = error_for(404, "application/json", r#"{"message":"not found"}"#).await;
+    assert_eq!(err.status(), Some(404));
+    assert_eq!(err.code(), None);
+    assert_eq!(err.api_error().unwrap().message, "not found");
+}
+
+#[tokio::test]
+async fn plain_text_body_is_not_parsed() {
+    let err = error_for(503, "text/plain", "upstream unavailable").await;
+    assert_eq!(err.status(), Some(503));
+    assert!(err.api_error().is_none());
+    assert_eq!(err.code(), None);
+    assert_eq!(err.request_id(), None);
+    assert_eq!(
+        err.to_string(),
+        "API error (HTTP 503): upstream unavailable"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// ErrorCode
+// ---------------------------------------------------------------------------
+
+#[test]
+fn error_code_matching_ignores_case_and_separator() {
+    assert_eq!(
+        ErrorCode::from("invoice-expired"),
+        ErrorCode::InvoiceExpired
+    );
+    assert_eq!(
+        ErrorCode::from("INVOICE_EXPIRED"),
+        ErrorCode::InvoiceExpired
+    );
+    assert_eq!(ErrorCode::InvoiceExpired.as_str(), "invoice_expired");
+}
+
+#[test]
+fn non_api_errors_have_no_status() {
+    let err = LnBotError::Config("bad".into());
+    assert_eq!(err.status(), None);
+    assert_eq!(err.code(), None);
+    assert_eq!(err.request_id(), None);
+}
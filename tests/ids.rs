@@ -0,0 +1,115 @@
+use lnbot::*;
+
+// ---------------------------------------------------------------------------
+// WalletId
+// ---------------------------------------------------------------------------
+
+#[test]
+fn wallet_id_requires_prefix() {
+    let id: WalletId = "wal_abc-123_X".parse().unwrap();
+    assert_eq!(id, "wal_abc-123_X");
+    assert_eq!(id.to_string(), "wal_abc-123_X");
+
+    for bad in ["", "wal_", "abc", "WAL_abc", "wal_a/b", "wal_a b", "wal_é"] {
+        assert!(bad.parse::<WalletId>().is_err(), "{:?}", bad);
+    }
+    let err = WalletId::new("abc").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"invalid wallet ID "abc": expected a `wal_` prefix"#
+    );
+}
+
+#[test]
+fn wallet_id_is_not_checked_when_deserialized() {
+    let id: WalletId = serde_json::from_str(r#""wal_1""#).unwrap();
+    assert_eq!(serde_json::to_string(&id).unwrap(), r#""wal_1""#);
+
+    let w: WalletResponse = serde_json::from_str(
+        r#"{"walletId":"w1","name":"w","balance":0,"onHold":0,"available":0}"#,
+    )
+    .unwrap();
+    assert_eq!(w.wallet_id, "w1");
+}
+
+#[test]
+fn wallet_accepts_owned_and_borrowed_ids() {
+    let client = LnBot::new("key_test");
+    let id: WalletId = "wal_1".parse().unwrap();
+    assert_eq!(client.wallet(&id).unwrap().id(), &id);
+    assert_eq!(client.wallet(id.clone()).unwrap().id(), &id);
+    assert_eq!(client.wallet(String::from("wal_1")).unwrap().id(), &id);
+}
+
+// ---------------------------------------------------------------------------
+// Numbers
+// ---------------------------------------------------------------------------
+
+#[test]
+fn numbers_serialize_as_plain_integers() {
+    let p: L402PayResponse = serde_json::from_str(
+        r#"{"authorization":null,"paymentHash":"h","preimage":null,"amount":10,"fee":null,"paymentNumber":7,"status":"pending"}"#,
+    )
+    .unwrap();
+    assert_eq!(p.payment_number, PaymentNumber::new(7));
+    assert_eq!(p.payment_number, 7);
+    assert_eq!(i32::from(p.payment_number), 7);
+    assert_eq!(serde_json::to_string(&TxNumber::new(3)).unwrap(), "3");
+}
+
+#[test]
+fn invoice_ref_from_number_or_hash() {
+    assert_eq!(InvoiceRef::from(InvoiceNumber::new(3)), InvoiceRef::from(3));
+    let hash = PaymentHash::from_bytes([0xab; 32]);
+    assert_eq!(InvoiceRef::from(hash), InvoiceRef::from("ab".repeat(32)));
+}
+
+#[tokio::test]
+async fn invoices_get_by_number_or_hash() {
+    let mut server = mockito::Server::new_async().await;
+    let body = r#"{"number":3,"status":"pending","amount":100,"bolt11":"lnbc1...","reference":null,"memo":null,"preimage":null,"txNumber":null,"createdAt":null,"settledAt":null,"expiresAt":null}"#;
+    let by_number = server
+        .mock("GET", "/v1/wallets/wal_1/invoices/3")
+        .with_header("content-type", "application/json")
+        .with_body(body)
+        .create_async()
+        .await;
+    let by_hash = server
+        .mock("GET", "/v1/wallets/wal_1/invoices/abc")
+        .with_header("content-type", "application/json")
+        .with_body(body)
+        .create_async()
+        .await;
+
+    let invoices = LnBot::new("key_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap()
+        .invoices();
+    let invoice = invoices.get(InvoiceNumber::new(3)).await.unwrap();
+    assert_eq!(invoice.number, 3);
+    invoices.get("abc").await.unwrap();
+    by_number.assert_async().await;
+    by_hash.assert_async().await;
+}
+
+// ---------------------------------------------------------------------------
+// Keys
+// ---------------------------------------------------------------------------
+
+#[test]
+fn keys_are_redacted_in_debug() {
+    let key = ApiKey::from("uk_secret123");
+    assert_eq!(format!("{:?}", key), r#"ApiKey("uk_***")"#);
+    assert_eq!(key.expose_secret(), "uk_secret123");
+
+    let r: RegisterResponse = serde_json::from_str(
+        r#"{"userId":"u1","primaryKey":"uk_1","secondaryKey":"uk_2","recoveryPassphrase":"words"}"#,
+    )
+    .unwrap();
+    let debug = format!("{:?}", r);
+    assert!(!debug.contains("uk_1") && !debug.contains("uk_2"));
+
+    let w: WalletKeyResponse = serde_json::from_str(r#"{"key":"wk_abc"}"#).unwrap();
+    assert_eq!(format!("{:?}", w.key), r#"WalletKey("wk_***")"#);
+}
@@ -27,9 +27,9 @@ async fn account_register() {
     let c = LnBot::unauthenticated();
     let resp = c.register().await.unwrap();
     assert!(!resp.user_id.is_empty());
-    assert!(resp.primary_key.starts_with("uk_"));
-    assert!(resp.secondary_key.starts_with("uk_"));
-    assert!(!resp.recovery_passphrase.is_empty());
+    assert!(resp.primary_key.expose_secret().starts_with("uk_"));
+    assert!(resp.secondary_key.expose_secret().starts_with("uk_"));
+    assert!(!resp.recovery_passphrase.expose_secret().is_empty());
 }
 
 #[tokio::test]
@@ -49,7 +49,7 @@ async fn account_me() {
 async fn wallets_create_and_list() {
     let c = client();
     let created = c.wallets().create().await.unwrap();
-    assert!(created.wallet_id.starts_with("wal_"));
+    assert!(created.wallet_id.as_str().starts_with("wal_"));
     assert!(!created.address.is_empty());
 
     let list = c.wallets().list().await.unwrap();
@@ -60,7 +60,7 @@ async fn wallets_create_and_list() {
 #[ignore]
 async fn wallet_get_and_update() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
     let info = w.get().await.unwrap();
     assert_eq!(info.wallet_id, wallet_id());
 
@@ -76,14 +76,14 @@ async fn wallet_get_and_update() {
 #[ignore]
 async fn wallet_key_lifecycle() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
 
     // Delete existing key if any
     let _ = w.key().delete().await;
 
     // Create
     let created = w.key().create().await.unwrap();
-    assert!(created.key.starts_with("wk_"));
+    assert!(created.key.expose_secret().starts_with("wk_"));
 
     // Get info
     let info = w.key().get().await.unwrap();
@@ -91,7 +91,7 @@ async fn wallet_key_lifecycle() {
 
     // Rotate
     let rotated = w.key().rotate().await.unwrap();
-    assert!(rotated.key.starts_with("wk_"));
+    assert!(rotated.key.expose_secret().starts_with("wk_"));
     assert_ne!(rotated.key, created.key);
 
     // Delete
@@ -108,7 +108,7 @@ async fn wallet_key_lifecycle() {
 #[ignore]
 async fn addresses_lifecycle() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
 
     // Create random address
     let addr = w.addresses().create(&CreateAddressRequest::default()).await.unwrap();
@@ -131,7 +131,7 @@ async fn addresses_lifecycle() {
 #[ignore]
 async fn invoices_create_and_get() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
 
     let inv = w
         .invoices()
@@ -151,7 +151,7 @@ async fn invoices_create_and_get() {
 #[ignore]
 async fn invoices_list() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
     let list = w.invoices().list(&ListParams::default().limit(5)).await.unwrap();
     assert!(!list.is_empty());
 }
@@ -164,9 +164,10 @@ async fn invoices_list() {
 #[ignore]
 async fn public_invoice_for_wallet() {
     let c = LnBot::unauthenticated();
+    let wallet_id: WalletId = wallet_id().parse().unwrap();
     let resp = c
         .invoices()
-        .create_for_wallet(&CreateInvoiceForWalletRequest::new(wallet_id(), 1000))
+        .create_for_wallet(&CreateInvoiceForWalletRequest::new(wallet_id, 1000))
         .await
         .unwrap();
     assert!(resp.bolt11.starts_with("lnbc"));
@@ -181,7 +182,7 @@ async fn public_invoice_for_wallet() {
 #[ignore]
 async fn payments_resolve() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
 
     // Get a lightning address for this wallet
     let addrs = w.addresses().list().await.unwrap();
@@ -196,7 +197,7 @@ async fn payments_resolve() {
 #[ignore]
 async fn payments_list() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
     let _list = w.payments().list(&ListParams::default().limit(5)).await.unwrap();
 }
 
@@ -208,7 +209,7 @@ async fn payments_list() {
 #[ignore]
 async fn transactions_list() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
     let _list = w.transactions().list(&ListParams::default().limit(5)).await.unwrap();
 }
 
@@ -220,15 +221,15 @@ async fn transactions_list() {
 #[ignore]
 async fn webhooks_lifecycle() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
 
     let wh = w
         .webhooks()
         .create(&CreateWebhookRequest::new("https://example.com/integration-test"))
         .await
         .unwrap();
-    assert!(!wh.id.is_empty());
-    assert!(!wh.secret.is_empty());
+    assert!(!wh.id.as_str().is_empty());
+    assert!(!wh.secret.expose_secret().is_empty());
 
     let list = w.webhooks().list().await.unwrap();
     assert!(list.iter().any(|h| h.id == wh.id));
@@ -244,12 +245,12 @@ async fn webhooks_lifecycle() {
 #[ignore]
 async fn l402_challenge_and_verify() {
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
 
     let challenge = w
         .l402()
         .create_challenge(&CreateL402ChallengeRequest {
-            amount: 1,
+            amount: Sats::new(1),
             description: Some("integration test".into()),
             expiry_seconds: Some(3600),
             caveats: None,
@@ -270,7 +271,7 @@ async fn l402_challenge_and_verify() {
 async fn backup_recovery() {
     let c = client();
     let resp = c.backup().recovery().await.unwrap();
-    assert!(!resp.passphrase.is_empty());
+    assert!(!resp.passphrase.expose_secret().is_empty());
 }
 
 // ---------------------------------------------------------------------------
@@ -283,7 +284,7 @@ async fn keys_rotate() {
     // Use secondary key slot to avoid disrupting the primary key we use for tests
     let c = client();
     let resp = c.keys().rotate(1).await.unwrap();
-    assert!(!resp.key.is_empty());
+    assert!(!resp.key.expose_secret().is_empty());
     assert!(!resp.name.is_empty());
 }
 
@@ -303,7 +304,13 @@ async fn error_unauthorized() {
 #[ignore]
 async fn error_not_found() {
     let c = client();
-    let err = c.wallet(wallet_id()).invoices().get(999999).await.unwrap_err();
+    let err = c
+        .wallet(wallet_id())
+        .unwrap()
+        .invoices()
+        .get(999999)
+        .await
+        .unwrap_err();
     assert!(matches!(err, LnBotError::NotFound { .. }));
 }
 
@@ -317,7 +324,7 @@ async fn sse_invoice_watch() {
     use futures_util::StreamExt;
 
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
 
     let inv = w
         .invoices()
@@ -335,6 +342,7 @@ async fn sse_invoice_watch() {
     tokio::spawn(async move {
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         c2.wallet(wallet_id())
+            .unwrap()
             .payments()
             .create(&CreatePaymentRequest::new(&bolt11))
             .await
@@ -372,7 +380,7 @@ async fn sse_events_stream() {
     use futures_util::StreamExt;
 
     let c = client();
-    let w = c.wallet(wallet_id());
+    let w = c.wallet(wallet_id()).unwrap();
 
     let inv = w
         .invoices()
@@ -389,6 +397,7 @@ async fn sse_events_stream() {
     tokio::spawn(async move {
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         c2.wallet(wallet_id())
+            .unwrap()
             .payments()
             .create(&CreatePaymentRequest::new(&bolt11))
             .await
@@ -0,0 +1,288 @@
+use std::time::Duration;
+
+use lnbot::*;
+use mockito::Matcher;
+
+// 250,000 sats, from the BOLT11 specification.
+const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+const PRICE: i64 = 250_000;
+
+fn www_authenticate() -> String {
+    format!(r#"L402 macaroon="AgEEbHNhdA", invoice="{}""#, INVOICE)
+}
+
+fn pay_response(authorization: &str) -> String {
+    format!(
+        r#"{{"authorization":"{}","paymentHash":"0001020304050607080900010203040506070809000102030405060708090102","preimage":null,"amount":{},"fee":1,"paymentNumber":9,"status":"settled"}}"#,
+        authorization, PRICE
+    )
+}
+
+async fn mock_paywall(
+    server: &mut mockito::Server,
+    authorization: &str,
+) -> (mockito::Mock, mockito::Mock) {
+    let paid = server
+        .mock("GET", "/data")
+        .match_header("authorization", authorization)
+        .with_status(200)
+        .with_body("paid content")
+        .create_async()
+        .await;
+    let challenge = server
+        .mock("GET", "/data")
+        .match_header("authorization", Matcher::Missing)
+        .with_status(402)
+        .with_header("www-authenticate", "Basic realm=\"x\"")
+        .with_header("www-authenticate", &www_authenticate())
+        .create_async()
+        .await;
+    (paid, challenge)
+}
+
+fn client(server: &mockito::Server, max_price: i64) -> L402Client {
+    let w = LnBot::new("uk_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap();
+    L402Client::new(w, max_price)
+}
+
+// ---------------------------------------------------------------------------
+// Paying challenges
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn pays_challenge_and_retries() {
+    let mut server = mockito::Server::new_async().await;
+    let (paid, challenge) = mock_paywall(&mut server, "L402 AgEEbHNhdA:00ff").await;
+    let pay = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "wwwAuthenticate": www_authenticate(),
+            "maxFee": 5,
+            "wait": true,
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(pay_response("L402 AgEEbHNhdA:00ff"))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = client(&server, PRICE).max_fee(5);
+    let url = format!("{}/data", server.url());
+    let resp = client.get(&url).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "paid content");
+
+    // The cached token is reused without paying again.
+    let resp = client.get(&url).await.unwrap();
+    assert_eq!(resp.status(), 200);
+
+    pay.assert_async().await;
+    challenge.expect(1).assert_async().await;
+    paid.expect(2).assert_async().await;
+}
+
+#[tokio::test]
+async fn rejects_challenge_above_max_price() {
+    let mut server = mockito::Server::new_async().await;
+    mock_paywall(&mut server, "L402 token").await;
+    let pay = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .expect(0)
+        .create_async()
+        .await;
+
+    let err = client(&server, PRICE - 1)
+        .get(&format!("{}/data", server.url()))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        LnBotError::L402PriceExceeded { price, max_price }
+            if price == PRICE && max_price == PRICE - 1
+    ));
+    pay.assert_async().await;
+}
+
+#[tokio::test]
+async fn host_max_price_overrides_default() {
+    let mut server = mockito::Server::new_async().await;
+    mock_paywall(&mut server, "L402 token").await;
+    let pay = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(pay_response("L402 token"))
+        .create_async()
+        .await;
+
+    let url = format!("{}/data", server.url());
+    let resp = client(&server, 1)
+        .host_max_price("127.0.0.1", PRICE)
+        .get(&url)
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    pay.assert_async().await;
+
+    let err = client(&server, PRICE)
+        .host_max_price("127.0.0.1", 1)
+        .get(&url)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LnBotError::L402PriceExceeded { .. }));
+}
+
+#[tokio::test]
+async fn per_call_max_price_overrides_limits() {
+    let mut server = mockito::Server::new_async().await;
+    mock_paywall(&mut server, "L402 token").await;
+    let pay = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(pay_response("L402 token"))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let url = format!("{}/data", server.url());
+    let err = client(&server, PRICE)
+        .execute_with_max_price(reqwest::Client::new().get(&url).build().unwrap(), 1)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        LnBotError::L402PriceExceeded { max_price, .. } if max_price == 1
+    ));
+
+    let resp = client(&server, 1)
+        .execute_with_max_price(reqwest::Client::new().get(&url).build().unwrap(), PRICE)
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    pay.assert_async().await;
+}
+
+#[tokio::test]
+async fn concurrent_challenges_share_one_payment() {
+    let mut server = mockito::Server::new_async().await;
+    let (paid, _challenge) = mock_paywall(&mut server, "L402 token").await;
+    let pay = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(pay_response("L402 token"))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = client(&server, PRICE);
+    let url = format!("{}/data", server.url());
+    let responses = futures_util::future::join_all((0..5).map(|_| client.get(&url))).await;
+    for resp in responses {
+        assert_eq!(resp.unwrap().status(), 200);
+    }
+    pay.assert_async().await;
+    paid.expect(5).assert_async().await;
+}
+
+#[tokio::test]
+async fn repays_when_cached_token_expires() {
+    let mut server = mockito::Server::new_async().await;
+    let (_paid, challenge) = mock_paywall(&mut server, "L402 token").await;
+    let pay = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(pay_response("L402 token"))
+        .expect(2)
+        .create_async()
+        .await;
+
+    let client = client(&server, PRICE).token_ttl(Duration::ZERO);
+    let url = format!("{}/data", server.url());
+    client.get(&url).await.unwrap();
+    client.get(&url).await.unwrap();
+
+    pay.assert_async().await;
+    challenge.expect(2).assert_async().await;
+}
+
+#[tokio::test]
+async fn repays_when_macaroon_valid_until_passes() {
+    use base64::Engine;
+
+    let mut macaroon = vec![2, 2, 2];
+    macaroon.extend(b"id");
+    macaroon.extend([0, 2, 17]);
+    macaroon.extend(b"api_valid_until=1");
+    macaroon.extend([0, 0, 6, 32]);
+    macaroon.extend([0; 32]);
+    let authorization = format!(
+        "L402 {}:{}",
+        base64::engine::general_purpose::STANDARD.encode(macaroon),
+        "00".repeat(32)
+    );
+
+    let mut server = mockito::Server::new_async().await;
+    let (_paid, challenge) = mock_paywall(&mut server, &authorization).await;
+    let pay = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(pay_response(&authorization))
+        .expect(2)
+        .create_async()
+        .await;
+
+    let client = client(&server, PRICE);
+    let url = format!("{}/data", server.url());
+    client.get(&url).await.unwrap();
+    client.get(&url).await.unwrap();
+
+    pay.assert_async().await;
+    challenge.expect(2).assert_async().await;
+}
+
+#[tokio::test]
+async fn passes_through_non_l402_responses() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/data")
+        .with_status(402)
+        .with_header("www-authenticate", "Basic realm=\"x\"")
+        .create_async()
+        .await;
+
+    let resp = client(&server, PRICE)
+        .get(&format!("{}/data", server.url()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 402);
+}
+
+#[tokio::test]
+async fn missing_authorization_is_an_error() {
+    let mut server = mockito::Server::new_async().await;
+    mock_paywall(&mut server, "L402 token").await;
+    server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"authorization":null,"paymentHash":"00","preimage":null,"amount":250000,"fee":null,"paymentNumber":9,"status":"pending"}"#)
+        .create_async()
+        .await;
+
+    let err = client(&server, PRICE)
+        .get(&format!("{}/data", server.url()))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "L402 error: payment 9 returned no authorization (status: pending)"
+    );
+}
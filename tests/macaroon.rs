@@ -0,0 +1,327 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use lnbot::l402::{Caveats, L402Challenge, L402ParseError, L402Token};
+use lnbot::macaroon::{Macaroon, MacaroonError};
+use lnbot::*;
+
+// From the libmacaroons documentation.
+const V1: &str = "MDAxY2xvY2F0aW9uIGh0dHA6Ly9teWJhbmsvCjAwMjZpZGVudGlmaWVyIHdlIHVzZWQgb3VyIHNlY3JldCBrZXkKMDAyZnNpZ25hdHVyZSDj2eApCFJsTAA5rhURQRXZf91ovyujebNCqvD2F9BVLwo";
+const V1_SIGNATURE: &str = "e3d9e02908526c4c0039ae15114115d97fdd68bf2ba379b342aaf0f617d0552f";
+
+const PREIMAGE: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+const PAYMENT_HASH: &str = "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925";
+
+fn field(ty: u8, data: &[u8]) -> Vec<u8> {
+    let mut out = vec![ty, data.len() as u8];
+    out.extend_from_slice(data);
+    out
+}
+
+/// A V2 macaroon with an L402 identifier, two first-party caveats and a
+/// third-party caveat.
+fn l402_macaroon() -> Vec<u8> {
+    let mut identifier = vec![0, 0];
+    identifier.extend(hex::decode(PAYMENT_HASH).unwrap());
+    identifier.extend([7; 32]);
+
+    let mut bytes = vec![2];
+    bytes.extend(field(1, b"https://api.example.com"));
+    bytes.extend(field(2, &identifier));
+    bytes.push(0);
+    bytes.extend(field(2, b"services=api:0"));
+    bytes.push(0);
+    bytes.extend(field(2, b"api_capabilities = read,write"));
+    bytes.push(0);
+    bytes.extend(field(1, b"https://auth.example.com"));
+    bytes.extend(field(2, b"third-party-id"));
+    bytes.extend(field(4, &[9; 16]));
+    bytes.push(0);
+    bytes.push(0);
+    bytes.extend(field(6, &[1; 32]));
+    bytes
+}
+
+fn base64(bytes: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+// ---------------------------------------------------------------------------
+// Macaroons
+// ---------------------------------------------------------------------------
+
+#[test]
+fn decodes_v2() {
+    let mac = Macaroon::from_bytes(&l402_macaroon()).unwrap();
+    assert_eq!(mac.location.as_deref(), Some("https://api.example.com"));
+    assert_eq!(mac.identifier.len(), 66);
+    assert_eq!(mac.signature, [1; 32]);
+    assert_eq!(mac.caveats.len(), 3);
+
+    let conditions: Vec<_> = mac.conditions().collect();
+    assert_eq!(
+        conditions,
+        [("services", "api:0"), ("api_capabilities", "read,write")]
+    );
+
+    let third_party = &mac.caveats[2];
+    assert!(!third_party.is_first_party());
+    assert_eq!(
+        third_party.location.as_deref(),
+        Some("https://auth.example.com")
+    );
+    assert_eq!(third_party.verification_id.as_deref(), Some(&[9; 16][..]));
+    assert_eq!(third_party.condition(), None);
+    assert_eq!(third_party.to_string(), "third-party-id");
+}
+
+#[test]
+fn reads_payment_hash_from_l402_identifier() {
+    let mac = Macaroon::from_bytes(&l402_macaroon()).unwrap();
+    assert_eq!(mac.payment_hash().unwrap().to_hex(), PAYMENT_HASH);
+
+    let v1 = Macaroon::decode(V1).unwrap();
+    assert_eq!(v1.payment_hash(), None);
+}
+
+#[test]
+fn reads_earliest_valid_until_caveat() {
+    let mut bytes = vec![2];
+    bytes.extend(field(2, b"id"));
+    bytes.push(0);
+    for condition in [
+        &b"api_valid_until=1700000500"[..],
+        b"search_valid_until=1700000000",
+        b"other_valid_until=soon",
+    ] {
+        bytes.extend(field(2, condition));
+        bytes.push(0);
+    }
+    bytes.push(0);
+    bytes.extend(field(6, &[0; 32]));
+
+    let mac = Macaroon::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        mac.valid_until(),
+        Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+    );
+    assert_eq!(
+        Macaroon::from_bytes(&l402_macaroon())
+            .unwrap()
+            .valid_until(),
+        None
+    );
+}
+
+#[test]
+fn decodes_v1() {
+    let mac = Macaroon::decode(V1).unwrap();
+    assert_eq!(mac.location.as_deref(), Some("http://mybank/"));
+    assert_eq!(mac.identifier, b"we used our secret key");
+    assert!(mac.caveats.is_empty());
+    assert_eq!(hex::encode(mac.signature), V1_SIGNATURE);
+}
+
+#[test]
+fn round_trips_v2_through_base64() {
+    let bytes = l402_macaroon();
+    assert_eq!(Macaroon::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+
+    let encoded = base64(&bytes);
+    let mac: Macaroon = encoded.parse().unwrap();
+    assert_eq!(mac.to_base64(), encoded);
+
+    // URL-safe and unpadded input is accepted too.
+    let url_safe = encoded.replace('+', "-").replace('/', "_");
+    let url_safe = url_safe.trim_end_matches('=');
+    assert_eq!(Macaroon::decode(url_safe).unwrap(), mac);
+}
+
+#[test]
+fn rejects_malformed_macaroons() {
+    assert_eq!(
+        Macaroon::decode("not base64!"),
+        Err(MacaroonError::InvalidBase64)
+    );
+    assert_eq!(
+        Macaroon::from_bytes(b"{\"v\":2}"),
+        Err(MacaroonError::UnsupportedFormat(b'{'))
+    );
+
+    let bytes = l402_macaroon();
+    assert_eq!(
+        Macaroon::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(MacaroonError::Truncated)
+    );
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(
+        Macaroon::from_bytes(&trailing),
+        Err(MacaroonError::TrailingData(1))
+    );
+
+    let mut short_signature = bytes[..bytes.len() - 34].to_vec();
+    short_signature.extend(field(6, &[1; 16]));
+    assert_eq!(
+        Macaroon::from_bytes(&short_signature),
+        Err(MacaroonError::InvalidSignature(16))
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Tokens and challenges
+// ---------------------------------------------------------------------------
+
+#[test]
+fn parses_authorization_token() {
+    let mac = base64(&l402_macaroon());
+    let header = format!("L402 {}:{}", mac, PREIMAGE);
+    let token: L402Token = header.parse().unwrap();
+    assert_eq!(token.macaroons.len(), 1);
+    assert_eq!(token.macaroons[0], mac);
+    assert_eq!(token.preimage.to_hex(), PREIMAGE);
+    assert!(token.verify_preimage().unwrap());
+    assert_eq!(token.to_string(), header);
+
+    let legacy = L402Token::parse(&format!("LSAT {},{}:{}", mac, V1, PREIMAGE)).unwrap();
+    assert_eq!(legacy.decode_macaroons().unwrap().len(), 2);
+}
+
+#[test]
+fn detects_wrong_preimage_offline() {
+    let header = format!("L402 {}:{}", base64(&l402_macaroon()), "11".repeat(32));
+    assert!(!L402Token::parse(&header)
+        .unwrap()
+        .verify_preimage()
+        .unwrap());
+}
+
+#[test]
+fn verify_preimage_without_macaroons_is_an_error() {
+    let mut token = L402Token::parse(&format!("L402 {}:{}", V1, PREIMAGE)).unwrap();
+    token.macaroons.clear();
+    assert_eq!(
+        token.verify_preimage(),
+        Err(L402ParseError::MissingParameter("macaroon"))
+    );
+}
+
+#[test]
+fn rejects_malformed_tokens() {
+    assert_eq!(L402Token::parse("Bearer abc"), Err(L402ParseError::Scheme));
+    assert_eq!(
+        L402Token::parse("L402 abc"),
+        Err(L402ParseError::MissingParameter("preimage"))
+    );
+    assert_eq!(
+        L402Token::parse(&format!("L402 :{}", PREIMAGE)),
+        Err(L402ParseError::MissingParameter("macaroon"))
+    );
+    assert!(matches!(
+        L402Token::parse("L402 abc:zz"),
+        Err(L402ParseError::Preimage(_))
+    ));
+}
+
+#[test]
+fn parses_challenge() {
+    let mac = base64(&l402_macaroon());
+    let challenge =
+        L402Challenge::parse(&format!(r#"L402 macaroon="{}", invoice="lnbc1...""#, mac)).unwrap();
+    assert_eq!(challenge.macaroon, mac);
+    assert_eq!(challenge.invoice, "lnbc1...");
+    assert_eq!(
+        challenge
+            .decode_macaroon()
+            .unwrap()
+            .payment_hash()
+            .unwrap()
+            .to_hex(),
+        PAYMENT_HASH
+    );
+
+    let legacy: L402Challenge = r#"LSAT token="abc", invoice="lnbc1...""#.parse().unwrap();
+    assert_eq!(legacy.macaroon, "abc");
+
+    assert_eq!(
+        L402Challenge::parse(r#"L402 macaroon="abc""#),
+        Err(L402ParseError::MissingParameter("invoice"))
+    );
+}
+
+#[test]
+fn response_helpers_decode_tokens() {
+    let mac = base64(&l402_macaroon());
+    let pay: L402PayResponse = serde_json::from_value(serde_json::json!({
+        "authorization": format!("L402 {}:{}", mac, PREIMAGE),
+        "paymentHash": PAYMENT_HASH,
+        "preimage": PREIMAGE,
+        "amount": 10,
+        "fee": 0,
+        "paymentNumber": 1,
+        "status": "settled",
+    }))
+    .unwrap();
+    assert!(pay.token().unwrap().unwrap().verify_preimage().unwrap());
+
+    let challenge: L402ChallengeResponse = serde_json::from_value(serde_json::json!({
+        "macaroon": mac,
+        "invoice": "lnbc1...",
+        "paymentHash": PAYMENT_HASH,
+        "expiresAt": "2024-01-01T00:00:00Z",
+        "wwwAuthenticate": "L402 ...",
+    }))
+    .unwrap();
+    assert_eq!(challenge.decode_macaroon().unwrap().caveats.len(), 3);
+}
+
+#[test]
+fn l402_parse_error_converts_to_lnbot_error() {
+    let e = LnBotError::from(L402ParseError::Scheme);
+    assert_eq!(e.to_string(), "Invalid L402 header: not an L402 header");
+}
+
+// ---------------------------------------------------------------------------
+// Caveat builder
+// ---------------------------------------------------------------------------
+
+#[test]
+fn builds_caveats() {
+    let caveats = Caveats::new()
+        .services(&[("api", 0), ("search", 1)])
+        .capabilities("api", &["read", "write"])
+        .valid_until("api", UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        .condition("tier", "premium");
+    let req = CreateL402ChallengeRequest {
+        amount: Sats::new(100),
+        description: None,
+        expiry_seconds: None,
+        caveats: Some(caveats.try_into().unwrap()),
+    };
+    assert_eq!(
+        req.caveats.unwrap(),
+        [
+            "services=api:0,search:1",
+            "api_capabilities=read,write",
+            "api_valid_until=1700000000",
+            "tier=premium",
+        ]
+    );
+}
+
+#[test]
+fn invalid_caveat_key_fails_build() {
+    let caveats = Caveats::new()
+        .condition("a=b", "c")
+        .condition("tier", "premium")
+        .condition("", "d");
+    assert_eq!(
+        caveats.build(),
+        Err(L402ParseError::CaveatKey("a=b".into()))
+    );
+    assert_eq!(
+        Caveats::new().capabilities("a=b", &["read"]).build(),
+        Err(L402ParseError::CaveatKey("a=b_capabilities".into()))
+    );
+}
@@ -0,0 +1,372 @@
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use futures_util::future::BoxFuture;
+use lnbot::policy::{BudgetStore, Spend};
+use lnbot::*;
+
+// 250,000 sats, from the BOLT11 specification.
+const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+const PAYEE: &str = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad";
+
+fn payment_json(status: &str, amount: i64) -> String {
+    format!(
+        r#"{{"number":1,"status":"{}","amount":{},"maxFee":10,"serviceFee":0,"actualFee":null,"address":"a@ln.bot","reference":null,"preimage":null,"txNumber":null,"failureReason":null,"createdAt":null,"settledAt":null}}"#,
+        status, amount
+    )
+}
+
+async fn mock_payments(
+    server: &mut mockito::Server,
+    status: usize,
+    expect: usize,
+) -> mockito::Mock {
+    server
+        .mock("POST", "/v1/wallets/wal_1/payments")
+        .with_status(status)
+        .with_header("content-type", "application/json")
+        .with_body(payment_json("pending", 100))
+        .expect(expect)
+        .create_async()
+        .await
+}
+
+fn wallet(server: &mockito::Server, policy: SpendingPolicy) -> Wallet {
+    LnBot::new("uk_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap()
+        .with_spending_policy(policy)
+}
+
+fn violation(err: LnBotError) -> PolicyViolation {
+    match err {
+        LnBotError::PolicyViolation(v) => v,
+        other => panic!("expected PolicyViolation, got {:?}", other),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Limits
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn per_payment_cap_blocks_before_sending() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_payments(&mut server, 200, 1).await;
+    let w = wallet(&server, SpendingPolicy::new().max_per_payment(1_000));
+
+    let err = w
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1_001))
+        .await
+        .unwrap_err();
+    let v = violation(err);
+    assert_eq!(
+        v,
+        PolicyViolation::AmountAboveLimit {
+            amount: Sats::new(1_001),
+            limit: Sats::new(1_000)
+        }
+    );
+    assert_eq!(
+        v.to_string(),
+        "amount of 1001 sats exceeds the per-payment limit of 1000 sats"
+    );
+
+    w.payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1_000))
+        .await
+        .unwrap();
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn amount_is_read_from_bolt11_invoice() {
+    let server = mockito::Server::new_async().await;
+    let w = wallet(&server, SpendingPolicy::new().max_per_payment(1_000));
+    let err = w
+        .payments()
+        .create(&CreatePaymentRequest::new(format!("lightning:{}", INVOICE)))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        violation(err),
+        PolicyViolation::AmountAboveLimit { amount, .. } if amount == 250_000
+    ));
+
+    let err = w
+        .payments()
+        .create(&CreatePaymentRequest::new("lnurl1dp68gurn8ghj7"))
+        .await
+        .unwrap_err();
+    assert_eq!(violation(err), PolicyViolation::UnknownAmount);
+}
+
+#[tokio::test]
+async fn invoice_amount_cannot_be_overridden() {
+    let server = mockito::Server::new_async().await;
+    let w = wallet(&server, SpendingPolicy::new().max_per_payment(1_000));
+
+    let err = w
+        .payments()
+        .create(&CreatePaymentRequest::new(INVOICE).amount(1))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        violation(err),
+        PolicyViolation::AmountMismatch {
+            requested: Sats::new(1),
+            invoice: Sats::new(250_000)
+        }
+    );
+
+    let err = w
+        .payments()
+        .create(&CreatePaymentRequest::new(INVOICE).amount(250_000))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        violation(err),
+        PolicyViolation::AmountAboveLimit { amount, .. } if amount == 250_000
+    ));
+}
+
+#[tokio::test]
+async fn fee_ratio_requires_bounded_max_fee() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_payments(&mut server, 200, 1).await;
+    let w = wallet(&server, SpendingPolicy::new().max_fee_ratio(0.01));
+
+    let err = w
+        .payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1_000))
+        .await
+        .unwrap_err();
+    assert_eq!(violation(err), PolicyViolation::MaxFeeRequired);
+
+    let err = w
+        .payments()
+        .create(
+            &CreatePaymentRequest::new("a@ln.bot")
+                .amount(1_000)
+                .max_fee(11),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        violation(err),
+        PolicyViolation::FeeRatioExceeded { max_fee, .. } if max_fee == 11
+    ));
+
+    w.payments()
+        .create(
+            &CreatePaymentRequest::new("a@ln.bot")
+                .amount(1_000)
+                .max_fee(10),
+        )
+        .await
+        .unwrap();
+    mock.assert_async().await;
+}
+
+// ---------------------------------------------------------------------------
+// Budgets
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn hourly_budget_counts_amount_and_fee() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_payments(&mut server, 200, 2).await;
+    let w = wallet(&server, SpendingPolicy::new().hourly_budget(1_000));
+    let req = CreatePaymentRequest::new("a@ln.bot")
+        .amount(400)
+        .max_fee(50);
+
+    w.payments().create(&req).await.unwrap();
+    // Clones of the handle share the budget.
+    w.clone().payments().create(&req).await.unwrap();
+    let err = w.payments().create(&req).await.unwrap_err();
+    assert!(matches!(
+        violation(err),
+        PolicyViolation::BudgetExceeded {
+            spent,
+            amount,
+            limit,
+            ..
+        } if spent == 900 && amount == 450 && limit == 1_000
+    ));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn rejected_payment_releases_budget() {
+    let mut server = mockito::Server::new_async().await;
+    let w = wallet(&server, SpendingPolicy::new().daily_budget(500));
+    let req = CreatePaymentRequest::new("a@ln.bot").amount(400);
+
+    let rejected = mock_payments(&mut server, 400, 1).await;
+    assert!(matches!(
+        w.payments().create(&req).await,
+        Err(LnBotError::BadRequest { .. })
+    ));
+    rejected.assert_async().await;
+    rejected.remove_async().await;
+
+    let accepted = mock_payments(&mut server, 200, 1).await;
+    w.payments().create(&req).await.unwrap();
+    accepted.assert_async().await;
+}
+
+#[tokio::test]
+async fn rate_limited_payment_releases_budget() {
+    let mut server = mockito::Server::new_async().await;
+    let w = wallet(&server, SpendingPolicy::new().daily_budget(500));
+    let req = CreatePaymentRequest::new("a@ln.bot").amount(400);
+
+    let limited = mock_payments(&mut server, 429, 1).await;
+    assert!(matches!(
+        w.payments().create(&req).await,
+        Err(LnBotError::RateLimited { .. })
+    ));
+    limited.assert_async().await;
+    limited.remove_async().await;
+
+    let accepted = mock_payments(&mut server, 200, 1).await;
+    w.payments().create(&req).await.unwrap();
+    accepted.assert_async().await;
+}
+
+#[derive(Clone, Default)]
+struct RecordingStore {
+    spends: Arc<Mutex<Vec<Spend>>>,
+}
+
+impl BudgetStore for RecordingStore {
+    fn spent_since(&self, since: SystemTime) -> BoxFuture<'_, Result<Sats, LnBotError>> {
+        let spent = self
+            .spends
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|s| s.at >= since)
+            .map(|s| s.amount)
+            .fold(Sats::ZERO, Sats::saturating_add);
+        Box::pin(async move { Ok(spent) })
+    }
+
+    fn record(&self, spend: Spend) -> BoxFuture<'_, Result<(), LnBotError>> {
+        self.spends.lock().unwrap().push(spend);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn release(&self, id: u64) -> BoxFuture<'_, Result<(), LnBotError>> {
+        self.spends.lock().unwrap().retain(|s| s.id != id);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[tokio::test]
+async fn custom_budget_store_is_used() {
+    let mut server = mockito::Server::new_async().await;
+    mock_payments(&mut server, 200, 1).await;
+    let store = RecordingStore::default();
+    let policy = SpendingPolicy::new()
+        .daily_budget(1_000)
+        .budget_store(store.clone());
+    let w = wallet(&server, policy);
+
+    w.payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(300))
+        .await
+        .unwrap();
+    let spends = store.spends.lock().unwrap();
+    assert_eq!(spends.len(), 1);
+    assert_eq!(spends[0].amount, 300);
+}
+
+// ---------------------------------------------------------------------------
+// Targets
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn deny_list_blocks_domain() {
+    let server = mockito::Server::new_async().await;
+    let w = wallet(&server, SpendingPolicy::new().deny_domain("Casino.example"));
+    let err = w
+        .payments()
+        .create(&CreatePaymentRequest::new("bob@casino.example").amount(1))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        violation(err),
+        PolicyViolation::TargetDenied("casino.example".into())
+    );
+}
+
+#[tokio::test]
+async fn allow_list_only_accepts_listed_targets() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_payments(&mut server, 200, 2).await;
+    let w = wallet(
+        &server,
+        SpendingPolicy::new()
+            .allow_domain("ln.bot")
+            .allow_node(PAYEE),
+    );
+
+    w.payments()
+        .create(&CreatePaymentRequest::new("a@ln.bot").amount(1))
+        .await
+        .unwrap();
+    w.payments()
+        .create(&CreatePaymentRequest::new(INVOICE))
+        .await
+        .unwrap();
+    for target in ["a@example.com", "lnurl1dp68gurn8ghj7"] {
+        let err = w
+            .payments()
+            .create(&CreatePaymentRequest::new(target).amount(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            violation(err),
+            PolicyViolation::TargetNotAllowed(_)
+        ));
+    }
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn l402_pay_checks_challenge_invoice() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/v1/wallets/wal_1/l402/pay")
+        .expect(0)
+        .create_async()
+        .await;
+    let w = wallet(&server, SpendingPolicy::new().deny_node(PAYEE));
+
+    let err = w
+        .l402()
+        .pay(&PayL402Request {
+            www_authenticate: format!(r#"L402 macaroon="abc", invoice="{}""#, INVOICE),
+            max_fee: None,
+            reference: None,
+            wait: None,
+            timeout: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(violation(err), PolicyViolation::TargetDenied(PAYEE.into()));
+    mock.assert_async().await;
+}
+
+#[test]
+fn policy_violation_converts_to_lnbot_error() {
+    let e = LnBotError::from(PolicyViolation::MaxFeeRequired);
+    assert_eq!(
+        e.to_string(),
+        "Payment refused by spending policy: a max fee is required"
+    );
+}
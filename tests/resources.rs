@@ -1,4 +1,7 @@
 use std::collections::HashMap;
+use std::time::{Duration, UNIX_EPOCH};
+
+use futures_util::StreamExt;
 
 use lnbot::*;
 
@@ -59,8 +62,14 @@ async fn wallet_key_create() {
         .await;
 
     let client = LnBot::new("uk_test").with_base_url(server.url());
-    let resp = client.wallet("wal_1").key().create().await.unwrap();
-    assert_eq!(resp.key, "wk_abc");
+    let resp = client
+        .wallet("wal_1")
+        .unwrap()
+        .key()
+        .create()
+        .await
+        .unwrap();
+    assert_eq!(resp.key.expose_secret(), "wk_abc");
     mock.assert_async().await;
 }
 
@@ -76,7 +85,7 @@ async fn wallet_key_get() {
         .await;
 
     let client = LnBot::new("uk_test").with_base_url(server.url());
-    let resp = client.wallet("wal_1").key().get().await.unwrap();
+    let resp = client.wallet("wal_1").unwrap().key().get().await.unwrap();
     assert_eq!(resp.hint, "wk_abc...xyz");
     mock.assert_async().await;
 }
@@ -91,7 +100,13 @@ async fn wallet_key_delete() {
         .await;
 
     let client = LnBot::new("uk_test").with_base_url(server.url());
-    client.wallet("wal_1").key().delete().await.unwrap();
+    client
+        .wallet("wal_1")
+        .unwrap()
+        .key()
+        .delete()
+        .await
+        .unwrap();
     mock.assert_async().await;
 }
 
@@ -107,8 +122,14 @@ async fn wallet_key_rotate() {
         .await;
 
     let client = LnBot::new("uk_test").with_base_url(server.url());
-    let resp = client.wallet("wal_1").key().rotate().await.unwrap();
-    assert_eq!(resp.key, "wk_new");
+    let resp = client
+        .wallet("wal_1")
+        .unwrap()
+        .key()
+        .rotate()
+        .await
+        .unwrap();
+    assert_eq!(resp.key.expose_secret(), "wk_new");
     mock.assert_async().await;
 }
 
@@ -129,7 +150,7 @@ async fn keys_rotate() {
 
     let client = LnBot::new("key_test").with_base_url(server.url());
     let resp = client.keys().rotate(1).await.unwrap();
-    assert_eq!(resp.key, "key_new");
+    assert_eq!(resp.key.expose_secret(), "key_new");
     assert_eq!(resp.name, "primary");
     mock.assert_async().await;
 }
@@ -155,6 +176,7 @@ async fn invoices_create() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let inv = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .create(&CreateInvoiceRequest::new(100).memo("test"))
         .await
@@ -182,6 +204,7 @@ async fn invoices_list() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let list = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .list(&ListParams::default().limit(5))
         .await
@@ -209,6 +232,7 @@ async fn invoices_list_with_pagination() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let list = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .list(&ListParams::default().limit(10).after(5))
         .await
@@ -229,7 +253,13 @@ async fn invoices_get() {
         .await;
 
     let client = LnBot::new("key_test").with_base_url(server.url());
-    let inv = client.wallet("wal_1").invoices().get(42).await.unwrap();
+    let inv = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .get(42)
+        .await
+        .unwrap();
     assert_eq!(inv.number, 42);
     assert_eq!(inv.status, InvoiceStatus::Settled);
     assert_eq!(inv.preimage.as_deref(), Some("abc"));
@@ -250,6 +280,7 @@ async fn invoices_get_by_hash() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let inv = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .get_by_hash("abc123")
         .await
@@ -258,6 +289,225 @@ async fn invoices_get_by_hash() {
     mock.assert_async().await;
 }
 
+// ---------------------------------------------------------------------------
+// Invoices: wait
+// ---------------------------------------------------------------------------
+
+fn invoice_with_status(status: &str, expires_at: &str) -> String {
+    format!(
+        r#"{{"number":1,"status":"{}","amount":100,"bolt11":"lnbc1...","reference":null,"memo":null,"preimage":null,"txNumber":null,"createdAt":null,"settledAt":null,"expiresAt":"{}"}}"#,
+        status, expires_at
+    )
+}
+
+/// An SSE body sending only comments, ending early once the client hangs up.
+fn keep_alive_for_two_seconds(w: &mut dyn std::io::Write) -> std::io::Result<()> {
+    for _ in 0..40 {
+        w.write_all(b": keep-alive\n\n")?;
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    Ok(())
+}
+
+const FAR_FUTURE: &str = "2999-01-01T00:00:00Z";
+
+#[tokio::test]
+async fn invoices_wait_returns_settled_from_events() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1")
+        .with_status(200)
+        .with_body(invoice_with_status("pending", FAR_FUTURE))
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1/events")
+        .match_query(mockito::Matcher::UrlEncoded("timeout".into(), "5".into()))
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(format!(
+            "event: settled\ndata: {}\n\n",
+            invoice_with_status("settled", FAR_FUTURE)
+        ))
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let invoice = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .wait(1, Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Settled);
+}
+
+#[tokio::test]
+async fn invoices_wait_sees_invoice_settled_before_stream_opened() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1")
+        .with_status(200)
+        .with_body(invoice_with_status("pending", FAR_FUTURE))
+        .expect(1)
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1")
+        .with_status(200)
+        .with_body(invoice_with_status("settled", FAR_FUTURE))
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1/events")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_chunked_body(keep_alive_for_two_seconds)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let invoice = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .wait(1, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Settled);
+}
+
+#[tokio::test]
+async fn invoices_wait_by_hash_polls_after_disconnect() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/abc123")
+        .with_status(200)
+        .with_body(invoice_with_status("pending", FAR_FUTURE))
+        .expect(2)
+        .create_async()
+        .await;
+    let settled = server
+        .mock("GET", "/v1/wallets/wal_1/invoices/abc123")
+        .with_status(200)
+        .with_body(invoice_with_status("settled", FAR_FUTURE))
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/abc123/events")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(": keep-alive\n\n")
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let invoice = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .wait("abc123", Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Settled);
+    settled.assert_async().await;
+}
+
+#[tokio::test]
+async fn invoices_wait_stops_once_expired() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1")
+        .with_status(200)
+        .with_body(invoice_with_status("pending", "2020-01-01T00:00:00Z"))
+        .expect(1)
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1")
+        .with_status(200)
+        .with_body(invoice_with_status("expired", "2020-01-01T00:00:00Z"))
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let started = std::time::Instant::now();
+    let invoice = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .wait(1, Duration::from_secs(30))
+        .await
+        .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Expired);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn invoices_wait_times_out_if_still_pending_after_expiry() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1")
+        .with_status(200)
+        .with_body(invoice_with_status("pending", "2020-01-01T00:00:00Z"))
+        .expect(2)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let err = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .wait(1, Duration::from_secs(30))
+        .await
+        .unwrap_err();
+    match err {
+        LnBotError::InvoiceTimeout { invoice } => {
+            assert_eq!(invoice.status, InvoiceStatus::Pending)
+        }
+        other => panic!("expected InvoiceTimeout, got {:?}", other),
+    }
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn invoices_wait_times_out_with_last_known_invoice() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1")
+        .with_status(200)
+        .with_body(invoice_with_status("pending", FAR_FUTURE))
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1/events")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body("")
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let err = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .wait(1, Duration::from_millis(300))
+        .await
+        .unwrap_err();
+    match err {
+        LnBotError::InvoiceTimeout { invoice } => {
+            assert_eq!(invoice.status, InvoiceStatus::Pending)
+        }
+        other => panic!("expected InvoiceTimeout, got {:?}", other),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Public Invoices (top-level, no auth)
 // ---------------------------------------------------------------------------
@@ -268,7 +518,7 @@ async fn invoices_create_for_wallet() {
     let mock = server
         .mock("POST", "/v1/invoices/for-wallet")
         .match_body(mockito::Matcher::Json(
-            serde_json::json!({"walletId": "wid", "amount": 200}),
+            serde_json::json!({"walletId": "wal_1", "amount": 200}),
         ))
         .with_status(200)
         .with_header("content-type", "application/json")
@@ -279,7 +529,10 @@ async fn invoices_create_for_wallet() {
     let client = LnBot::unauthenticated().with_base_url(server.url());
     let resp = client
         .invoices()
-        .create_for_wallet(&CreateInvoiceForWalletRequest::new("wid", 200))
+        .create_for_wallet(&CreateInvoiceForWalletRequest::new(
+            "wal_1".parse::<WalletId>().unwrap(),
+            200,
+        ))
         .await
         .unwrap();
     assert_eq!(resp.bolt11, "lnbc2...");
@@ -320,7 +573,7 @@ async fn payments_create() {
     let mut server = mockito::Server::new_async().await;
     let mock = server
         .mock("POST", "/v1/wallets/wal_1/payments")
-        .match_body(mockito::Matcher::Json(
+        .match_body(mockito::Matcher::PartialJson(
             serde_json::json!({"target": "user@ln.bot", "amount": 50}),
         ))
         .with_status(200)
@@ -332,12 +585,14 @@ async fn payments_create() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let p = client
         .wallet("wal_1")
+        .unwrap()
         .payments()
         .create(&CreatePaymentRequest::new("user@ln.bot").amount(50))
         .await
         .unwrap();
     assert_eq!(p.number, 1);
     assert_eq!(p.status, PaymentStatus::Pending);
+    assert!(p.idempotency_key.is_some());
     mock.assert_async().await;
 }
 
@@ -355,6 +610,7 @@ async fn payments_list() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let list = client
         .wallet("wal_1")
+        .unwrap()
         .payments()
         .list(&ListParams::default())
         .await
@@ -375,7 +631,13 @@ async fn payments_get() {
         .await;
 
     let client = LnBot::new("key_test").with_base_url(server.url());
-    let p = client.wallet("wal_1").payments().get(7).await.unwrap();
+    let p = client
+        .wallet("wal_1")
+        .unwrap()
+        .payments()
+        .get(7)
+        .await
+        .unwrap();
     assert_eq!(p.number, 7);
     mock.assert_async().await;
 }
@@ -394,6 +656,7 @@ async fn payments_get_by_hash() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     client
         .wallet("wal_1")
+        .unwrap()
         .payments()
         .get_by_hash("hash123")
         .await
@@ -419,6 +682,7 @@ async fn payments_resolve() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let resp = client
         .wallet("wal_1")
+        .unwrap()
         .payments()
         .resolve("user@ln.bot")
         .await
@@ -428,6 +692,221 @@ async fn payments_resolve() {
     mock.assert_async().await;
 }
 
+// ---------------------------------------------------------------------------
+// Payments: pay_and_wait
+// ---------------------------------------------------------------------------
+
+fn payment_with_status(status: &str) -> String {
+    format!(
+        r#"{{"number":1,"status":"{}","amount":100,"maxFee":10,"serviceFee":0,"actualFee":null,"address":"user@ln.bot","reference":null,"preimage":null,"txNumber":null,"failureReason":null,"createdAt":null,"settledAt":null}}"#,
+        status
+    )
+}
+
+async fn mock_create_payment(server: &mut mockito::ServerGuard, status: &str) -> mockito::Mock {
+    server
+        .mock("POST", "/v1/wallets/wal_1/payments")
+        .with_status(200)
+        .with_body(payment_with_status(status))
+        .create_async()
+        .await
+}
+
+#[tokio::test]
+async fn pay_and_wait_returns_terminal_payment_without_waiting() {
+    let mut server = mockito::Server::new_async().await;
+    mock_create_payment(&mut server, "settled").await;
+    let events = server
+        .mock("GET", mockito::Matcher::Any)
+        .expect(0)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let p = client
+        .wallet("wal_1")
+        .unwrap()
+        .payments()
+        .pay_and_wait(
+            &CreatePaymentRequest::new("user@ln.bot"),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert_eq!(p.status, PaymentStatus::Settled);
+    events.assert_async().await;
+}
+
+#[tokio::test]
+async fn pay_and_wait_follows_events() {
+    let mut server = mockito::Server::new_async().await;
+    mock_create_payment(&mut server, "pending").await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments/1/events")
+        .match_query(mockito::Matcher::UrlEncoded("timeout".into(), "5".into()))
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(format!(
+            "event: processing\ndata: {}\n\nevent: settled\ndata: {}\n\n",
+            payment_with_status("processing"),
+            payment_with_status("settled")
+        ))
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let p = client
+        .wallet("wal_1")
+        .unwrap()
+        .payments()
+        .pay_and_wait(
+            &CreatePaymentRequest::new("user@ln.bot"),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert_eq!(p.status, PaymentStatus::Settled);
+}
+
+#[tokio::test]
+async fn pay_and_wait_sees_payment_settled_before_stream_opened() {
+    let mut server = mockito::Server::new_async().await;
+    mock_create_payment(&mut server, "pending").await;
+    // The stream stays open without events: the settlement came before it.
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments/1/events")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_chunked_body(keep_alive_for_two_seconds)
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments/1")
+        .with_status(200)
+        .with_body(payment_with_status("settled"))
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let p = client
+        .wallet("wal_1")
+        .unwrap()
+        .payments()
+        .pay_and_wait(
+            &CreatePaymentRequest::new("user@ln.bot"),
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(p.status, PaymentStatus::Settled);
+}
+
+#[tokio::test]
+async fn pay_and_wait_polls_when_stream_fails() {
+    let mut server = mockito::Server::new_async().await;
+    mock_create_payment(&mut server, "pending").await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments/1/events")
+        .match_query(mockito::Matcher::Any)
+        .with_status(503)
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments/1")
+        .with_status(200)
+        .with_body(payment_with_status("processing"))
+        .expect(1)
+        .create_async()
+        .await;
+    let failed = server
+        .mock("GET", "/v1/wallets/wal_1/payments/1")
+        .with_status(200)
+        .with_body(payment_with_status("failed"))
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let p = client
+        .wallet("wal_1")
+        .unwrap()
+        .payments()
+        .pay_and_wait(
+            &CreatePaymentRequest::new("user@ln.bot"),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert_eq!(p.status, PaymentStatus::Failed);
+    failed.assert_async().await;
+}
+
+#[tokio::test]
+async fn pay_and_wait_times_out_with_last_known_payment() {
+    let mut server = mockito::Server::new_async().await;
+    mock_create_payment(&mut server, "pending").await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments/1/events")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(format!(
+            "event: processing\ndata: {}\n\n",
+            payment_with_status("processing")
+        ))
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments/1")
+        .with_status(200)
+        .with_body(payment_with_status("processing"))
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let err = client
+        .wallet("wal_1")
+        .unwrap()
+        .payments()
+        .pay_and_wait(
+            &CreatePaymentRequest::new("user@ln.bot"),
+            Duration::from_millis(300),
+        )
+        .await
+        .unwrap_err();
+    match err {
+        LnBotError::PaymentTimeout { payment } => {
+            assert_eq!(payment.number, 1);
+            assert_eq!(payment.status, PaymentStatus::Processing);
+        }
+        other => panic!("expected PaymentTimeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn pay_and_wait_returns_create_error() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/v1/wallets/wal_1/payments")
+        .with_status(400)
+        .with_body("insufficient balance")
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let err = client
+        .wallet("wal_1")
+        .unwrap()
+        .payments()
+        .pay_and_wait(
+            &CreatePaymentRequest::new("user@ln.bot"),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LnBotError::BadRequest { .. }));
+}
+
 // ---------------------------------------------------------------------------
 // Addresses (wallet-scoped)
 // ---------------------------------------------------------------------------
@@ -446,6 +925,7 @@ async fn addresses_create() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let addr = client
         .wallet("wal_1")
+        .unwrap()
         .addresses()
         .create(&CreateAddressRequest {
             address: Some("bot@ln.bot".into()),
@@ -469,7 +949,13 @@ async fn addresses_list() {
         .await;
 
     let client = LnBot::new("key_test").with_base_url(server.url());
-    let list = client.wallet("wal_1").addresses().list().await.unwrap();
+    let list = client
+        .wallet("wal_1")
+        .unwrap()
+        .addresses()
+        .list()
+        .await
+        .unwrap();
     assert_eq!(list.len(), 1);
     assert!(list[0].generated);
     mock.assert_async().await;
@@ -487,6 +973,7 @@ async fn addresses_delete_url_encodes() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     client
         .wallet("wal_1")
+        .unwrap()
         .addresses()
         .delete("user@ln.bot")
         .await
@@ -511,6 +998,7 @@ async fn addresses_transfer() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let resp = client
         .wallet("wal_1")
+        .unwrap()
         .addresses()
         .transfer("user@ln.bot", &TransferAddressRequest::new("key_other"))
         .await
@@ -538,6 +1026,7 @@ async fn transactions_list() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let list = client
         .wallet("wal_1")
+        .unwrap()
         .transactions()
         .list(&ListParams::default())
         .await
@@ -547,6 +1036,242 @@ async fn transactions_list() {
     mock.assert_async().await;
 }
 
+// ---------------------------------------------------------------------------
+// Pagination (list_all / iter)
+// ---------------------------------------------------------------------------
+
+fn transaction_page(numbers: &[i32]) -> String {
+    let items: Vec<String> = numbers
+        .iter()
+        .map(|n| format!(r#"{{"number":{},"type":"credit","amount":100,"balanceAfter":100,"networkFee":0,"serviceFee":0,"paymentHash":null,"preimage":null,"reference":null,"note":null,"createdAt":null}}"#, n))
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn invoice_page(numbers: &[i32]) -> String {
+    let items: Vec<String> = numbers
+        .iter()
+        .map(|n| format!(r#"{{"number":{},"status":"settled","amount":100,"bolt11":"lnbc1...","reference":null,"memo":null,"preimage":null,"txNumber":null,"createdAt":null,"settledAt":null,"expiresAt":null}}"#, n))
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn payment_json(number: i32, created_at: &str) -> String {
+    format!(
+        r#"{{"number":{},"status":"settled","amount":100,"maxFee":10,"serviceFee":0,"actualFee":0,"address":"user@ln.bot","reference":null,"preimage":null,"txNumber":null,"failureReason":null,"createdAt":"{}","settledAt":null}}"#,
+        number, created_at
+    )
+}
+
+#[tokio::test]
+async fn transactions_list_all_follows_cursor() {
+    let mut server = mockito::Server::new_async().await;
+    let first = server
+        .mock("GET", "/v1/wallets/wal_1/transactions")
+        .match_query(mockito::Matcher::UrlEncoded("limit".into(), "2".into()))
+        .with_status(200)
+        .with_body(transaction_page(&[5, 4]))
+        .create_async()
+        .await;
+    let second = server
+        .mock("GET", "/v1/wallets/wal_1/transactions")
+        .match_query(mockito::Matcher::AllOf(vec![
+            mockito::Matcher::UrlEncoded("limit".into(), "2".into()),
+            mockito::Matcher::UrlEncoded("after".into(), "4".into()),
+        ]))
+        .with_status(200)
+        .with_body(transaction_page(&[3, 2]))
+        .create_async()
+        .await;
+    let third = server
+        .mock("GET", "/v1/wallets/wal_1/transactions")
+        .match_query(mockito::Matcher::AllOf(vec![
+            mockito::Matcher::UrlEncoded("limit".into(), "2".into()),
+            mockito::Matcher::UrlEncoded("after".into(), "2".into()),
+        ]))
+        .with_status(200)
+        .with_body(transaction_page(&[1]))
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let mut stream = client
+        .wallet("wal_1")
+        .unwrap()
+        .transactions()
+        .list_all(ListAllParams::default().page_size(2));
+    let mut numbers = Vec::new();
+    while let Some(tx) = stream.next().await {
+        numbers.push(tx.unwrap().number);
+    }
+
+    assert_eq!(numbers, vec![5, 4, 3, 2, 1]);
+    assert_eq!(stream.cursor(), Some(1));
+    first.assert_async().await;
+    second.assert_async().await;
+    third.assert_async().await;
+}
+
+#[tokio::test]
+async fn invoices_iter_stops_on_empty_page() {
+    let mut server = mockito::Server::new_async().await;
+    let first = server
+        .mock("GET", "/v1/wallets/wal_1/invoices")
+        .match_query(mockito::Matcher::Missing)
+        .with_status(200)
+        .with_body(invoice_page(&[2, 1]))
+        .create_async()
+        .await;
+    let second = server
+        .mock("GET", "/v1/wallets/wal_1/invoices")
+        .match_query(mockito::Matcher::UrlEncoded("after".into(), "1".into()))
+        .with_status(200)
+        .with_body("[]")
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let invoices: Vec<InvoiceResponse> = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .iter()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(invoices.len(), 2);
+    first.assert_async().await;
+    second.assert_async().await;
+}
+
+#[tokio::test]
+async fn invoices_list_all_stops_at_max_items() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices")
+        .match_query(mockito::Matcher::UrlEncoded("after".into(), "10".into()))
+        .with_status(200)
+        .with_body(invoice_page(&[9, 8]))
+        .create_async()
+        .await;
+    let second = server
+        .mock("GET", "/v1/wallets/wal_1/invoices")
+        .match_query(mockito::Matcher::UrlEncoded("after".into(), "8".into()))
+        .with_status(200)
+        .with_body(invoice_page(&[7, 6]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let mut stream = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .list_all(ListAllParams::default().page_size(2).after(10).max_items(3));
+    let mut numbers = Vec::new();
+    while let Some(invoice) = stream.next().await {
+        numbers.push(invoice.unwrap().number);
+    }
+
+    assert_eq!(numbers, vec![9, 8, 7]);
+    assert_eq!(stream.cursor(), Some(7));
+    second.assert_async().await;
+}
+
+#[tokio::test]
+async fn payments_list_all_stops_at_number() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments")
+        .with_status(200)
+        .with_body(format!(
+            "[{},{},{}]",
+            payment_json(5, "2024-01-05T00:00:00Z"),
+            payment_json(4, "2024-01-04T00:00:00Z"),
+            payment_json(3, "2024-01-03T00:00:00Z"),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let mut stream = client
+        .wallet("wal_1")
+        .unwrap()
+        .payments()
+        .list_all(ListAllParams::default().until_number(4));
+    let mut numbers = Vec::new();
+    while let Some(payment) = stream.next().await {
+        numbers.push(payment.unwrap().number);
+    }
+
+    assert_eq!(numbers, vec![5]);
+    assert_eq!(stream.cursor(), Some(5));
+}
+
+#[tokio::test]
+async fn payments_list_all_stops_at_date() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/payments")
+        .with_status(200)
+        .with_body(format!(
+            "[{},{},{}]",
+            payment_json(5, "2024-01-03T00:00:00Z"),
+            payment_json(4, "2024-01-02T00:00:00.5Z"),
+            payment_json(3, "2024-01-02T01:00:00+02:00"),
+        ))
+        .create_async()
+        .await;
+
+    // 2024-01-02T00:00:00Z
+    let since = UNIX_EPOCH + Duration::from_secs(1_704_153_600);
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let payments: Vec<PaymentResponse> = client
+        .wallet("wal_1")
+        .unwrap()
+        .payments()
+        .list_all(ListAllParams::default().since(since))
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    let numbers: Vec<i32> = payments.iter().map(|p| p.number.get()).collect();
+    assert_eq!(numbers, vec![5, 4]);
+}
+
+#[tokio::test]
+async fn list_all_ends_with_error_and_keeps_cursor() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/transactions")
+        .match_query(mockito::Matcher::Missing)
+        .with_status(200)
+        .with_body(transaction_page(&[3, 2]))
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/transactions")
+        .match_query(mockito::Matcher::UrlEncoded("after".into(), "2".into()))
+        .with_status(500)
+        .with_body("boom")
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let mut stream = client.wallet("wal_1").unwrap().transactions().iter();
+    assert_eq!(stream.next().await.unwrap().unwrap().number, 3);
+    assert_eq!(stream.next().await.unwrap().unwrap().number, 2);
+    assert!(matches!(
+        stream.next().await,
+        Some(Err(LnBotError::Api { status: 500, .. }))
+    ));
+    assert!(stream.next().await.is_none());
+    assert_eq!(stream.cursor(), Some(2));
+}
+
 // ---------------------------------------------------------------------------
 // Webhooks (wallet-scoped)
 // ---------------------------------------------------------------------------
@@ -568,12 +1293,13 @@ async fn webhooks_create() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let wh = client
         .wallet("wal_1")
+        .unwrap()
         .webhooks()
         .create(&CreateWebhookRequest::new("https://example.com/hook"))
         .await
         .unwrap();
     assert_eq!(wh.id, "wh1");
-    assert_eq!(wh.secret, "sec123");
+    assert_eq!(wh.secret.expose_secret(), "sec123");
     mock.assert_async().await;
 }
 
@@ -589,7 +1315,13 @@ async fn webhooks_list() {
         .await;
 
     let client = LnBot::new("key_test").with_base_url(server.url());
-    let list = client.wallet("wal_1").webhooks().list().await.unwrap();
+    let list = client
+        .wallet("wal_1")
+        .unwrap()
+        .webhooks()
+        .list()
+        .await
+        .unwrap();
     assert_eq!(list.len(), 1);
     assert!(list[0].active);
     mock.assert_async().await;
@@ -607,6 +1339,7 @@ async fn webhooks_delete() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     client
         .wallet("wal_1")
+        .unwrap()
         .webhooks()
         .delete("wh-123")
         .await
@@ -631,7 +1364,7 @@ async fn backup_recovery() {
 
     let client = LnBot::new("key_test").with_base_url(server.url());
     let resp = client.backup().recovery().await.unwrap();
-    assert_eq!(resp.passphrase, "word1 word2 word3");
+    assert_eq!(resp.passphrase.expose_secret(), "word1 word2 word3");
     mock.assert_async().await;
 }
 
@@ -699,7 +1432,7 @@ async fn restore_recovery() {
         .await
         .unwrap();
     assert_eq!(resp.wallet_id, "w1");
-    assert_eq!(resp.primary_key, "pk");
+    assert_eq!(resp.primary_key.expose_secret(), "pk");
     mock.assert_async().await;
 }
 
@@ -763,9 +1496,10 @@ async fn l402_create_challenge() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let resp = client
         .wallet("wal_1")
+        .unwrap()
         .l402()
         .create_challenge(&CreateL402ChallengeRequest {
-            amount: 100,
+            amount: Sats::new(100),
             description: None,
             expiry_seconds: None,
             caveats: None,
@@ -794,6 +1528,7 @@ async fn l402_verify() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let resp = client
         .wallet("wal_1")
+        .unwrap()
         .l402()
         .verify(&VerifyL402Request {
             authorization: "L402 token".into(),
@@ -818,6 +1553,7 @@ async fn l402_pay() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let resp = client
         .wallet("wal_1")
+        .unwrap()
         .l402()
         .pay(&PayL402Request {
             www_authenticate: "L402 mac:inv".into(),
@@ -828,7 +1564,10 @@ async fn l402_pay() {
         })
         .await
         .unwrap();
-    assert_eq!(resp.authorization.as_deref(), Some("L402 final"));
+    assert_eq!(
+        resp.authorization.as_ref().map(Secret::expose_secret),
+        Some(&"L402 final".to_string())
+    );
     assert_eq!(resp.status, "settled");
     mock.assert_async().await;
 }
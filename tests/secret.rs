@@ -0,0 +1,114 @@
+use lnbot::webhooks::{self, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use lnbot::*;
+use reqwest::header::HeaderMap;
+
+// ---------------------------------------------------------------------------
+// Secret
+// ---------------------------------------------------------------------------
+
+#[test]
+fn secret_is_redacted() {
+    let secret = Secret::from("hunter2");
+    assert_eq!(format!("{:?}", secret), "Secret([REDACTED])");
+    assert_eq!(format!("{}", secret), "[REDACTED]");
+    assert_eq!(secret.expose_secret(), "hunter2");
+
+    let bytes = Secret::new(vec![1u8, 2, 3]);
+    assert_eq!(format!("{:?}", bytes), "Secret([REDACTED])");
+    assert_eq!(bytes.expose_secret(), &[1, 2, 3]);
+}
+
+#[test]
+fn secret_round_trips_through_serde() {
+    let secret: Secret = serde_json::from_str(r#""word1 word2""#).unwrap();
+    assert_eq!(secret.expose_secret(), "word1 word2");
+    assert_eq!(serde_json::to_string(&secret).unwrap(), r#""word1 word2""#);
+    assert_eq!(secret.clone(), secret);
+
+    let req = RecoveryRestoreRequest::new("word1 word2");
+    assert_eq!(
+        serde_json::to_value(&req).unwrap(),
+        serde_json::json!({"passphrase": "word1 word2"})
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Credential-bearing types
+// ---------------------------------------------------------------------------
+
+fn assert_redacted<T: std::fmt::Debug>(value: &T, secrets: &[&str]) {
+    let debug = format!("{:?}", value);
+    for secret in secrets {
+        assert!(!debug.contains(secret), "{:?} leaked in {}", secret, debug);
+    }
+}
+
+#[test]
+fn responses_do_not_leak_credentials_in_debug() {
+    let r: RegisterResponse = serde_json::from_str(
+        r#"{"userId":"u1","primaryKey":"uk_one","secondaryKey":"uk_two","recoveryPassphrase":"alpha bravo"}"#,
+    )
+    .unwrap();
+    assert_redacted(&r, &["uk_one", "uk_two", "alpha bravo"]);
+    assert_eq!(r.recovery_passphrase.expose_secret(), "alpha bravo");
+
+    let r: RecoveryBackupResponse =
+        serde_json::from_str(r#"{"passphrase":"alpha bravo"}"#).unwrap();
+    assert_redacted(&r, &["alpha bravo"]);
+
+    let r: RecoveryRestoreResponse = serde_json::from_str(
+        r#"{"walletId":"wal_1","name":"w","primaryKey":"uk_one","secondaryKey":"uk_two"}"#,
+    )
+    .unwrap();
+    assert_redacted(&r, &["uk_one", "uk_two"]);
+
+    let r: CreateWebhookResponse = serde_json::from_str(
+        r#"{"id":"wh1","url":"https://example.com","secret":"whsec_abc","createdAt":null}"#,
+    )
+    .unwrap();
+    assert_redacted(&r, &["whsec_abc"]);
+
+    let preimage = "ab".repeat(32);
+    let r: L402PayResponse = serde_json::from_str(
+        r#"{"authorization":"L402 mac:pre","paymentHash":"00","preimage":null,"amount":10,"fee":null,"paymentNumber":1,"status":"settled"}"#,
+    )
+    .unwrap();
+    assert_redacted(&r, &["mac:pre"]);
+    assert_redacted(
+        &VerifyL402Request {
+            authorization: "L402 mac:pre".into(),
+        },
+        &["mac:pre"],
+    );
+    let token = lnbot::l402::L402Token::parse(&format!("L402 mac:{}", preimage)).unwrap();
+    assert_redacted(&token, &[&preimage]);
+    assert_eq!(token.preimage.to_hex(), preimage);
+
+    assert_redacted(
+        &RecoveryRestoreRequest::new("alpha bravo"),
+        &["alpha bravo"],
+    );
+    assert_redacted(&LnBot::builder().api_key("uk_secret"), &["uk_secret"]);
+}
+
+#[test]
+fn webhook_receiver_takes_secret_from_response() {
+    let r: CreateWebhookResponse = serde_json::from_str(
+        r#"{"id":"wh1","url":"https://example.com","secret":"whsec_abc","createdAt":null}"#,
+    )
+    .unwrap();
+    let body = br#"{"event":"wallet.updated","createdAt":"2024-01-01T00:00:00Z","data":{}}"#;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut headers = HeaderMap::new();
+    headers.insert(TIMESTAMP_HEADER, now.to_string().parse().unwrap());
+    headers.insert(
+        SIGNATURE_HEADER,
+        webhooks::sign("whsec_abc", now, body).parse().unwrap(),
+    );
+
+    let receiver = webhooks::WebhookReceiver::new(r.secret);
+    assert!(receiver.verify(&headers, body).unwrap().is_some());
+}
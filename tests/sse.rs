@@ -1,4 +1,7 @@
+use std::time::Duration;
+
 use futures_util::StreamExt;
+use lnbot::sse::{self, SseDecoder};
 use lnbot::*;
 
 // ---------------------------------------------------------------------------
@@ -19,6 +22,7 @@ async fn invoice_watch_yields_event() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let events: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .watch(1, None)
         .collect::<Vec<_>>()
@@ -55,6 +59,7 @@ async fn invoice_watch_multiple_events() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let events: Vec<InvoiceEvent> = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .watch(1, None)
         .collect::<Vec<_>>()
@@ -89,6 +94,7 @@ async fn invoice_watch_skips_comments() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let events: Vec<InvoiceEvent> = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .watch(1, None)
         .collect::<Vec<_>>()
@@ -114,6 +120,7 @@ async fn invoice_watch_empty_stream() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let events: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .watch(1, None)
         .collect::<Vec<_>>()
@@ -139,6 +146,7 @@ async fn invoice_watch_with_timeout() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let _: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .watch(42, Some(120))
         .collect()
@@ -161,6 +169,7 @@ async fn invoice_watch_sends_sse_accept_header() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let _: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .watch(1, None)
         .collect()
@@ -183,6 +192,7 @@ async fn invoice_watch_sends_authorization() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let _: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .watch(1, None)
         .collect()
@@ -204,6 +214,7 @@ async fn invoice_watch_by_hash() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let _: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .watch_by_hash("abc123", None)
         .collect()
@@ -224,6 +235,7 @@ async fn invoice_watch_http_error() {
     let client = LnBot::new("bad_key").with_base_url(server.url());
     let results: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .invoices()
         .watch(1, None)
         .collect()
@@ -251,6 +263,7 @@ async fn payment_watch_yields_event() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let events: Vec<PaymentEvent> = client
         .wallet("wal_1")
+        .unwrap()
         .payments()
         .watch(1, None)
         .collect::<Vec<_>>()
@@ -278,6 +291,7 @@ async fn payment_watch_with_timeout() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let _: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .payments()
         .watch(7, Some(60))
         .collect()
@@ -299,6 +313,7 @@ async fn payment_watch_by_hash() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let _: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .payments()
         .watch_by_hash("hash123", None)
         .collect()
@@ -319,6 +334,7 @@ async fn payment_watch_http_error() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let results: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .payments()
         .watch(1, None)
         .collect()
@@ -339,13 +355,14 @@ async fn events_stream_yields_event() {
         .mock("GET", "/v1/wallets/wal_1/events")
         .with_status(200)
         .with_header("content-type", "text/event-stream")
-        .with_body("data: {\"event\":\"invoice.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{\"number\":1}}\n")
+        .with_body("data: {\"event\":\"invoice.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{\"number\":1}}\n\n")
         .create_async()
         .await;
 
     let client = LnBot::new("key_test").with_base_url(server.url());
     let events: Vec<WalletEvent> = client
         .wallet("wal_1")
+        .unwrap()
         .events()
         .stream()
         .collect::<Vec<_>>()
@@ -363,7 +380,9 @@ async fn events_stream_yields_event() {
 async fn events_stream_multiple() {
     let body = "\
         data: {\"event\":\"invoice.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{\"number\":1}}\n\
-        data: {\"event\":\"payment.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{\"number\":2}}\n";
+        \n\
+        data: {\"event\":\"payment.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{\"number\":2}}\n\
+        \n";
 
     let mut server = mockito::Server::new_async().await;
     server
@@ -377,6 +396,7 @@ async fn events_stream_multiple() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let events: Vec<WalletEvent> = client
         .wallet("wal_1")
+        .unwrap()
         .events()
         .stream()
         .collect::<Vec<_>>()
@@ -395,7 +415,8 @@ async fn events_stream_skips_non_data_lines() {
     let body = "\
         : keepalive\n\
         event: ignored\n\
-        data: {\"event\":\"payment.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{\"number\":1}}\n";
+        data: {\"event\":\"payment.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{\"number\":1}}\n\
+        \n";
 
     let mut server = mockito::Server::new_async().await;
     server
@@ -409,6 +430,7 @@ async fn events_stream_skips_non_data_lines() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let events: Vec<WalletEvent> = client
         .wallet("wal_1")
+        .unwrap()
         .events()
         .stream()
         .collect::<Vec<_>>()
@@ -434,7 +456,13 @@ async fn events_stream_sends_sse_headers() {
         .await;
 
     let client = LnBot::new("key_test").with_base_url(server.url());
-    let _: Vec<_> = client.wallet("wal_1").events().stream().collect().await;
+    let _: Vec<_> = client
+        .wallet("wal_1")
+        .unwrap()
+        .events()
+        .stream()
+        .collect()
+        .await;
     mock.assert_async().await;
 }
 
@@ -452,6 +480,7 @@ async fn events_stream_empty() {
     let client = LnBot::new("key_test").with_base_url(server.url());
     let events: Vec<_> = client
         .wallet("wal_1")
+        .unwrap()
         .events()
         .stream()
         .collect::<Vec<_>>()
@@ -474,8 +503,481 @@ async fn events_stream_http_error() {
         .await;
 
     let client = LnBot::new("key_test").with_base_url(server.url());
-    let results: Vec<_> = client.wallet("wal_1").events().stream().collect().await;
+    let results: Vec<_> = client
+        .wallet("wal_1")
+        .unwrap()
+        .events()
+        .stream()
+        .collect()
+        .await;
     assert_eq!(results.len(), 1);
     let err = results[0].as_ref().unwrap_err();
     assert!(matches!(err, LnBotError::Forbidden { .. }));
 }
+
+#[tokio::test]
+async fn events_stream_outlives_wallet_handle() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/events")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body("data: {\"event\":\"invoice.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{}}\n\n")
+        .create_async()
+        .await;
+
+    let stream = LnBot::new("key_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap()
+        .events()
+        .stream();
+    let events = tokio::spawn(stream.collect::<Vec<_>>()).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].as_ref().unwrap().event, "invoice.settled");
+}
+
+#[tokio::test]
+async fn invoice_watch_handles_crlf_and_multiline_data() {
+    let body = "event: settled\r\n\
+        data: {\"number\":1,\"status\":\"settled\",\"amount\":100,\r\n\
+        data: \"bolt11\":\"lnbc1...\",\"reference\":null,\"memo\":null,\"preimage\":null,\r\n\
+        data: \"txNumber\":null,\"createdAt\":null,\"settledAt\":null,\"expiresAt\":null}\r\n\
+        \r\n";
+
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/invoices/1/events")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(body)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let events: Vec<InvoiceEvent> = client
+        .wallet("wal_1")
+        .unwrap()
+        .invoices()
+        .watch(1, None)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event, InvoiceEventType::Settled);
+    assert_eq!(events[0].data.amount, 100);
+}
+
+#[tokio::test]
+async fn events_typed_stream() {
+    let body = "\
+        data: {\"event\":\"invoice.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{\"number\":1,\"status\":\"settled\",\"amount\":100,\"bolt11\":\"lnbc1...\",\"reference\":null,\"memo\":null,\"preimage\":null,\"txNumber\":null,\"createdAt\":null,\"settledAt\":null,\"expiresAt\":null}}\n\
+        \n\
+        data: {\"event\":\"wallet.renamed\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{\"name\":\"x\"}}\n\
+        \n";
+
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/events")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(body)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let events: Vec<TypedWalletEvent> = client
+        .wallet("wal_1")
+        .unwrap()
+        .events()
+        .typed_stream()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        &events[0].kind,
+        WalletEventKind::InvoiceSettled(i) if i.amount == 100
+    ));
+    assert!(
+        matches!(&events[1].kind, WalletEventKind::Unknown { event, .. } if event == "wallet.renamed")
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Events stream (reconnecting)
+// ---------------------------------------------------------------------------
+
+fn wallet_event(n: u32) -> String {
+    format!(
+        "{{\"event\":\"invoice.settled\",\"createdAt\":\"2024-01-01T00:00:00Z\",\"data\":{{\"number\":{}}}}}",
+        n
+    )
+}
+
+fn fast_reconnect() -> ReconnectPolicy {
+    ReconnectPolicy::default()
+        .base_delay(Duration::from_millis(1))
+        .max_delay(Duration::from_millis(5))
+}
+
+#[tokio::test]
+async fn reconnecting_stream_resumes_with_last_event_id() {
+    let mut server = mockito::Server::new_async().await;
+    let first = server
+        .mock("GET", "/v1/wallets/wal_1/events")
+        .match_header("last-event-id", mockito::Matcher::Missing)
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(format!(
+            "retry: 5\nid: evt_1\ndata: {}\n\n",
+            wallet_event(1)
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let second = server
+        .mock("GET", "/v1/wallets/wal_1/events")
+        .match_header("last-event-id", "evt_1")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(format!("id: evt_2\ndata: {}\n\n", wallet_event(2)))
+        .expect_at_least(1)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let items: Vec<_> = client
+        .wallet("wal_1")
+        .unwrap()
+        .events()
+        .stream_reconnecting(fast_reconnect())
+        .take(5)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert!(matches!(
+        items[0],
+        StreamEvent::State(ConnectionState::Connected)
+    ));
+    assert!(matches!(&items[1], StreamEvent::Event(e) if e.data["number"] == 1));
+    match &items[2] {
+        // The server's retry hint replaces the base delay.
+        StreamEvent::State(ConnectionState::Reconnecting { attempt, delay }) => {
+            assert_eq!(*attempt, 1);
+            assert!(*delay >= Duration::from_micros(2500) && *delay <= Duration::from_millis(5));
+        }
+        other => panic!("expected Reconnecting, got {:?}", other),
+    }
+    assert!(matches!(
+        items[3],
+        StreamEvent::State(ConnectionState::Connected)
+    ));
+    assert!(matches!(&items[4], StreamEvent::Event(e) if e.data["number"] == 2));
+    first.assert_async().await;
+    second.assert_async().await;
+}
+
+#[tokio::test]
+async fn reconnecting_stream_gives_up_on_fatal_error() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/v1/wallets/wal_1/events")
+        .with_status(401)
+        .with_body("bad key")
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let items: Vec<_> = client
+        .wallet("wal_1")
+        .unwrap()
+        .events()
+        .stream_reconnecting(fast_reconnect())
+        .collect()
+        .await;
+
+    assert_eq!(items.len(), 2);
+    assert!(matches!(
+        items[0],
+        Ok(StreamEvent::State(ConnectionState::GaveUp))
+    ));
+    assert!(matches!(items[1], Err(LnBotError::Unauthorized { .. })));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn reconnecting_stream_gives_up_after_max_retries() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/v1/wallets/wal_1/events")
+        .with_status(503)
+        .expect(3)
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let items: Vec<_> = client
+        .wallet("wal_1")
+        .unwrap()
+        .events()
+        .stream_reconnecting(fast_reconnect().max_retries(2))
+        .collect()
+        .await;
+
+    let states: Vec<_> = items
+        .iter()
+        .filter_map(|i| match i {
+            Ok(StreamEvent::State(s)) => Some(s.clone()),
+            _ => None,
+        })
+        .collect();
+    assert!(matches!(
+        states[..],
+        [
+            ConnectionState::Reconnecting { attempt: 1, .. },
+            ConnectionState::Reconnecting { attempt: 2, .. },
+            ConnectionState::GaveUp,
+        ]
+    ));
+    assert!(matches!(
+        items.last(),
+        Some(Err(LnBotError::Api { status: 503, .. }))
+    ));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn reconnecting_stream_reports_bad_payload_without_disconnecting() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/events")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(format!("data: not json\n\ndata: {}\n\n", wallet_event(1)))
+        .create_async()
+        .await;
+
+    let client = LnBot::new("key_test").with_base_url(server.url());
+    let items: Vec<_> = client
+        .wallet("wal_1")
+        .unwrap()
+        .events()
+        .stream_reconnecting(fast_reconnect())
+        .take(3)
+        .collect()
+        .await;
+
+    assert!(matches!(items[1], Err(LnBotError::Json(_))));
+    assert!(matches!(&items[2], Ok(StreamEvent::Event(e)) if e.data["number"] == 1));
+}
+
+#[test]
+fn reconnect_delay_backs_off_from_server_hint() {
+    let policy = ReconnectPolicy::default()
+        .base_delay(Duration::from_secs(1))
+        .max_delay(Duration::from_secs(10))
+        .jitter(Jitter::None);
+    assert_eq!(policy.delay_for(1, None), Duration::from_secs(1));
+    assert_eq!(policy.delay_for(3, None), Duration::from_secs(4));
+    assert_eq!(policy.delay_for(10, None), Duration::from_secs(10));
+    assert_eq!(
+        policy.delay_for(2, Some(Duration::from_millis(300))),
+        Duration::from_millis(600)
+    );
+}
+
+#[test]
+fn decoder_reset_keeps_last_event_id_and_retry() {
+    let mut d = SseDecoder::new();
+    d.feed(b"id: 9\nretry: 100\ndata: x\n\ndata: partial");
+    d.reset();
+    assert_eq!(d.last_event_id(), Some("9"));
+    assert_eq!(d.retry(), Some(Duration::from_millis(100)));
+    let events = d.feed(b"\ndata: y\n\n");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].data, "y");
+}
+
+// ---------------------------------------------------------------------------
+// SSE decoder
+// ---------------------------------------------------------------------------
+
+#[test]
+fn decoder_dispatches_on_blank_line() {
+    let mut d = SseDecoder::new();
+    assert!(d.feed(b"event: settled\ndata: hello\n").is_empty());
+    let events = d.feed(b"\n");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event, "settled");
+    assert_eq!(events[0].data, "hello");
+    assert_eq!(events[0].id, None);
+    assert_eq!(events[0].retry, None);
+}
+
+#[test]
+fn decoder_defaults_event_type_to_message() {
+    let events = SseDecoder::new().feed(b"data: x\n\n");
+    assert_eq!(events[0].event, "message");
+}
+
+#[test]
+fn decoder_joins_multiline_data() {
+    let events = SseDecoder::new().feed(b"data: a\ndata:b\ndata:  c\n\n");
+    assert_eq!(events[0].data, "a\nb\n c");
+}
+
+#[test]
+fn decoder_handles_all_line_endings() {
+    let mut d = SseDecoder::new();
+    let events = d.feed(b"data: crlf\r\n\r\ndata: cr\r\rdata: lf\n\n");
+    let data: Vec<_> = events.iter().map(|e| e.data.as_str()).collect();
+    assert_eq!(data, ["crlf", "cr", "lf"]);
+}
+
+#[test]
+fn decoder_handles_crlf_split_across_chunks() {
+    let mut d = SseDecoder::new();
+    assert!(d.feed(b"data: x\r").is_empty());
+    assert!(d.feed(b"\n").is_empty());
+    let events = d.feed(b"\r\n");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].data, "x");
+}
+
+#[test]
+fn decoder_handles_utf8_split_across_chunks() {
+    let body = "data: ⚡ 1000 sats — ok\n\n".as_bytes();
+    let mut d = SseDecoder::new();
+    let mut events = Vec::new();
+    for byte in body {
+        events.extend(d.feed(std::slice::from_ref(byte)));
+    }
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].data, "⚡ 1000 sats — ok");
+}
+
+#[test]
+fn decoder_tracks_id_and_retry() {
+    let mut d = SseDecoder::new();
+    let events = d.feed(b"id: 1\nretry: 2500\ndata: a\n\ndata: b\n\nid\ndata: c\n\n");
+    assert_eq!(events[0].id.as_deref(), Some("1"));
+    assert_eq!(events[0].retry, Some(Duration::from_millis(2500)));
+    // The last event ID persists until the server changes it.
+    assert_eq!(events[1].id.as_deref(), Some("1"));
+    // An empty `id` field resets it.
+    assert_eq!(events[2].id, None);
+    assert_eq!(d.retry(), Some(Duration::from_millis(2500)));
+}
+
+#[test]
+fn decoder_ignores_invalid_id_and_retry() {
+    let mut d = SseDecoder::new();
+    d.feed(b"id: 7\nid: a\0b\nretry: 10s\nretry: -5\ndata: x\n\n");
+    assert_eq!(d.last_event_id(), Some("7"));
+    assert_eq!(d.retry(), None);
+}
+
+#[test]
+fn decoder_ignores_comments_and_unknown_fields() {
+    let events = SseDecoder::new().feed(b": ping\nfoo: bar\ndata: x\n\n");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].data, "x");
+}
+
+#[test]
+fn decoder_skips_events_without_data() {
+    let mut d = SseDecoder::new();
+    assert!(d.feed(b"event: ping\n\n").is_empty());
+    // The event type does not leak into the next event.
+    assert_eq!(d.feed(b"data: x\n\n")[0].event, "message");
+}
+
+#[test]
+fn decoder_dispatches_empty_data_field() {
+    let events = SseDecoder::new().feed(b"data\n\n");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].data, "");
+}
+
+#[test]
+fn decoder_strips_leading_bom() {
+    let events = SseDecoder::new().feed(b"\xEF\xBB\xBFdata: x\n\n");
+    assert_eq!(events[0].data, "x");
+}
+
+#[tokio::test]
+async fn decode_stream_discards_incomplete_event() {
+    let chunks: Vec<Result<bytes::Bytes, ()>> =
+        vec![Ok("data: one\n".into()), Ok("\ndata: two\n".into())];
+    let events: Vec<_> = sse::decode(futures_util::stream::iter(chunks))
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].as_ref().unwrap().data, "one");
+}
+
+#[tokio::test]
+async fn decode_stream_passes_through_errors() {
+    let chunks: Vec<Result<bytes::Bytes, &str>> = vec![
+        Ok("data: one\n\n".into()),
+        Err("boom"),
+        Ok("data: two\n\n".into()),
+    ];
+    let events: Vec<_> = sse::decode(futures_util::stream::iter(chunks))
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1], Err("boom"));
+}
+
+// ---------------------------------------------------------------------------
+// Idle timeout
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn events_stream_idle_timeout() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/wallets/wal_1/events")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_chunked_body(|w| {
+            w.write_all(b": keepalive\n\n")?;
+            w.flush()?;
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(())
+        })
+        .create_async()
+        .await;
+
+    let client = LnBot::builder()
+        .api_key("key_test")
+        .base_url(server.url())
+        .sse_idle_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let results: Vec<_> = client
+        .wallet("wal_1")
+        .unwrap()
+        .events()
+        .stream()
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(results.len(), 1);
+    assert!(matches!(
+        results[0],
+        Err(LnBotError::SseIdleTimeout(d)) if d == Duration::from_millis(100)
+    ));
+}
@@ -0,0 +1,146 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lnbot::*;
+
+const JAN_1_2024: u64 = 1_704_067_200;
+
+fn at(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+fn invoice(expires_at: &str) -> InvoiceResponse {
+    serde_json::from_str(&format!(
+        r#"{{"number":1,"status":"pending","amount":100,"bolt11":"lnbc1...","reference":null,"memo":null,"preimage":null,"txNumber":null,"createdAt":"2024-01-01T00:00:00Z","settledAt":null,"expiresAt":{}}}"#,
+        expires_at
+    ))
+    .unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// Timestamp
+// ---------------------------------------------------------------------------
+
+#[test]
+fn parses_rfc3339_with_offsets_and_fractions() {
+    let t: Timestamp = "2024-01-01T00:00:00Z".parse().unwrap();
+    assert_eq!(t.to_system_time(), at(JAN_1_2024));
+
+    let t: Timestamp = "2023-12-31T19:00:00.250-05:00".parse().unwrap();
+    assert_eq!(
+        t.to_system_time(),
+        at(JAN_1_2024) + Duration::from_millis(250)
+    );
+    assert_eq!(t.as_str(), "2023-12-31T19:00:00.250-05:00");
+    assert_eq!(t.to_string(), "2023-12-31T19:00:00.250-05:00");
+}
+
+#[test]
+fn rejects_invalid_timestamps() {
+    assert!("2024-01-01".parse::<Timestamp>().is_err());
+    assert!("2024-02-30T00:00:00Z".parse::<Timestamp>().is_err());
+    let err = serde_json::from_str::<Timestamp>(r#""yesterday""#).unwrap_err();
+    assert!(err.to_string().contains("invalid RFC 3339 timestamp"));
+}
+
+#[test]
+fn rejects_multibyte_characters() {
+    for s in [
+        "2024-01-01T12:00:0é00Z",
+        "2024-01-01T12:00:00+0é:0",
+        "2024-01-01T12:00:00.5é",
+    ] {
+        assert!(s.parse::<Timestamp>().is_err(), "{}", s);
+    }
+}
+
+#[test]
+fn malformed_field_reads_as_none() {
+    let inv = invoice(r#""2024-01-01T12:00:0é00Z""#);
+    assert!(inv.expires_at.is_none());
+    assert_eq!(inv.created_at.map(SystemTime::from), Some(at(JAN_1_2024)));
+
+    let event: WalletEvent =
+        serde_json::from_str(r#"{"event":"x","createdAt":"yesterday","data":null}"#).unwrap();
+    assert!(event.created_at.is_none());
+}
+
+#[test]
+fn compares_by_instant() {
+    let utc: Timestamp = "2024-01-01T00:00:00Z".parse().unwrap();
+    let offset: Timestamp = "2024-01-01T01:00:00+01:00".parse().unwrap();
+    let later: Timestamp = "2024-01-01T00:00:01Z".parse().unwrap();
+    assert_eq!(utc, offset);
+    assert!(later > offset);
+}
+
+#[test]
+fn round_trips_through_serde() {
+    let t: Timestamp = serde_json::from_str(r#""2024-01-01T01:00:00+01:00""#).unwrap();
+    assert_eq!(
+        serde_json::to_string(&t).unwrap(),
+        r#""2024-01-01T01:00:00+01:00""#
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Expiry helpers
+// ---------------------------------------------------------------------------
+
+#[test]
+fn invoice_is_expired() {
+    let inv = invoice(r#""2024-01-01T01:00:00Z""#);
+    assert!(!inv.is_expired(at(JAN_1_2024)));
+    assert!(inv.is_expired(at(JAN_1_2024 + 3_600)));
+    assert_eq!(inv.time_to_expiry(), Some(Duration::ZERO));
+
+    assert!(!invoice("null").is_expired(at(u32::MAX.into())));
+    assert_eq!(invoice("null").time_to_expiry(), None);
+}
+
+#[test]
+fn invoice_time_to_expiry_counts_down() {
+    let inv = invoice(r#""2999-01-01T00:00:00Z""#);
+    let expires = SystemTime::from(inv.clone().expires_at.unwrap());
+    let max = expires.duration_since(SystemTime::now()).unwrap();
+    let left = inv.time_to_expiry().unwrap();
+    assert!(left <= max && max - left < Duration::from_secs(5));
+}
+
+// ---------------------------------------------------------------------------
+// chrono / time
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "chrono")]
+#[test]
+fn converts_to_chrono() {
+    let t: Timestamp = "2024-01-01T01:00:00+01:00".parse().unwrap();
+    let dt: chrono::DateTime<chrono::Utc> = t.clone().into();
+    assert_eq!(dt.timestamp(), JAN_1_2024 as i64);
+    assert_eq!(t.to_chrono(), dt);
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn fields_are_chrono_datetimes() {
+    let expires_at: Option<chrono::DateTime<chrono::Utc>> =
+        invoice(r#""2024-01-01T01:00:00+01:00""#).expires_at;
+    assert_eq!(expires_at.unwrap().timestamp(), JAN_1_2024 as i64);
+}
+
+#[cfg(all(feature = "time", not(feature = "chrono")))]
+#[test]
+fn fields_are_offset_date_times() {
+    let expires_at: Option<time::OffsetDateTime> =
+        invoice(r#""2024-01-01T01:00:00+01:00""#).expires_at;
+    assert_eq!(expires_at.unwrap().unix_timestamp(), JAN_1_2024 as i64);
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn converts_to_time() {
+    let t: Timestamp = "2024-01-01T00:00:00.5Z".parse().unwrap();
+    let dt: time::OffsetDateTime = t.clone().into();
+    assert_eq!(dt.unix_timestamp(), JAN_1_2024 as i64);
+    assert_eq!(dt.millisecond(), 500);
+    assert_eq!(t.to_offset_date_time(), dt);
+}
@@ -0,0 +1,287 @@
+#![cfg(feature = "tower")]
+
+use std::convert::Infallible;
+
+use http::{Request, Response, StatusCode};
+use lnbot::l402::{L402Layer, L402Price, L402Verified};
+use lnbot::LnBot;
+use mockito::Matcher;
+use tower::{service_fn, Layer, ServiceExt};
+
+const WWW_AUTHENTICATE: &str = r#"L402 macaroon="AgEEbHNhdA", invoice="lnbc100n1...""#;
+const HASH: &str = "0001020304050607080900010203040506070809000102030405060708090102";
+
+async fn handler(req: Request<String>) -> Result<Response<String>, Infallible> {
+    let body = match req.extensions().get::<L402Verified>() {
+        Some(v) => format!("paid {}", v.payment_hash.unwrap()),
+        None => "free".to_string(),
+    };
+    Ok(Response::new(body))
+}
+
+fn get(path: &str, authorization: Option<&str>) -> Request<String> {
+    let mut req = Request::get(path);
+    if let Some(authorization) = authorization {
+        req = req.header("authorization", authorization);
+    }
+    req.body(String::new()).unwrap()
+}
+
+fn layer(server: &mockito::Server) -> L402Layer {
+    let w = LnBot::new("uk_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap();
+    L402Layer::new(w, L402Price::new(10).description("API access"))
+        .route("/premium", L402Price::new(100).caveat("tier=premium"))
+        .free("/health")
+}
+
+async fn mock_challenge(server: &mut mockito::Server, body: serde_json::Value) -> mockito::Mock {
+    server
+        .mock("POST", "/v1/wallets/wal_1/l402/challenges")
+        .match_body(Matcher::Json(body))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(
+            r#"{{"macaroon":"AgEEbHNhdA","invoice":"lnbc100n1...","paymentHash":"{}","expiresAt":"2024-01-01T01:00:00Z","wwwAuthenticate":{}}}"#,
+            HASH,
+            serde_json::to_string(WWW_AUTHENTICATE).unwrap()
+        ))
+        .create_async()
+        .await
+}
+
+async fn mock_verify(server: &mut mockito::Server, caveats: &str) -> mockito::Mock {
+    server
+        .mock("POST", "/v1/wallets/wal_1/l402/verify")
+        .match_body(Matcher::Json(
+            serde_json::json!({"authorization": "L402 AgEEbHNhdA:00ff"}),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(
+            r#"{{"valid":true,"paymentHash":"{}","caveats":{},"error":null}}"#,
+            HASH, caveats
+        ))
+        .create_async()
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Challenges
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn unauthenticated_request_gets_challenge() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_challenge(
+        &mut server,
+        serde_json::json!({"amount": 10, "description": "API access"}),
+    )
+    .await;
+
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc.oneshot(get("/data", None)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(resp.headers()["www-authenticate"], WWW_AUTHENTICATE);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn route_price_and_caveats_are_used() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_challenge(
+        &mut server,
+        serde_json::json!({"amount": 100, "caveats": ["tier=premium"]}),
+    )
+    .await;
+
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc.oneshot(get("/premium/data", None)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::PAYMENT_REQUIRED);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn free_routes_pass_through() {
+    let server = mockito::Server::new_async().await;
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc.oneshot(get("/health", None)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.body(), "free");
+}
+
+#[tokio::test]
+async fn routes_match_whole_path_segments() {
+    let mut server = mockito::Server::new_async().await;
+    let default = mock_challenge(
+        &mut server,
+        serde_json::json!({"amount": 10, "description": "API access"}),
+    )
+    .await
+    .expect(3);
+    let premium = mock_challenge(
+        &mut server,
+        serde_json::json!({"amount": 100, "caveats": ["tier=premium"]}),
+    )
+    .await
+    .expect(2);
+
+    let svc = layer(&server).layer(service_fn(handler));
+    for path in ["/healthz", "/health-admin/users", "/premiumfoo"] {
+        let resp = svc.clone().oneshot(get(path, None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYMENT_REQUIRED, "{}", path);
+    }
+    for path in ["/premium", "/premium/"] {
+        let resp = svc.clone().oneshot(get(path, None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYMENT_REQUIRED, "{}", path);
+    }
+    for path in ["/health", "/health/live"] {
+        let resp = svc.clone().oneshot(get(path, None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK, "{}", path);
+    }
+    default.assert_async().await;
+    premium.assert_async().await;
+}
+
+#[tokio::test]
+async fn root_route_covers_every_path() {
+    let server = mockito::Server::new_async().await;
+    let w = LnBot::new("uk_test")
+        .with_base_url(server.url())
+        .wallet("wal_1")
+        .unwrap();
+    let svc = L402Layer::new(w, L402Price::new(10))
+        .free("/")
+        .layer(service_fn(handler));
+    let resp = svc.oneshot(get("/anything", None)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn api_failure_is_service_unavailable() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/v1/wallets/wal_1/l402/challenges")
+        .with_status(500)
+        .create_async()
+        .await;
+
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc.oneshot(get("/data", None)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+// ---------------------------------------------------------------------------
+// Verification
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn valid_token_reaches_handler_with_extension() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_verify(&mut server, "[]").await;
+
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc
+        .oneshot(get("/data", Some("L402 AgEEbHNhdA:00ff")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.body(), &format!("paid {}", HASH));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn token_without_route_caveats_is_challenged() {
+    let mut server = mockito::Server::new_async().await;
+    mock_verify(&mut server, "[]").await;
+    let challenge = mock_challenge(
+        &mut server,
+        serde_json::json!({"amount": 100, "caveats": ["tier=premium"]}),
+    )
+    .await;
+
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc
+        .oneshot(get("/premium/data", Some("L402 AgEEbHNhdA:00ff")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::PAYMENT_REQUIRED);
+    challenge.assert_async().await;
+
+    let mut server = mockito::Server::new_async().await;
+    mock_verify(&mut server, r#"["tier=premium"]"#).await;
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc
+        .oneshot(get("/premium/data", Some("L402 AgEEbHNhdA:00ff")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn invalid_token_is_challenged() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/v1/wallets/wal_1/l402/verify")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"valid":false,"paymentHash":null,"caveats":null,"error":"bad preimage"}"#)
+        .create_async()
+        .await;
+    let challenge = mock_challenge(
+        &mut server,
+        serde_json::json!({"amount": 10, "description": "API access"}),
+    )
+    .await;
+
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc
+        .oneshot(get("/data", Some("L402 AgEEbHNhdA:00ff")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::PAYMENT_REQUIRED);
+    challenge.assert_async().await;
+}
+
+#[tokio::test]
+async fn rejected_token_is_challenged() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/v1/wallets/wal_1/l402/verify")
+        .with_status(400)
+        .with_body("malformed token")
+        .create_async()
+        .await;
+    let challenge = mock_challenge(
+        &mut server,
+        serde_json::json!({"amount": 10, "description": "API access"}),
+    )
+    .await;
+
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc
+        .oneshot(get("/data", Some("L402 AgEEbHNhdA:00ff")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::PAYMENT_REQUIRED);
+    challenge.assert_async().await;
+}
+
+#[tokio::test]
+async fn verify_server_error_is_service_unavailable() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/v1/wallets/wal_1/l402/verify")
+        .with_status(502)
+        .create_async()
+        .await;
+
+    let svc = layer(&server).layer(service_fn(handler));
+    let resp = svc
+        .oneshot(get("/data", Some("L402 AgEEbHNhdA:00ff")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
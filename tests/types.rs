@@ -1,5 +1,11 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use lnbot::*;
 
+fn jan_1_2024() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1_704_067_200)
+}
+
 // ---------------------------------------------------------------------------
 // Request builders
 // ---------------------------------------------------------------------------
@@ -30,8 +36,8 @@ fn create_payment_request_builder() {
         .idempotency_key("idem-1")
         .reference("pay-ref");
     assert_eq!(req.target, "lnbc1...");
-    assert_eq!(req.amount, Some(50));
-    assert_eq!(req.max_fee, Some(10));
+    assert_eq!(req.amount, Some(Sats::new(50)));
+    assert_eq!(req.max_fee, Some(Sats::new(10)));
     assert_eq!(req.idempotency_key.as_deref(), Some("idem-1"));
     assert_eq!(req.reference.as_deref(), Some("pay-ref"));
 }
@@ -60,7 +66,7 @@ fn create_address_request_default() {
 #[test]
 fn transfer_address_request_new() {
     let req = TransferAddressRequest::new("key_target");
-    assert_eq!(req.target_wallet_key, "key_target");
+    assert_eq!(req.target_wallet_key.expose_secret(), "key_target");
 }
 
 #[test]
@@ -72,15 +78,16 @@ fn create_webhook_request_new() {
 #[test]
 fn recovery_restore_request_new() {
     let req = RecoveryRestoreRequest::new("word1 word2 word3");
-    assert_eq!(req.passphrase, "word1 word2 word3");
+    assert_eq!(req.passphrase.expose_secret(), "word1 word2 word3");
 }
 
 #[test]
 fn create_invoice_for_wallet_request_builder() {
-    let req = CreateInvoiceForWalletRequest::new("wid", 200)
+    let wallet_id: WalletId = "wal_1".parse().unwrap();
+    let req = CreateInvoiceForWalletRequest::new(&wallet_id, 200)
         .reference("ref")
         .comment("hello");
-    assert_eq!(req.wallet_id, "wid");
+    assert_eq!(req.wallet_id, wallet_id);
     assert_eq!(req.amount, 200);
     assert_eq!(req.reference.as_deref(), Some("ref"));
     assert_eq!(req.comment.as_deref(), Some("hello"));
@@ -146,7 +153,7 @@ fn transfer_address_serializes_camel_case() {
 fn pay_l402_serializes_camel_case() {
     let req = PayL402Request {
         www_authenticate: "L402 mac:inv".into(),
-        max_fee: Some(10),
+        max_fee: Some(Sats::new(10)),
         reference: None,
         wait: Some(true),
         timeout: Some(60),
@@ -196,8 +203,8 @@ fn register_response_deserializes() {
     let json = r#"{"userId":"u1","primaryKey":"uk_1","secondaryKey":"uk_2","recoveryPassphrase":"words"}"#;
     let r: RegisterResponse = serde_json::from_str(json).unwrap();
     assert_eq!(r.user_id, "u1");
-    assert_eq!(r.primary_key, "uk_1");
-    assert_eq!(r.recovery_passphrase, "words");
+    assert_eq!(r.primary_key.expose_secret(), "uk_1");
+    assert_eq!(r.recovery_passphrase.expose_secret(), "words");
 }
 
 #[test]
@@ -221,7 +228,7 @@ fn me_response_minimal() {
 fn wallet_key_response_deserializes() {
     let json = r#"{"key":"wk_abc"}"#;
     let r: WalletKeyResponse = serde_json::from_str(json).unwrap();
-    assert_eq!(r.key, "wk_abc");
+    assert_eq!(r.key.expose_secret(), "wk_abc");
 }
 
 #[test]
@@ -229,7 +236,7 @@ fn wallet_key_info_response_deserializes() {
     let json = r#"{"hint":"wk_abc...xyz","createdAt":"2024-01-01T00:00:00Z","lastUsedAt":null}"#;
     let r: WalletKeyInfoResponse = serde_json::from_str(json).unwrap();
     assert_eq!(r.hint, "wk_abc...xyz");
-    assert_eq!(r.created_at.as_deref(), Some("2024-01-01T00:00:00Z"));
+    assert_eq!(r.created_at.map(SystemTime::from), Some(jan_1_2024()));
     assert!(r.last_used_at.is_none());
 }
 
@@ -266,10 +273,10 @@ fn payment_response_deserializes() {
     let p: PaymentResponse = serde_json::from_str(json).unwrap();
     assert_eq!(p.number, 1);
     assert_eq!(p.status, PaymentStatus::Settled);
-    assert_eq!(p.actual_fee, Some(2));
+    assert_eq!(p.actual_fee, Some(Sats::new(2)));
     assert_eq!(p.address, "user@ln.bot");
     assert_eq!(p.preimage.as_deref(), Some("abc"));
-    assert_eq!(p.tx_number, Some(5));
+    assert_eq!(p.tx_number, Some(TxNumber::new(5)));
 }
 
 #[test]
@@ -372,6 +379,187 @@ fn payment_event_type_from_str() {
     );
 }
 
+// ---------------------------------------------------------------------------
+// Invoice references and terminal states
+// ---------------------------------------------------------------------------
+
+#[test]
+fn invoice_ref_conversions() {
+    assert_eq!(
+        InvoiceRef::from(7),
+        InvoiceRef::Number(InvoiceNumber::new(7))
+    );
+    assert_eq!(InvoiceRef::from("abc"), InvoiceRef::Hash("abc".into()));
+    assert_eq!(InvoiceRef::from(7).to_string(), "7");
+    let hash = PaymentHash::from_bytes([0xab; 32]);
+    assert_eq!(InvoiceRef::from(hash).to_string(), "ab".repeat(32));
+}
+
+#[test]
+fn terminal_statuses() {
+    assert!(InvoiceStatus::Settled.is_terminal());
+    assert!(InvoiceStatus::Expired.is_terminal());
+    assert!(!InvoiceStatus::Pending.is_terminal());
+    assert!(PaymentStatus::Settled.is_terminal());
+    assert!(PaymentStatus::Failed.is_terminal());
+    assert!(!PaymentStatus::Processing.is_terminal());
+}
+
+// ---------------------------------------------------------------------------
+// Typed wallet events
+// ---------------------------------------------------------------------------
+
+fn wallet_event(json: &str) -> WalletEvent {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn wallet_event_kind_invoice_settled() {
+    let e = wallet_event(
+        r#"{"event":"invoice.settled","createdAt":"2024-01-01T00:00:00Z","data":{"number":7,"status":"settled","amount":100,"bolt11":"lnbc1...","reference":null,"memo":null,"preimage":"abc","txNumber":3,"createdAt":null,"settledAt":null,"expiresAt":null}}"#,
+    );
+    match e.kind().unwrap() {
+        WalletEventKind::InvoiceSettled(invoice) => {
+            assert_eq!(invoice.number, 7);
+            assert_eq!(invoice.status, InvoiceStatus::Settled);
+        }
+        other => panic!("expected InvoiceSettled, got {:?}", other),
+    }
+}
+
+#[test]
+fn wallet_event_kind_payment_failed() {
+    let e = wallet_event(
+        r#"{"event":"payment.failed","createdAt":"2024-01-01T00:00:00Z","data":{"number":2,"status":"failed","amount":50,"maxFee":10,"serviceFee":0,"actualFee":null,"address":"user@ln.bot","reference":null,"preimage":null,"txNumber":null,"failureReason":"no route","createdAt":null,"settledAt":null}}"#,
+    );
+    let typed = e.into_typed().unwrap();
+    assert_eq!(typed.created_at.map(SystemTime::from), Some(jan_1_2024()));
+    assert_eq!(typed.kind.name(), "payment.failed");
+    match typed.kind {
+        WalletEventKind::PaymentFailed(p) => {
+            assert_eq!(p.failure_reason.as_deref(), Some("no route"))
+        }
+        other => panic!("expected PaymentFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn wallet_event_kind_address_created() {
+    let e = wallet_event(
+        r#"{"event":"address.created","createdAt":"2024-01-01T00:00:00Z","data":{"address":"bot@ln.bot","generated":false,"cost":1000,"createdAt":null}}"#,
+    );
+    assert!(matches!(
+        e.kind().unwrap(),
+        WalletEventKind::AddressCreated(a) if a.address == "bot@ln.bot"
+    ));
+}
+
+#[test]
+fn wallet_event_kind_unknown_keeps_raw_data() {
+    let e = wallet_event(
+        r#"{"event":"wallet.renamed","createdAt":"2024-01-01T00:00:00Z","data":{"name":"x"}}"#,
+    );
+    let kind = TypedWalletEvent::try_from(e).unwrap().kind;
+    assert_eq!(kind.name(), "wallet.renamed");
+    match kind {
+        WalletEventKind::Unknown { event, data } => {
+            assert_eq!(event, "wallet.renamed");
+            assert_eq!(data["name"], "x");
+        }
+        other => panic!("expected Unknown, got {:?}", other),
+    }
+}
+
+#[test]
+fn wallet_event_kind_rejects_malformed_payload() {
+    let e = wallet_event(
+        r#"{"event":"invoice.settled","createdAt":"2024-01-01T00:00:00Z","data":{"number":"seven"}}"#,
+    );
+    assert!(e.kind().is_err());
+}
+
+// ---------------------------------------------------------------------------
+// Payment hashes and preimages
+// ---------------------------------------------------------------------------
+
+// SHA-256 of 32 zero bytes.
+const ZERO_PREIMAGE: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+const ZERO_PREIMAGE_HASH: &str = "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925";
+
+#[test]
+fn preimage_verifies_payment_hash() {
+    let preimage: Preimage = ZERO_PREIMAGE.parse().unwrap();
+    let hash: PaymentHash = ZERO_PREIMAGE_HASH.parse().unwrap();
+    assert_eq!(preimage.payment_hash(), hash);
+    assert!(preimage.verify(&hash));
+    assert!(!Preimage::from_bytes([1; 32]).verify(&hash));
+}
+
+#[test]
+fn payment_hash_parses_and_formats_hex() {
+    let hash: PaymentHash = ZERO_PREIMAGE_HASH.to_uppercase().parse().unwrap();
+    assert_eq!(hash.to_string(), ZERO_PREIMAGE_HASH);
+    assert_eq!(hash.as_bytes()[0], 0x66);
+    assert_eq!(
+        format!("{:?}", hash),
+        format!("PaymentHash({})", ZERO_PREIMAGE_HASH)
+    );
+}
+
+#[test]
+fn payment_hash_rejects_bad_input() {
+    assert!(matches!(
+        "zz".parse::<PaymentHash>(),
+        Err(lnbot::preimage::ParseHashError::InvalidHex(_))
+    ));
+    assert_eq!(
+        "abcd".parse::<Preimage>(),
+        Err(lnbot::preimage::ParseHashError::InvalidLength(2))
+    );
+}
+
+#[test]
+fn payment_hash_serde_roundtrip() {
+    let hash: PaymentHash = ZERO_PREIMAGE_HASH.parse().unwrap();
+    let json = serde_json::to_string(&hash).unwrap();
+    assert_eq!(json, format!("\"{}\"", ZERO_PREIMAGE_HASH));
+    let back: PaymentHash = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, hash);
+    assert!(serde_json::from_str::<PaymentHash>("\"00\"").is_err());
+}
+
+#[test]
+fn transaction_verify_preimage() {
+    let json = format!(
+        r#"{{"number":1,"type":"debit","amount":100,"balanceAfter":0,"networkFee":0,"serviceFee":0,"paymentHash":"{}","preimage":"{}","reference":null,"note":null,"createdAt":null}}"#,
+        ZERO_PREIMAGE_HASH, ZERO_PREIMAGE
+    );
+    let mut tx: TransactionResponse = serde_json::from_str(&json).unwrap();
+    assert!(tx.verify_preimage().unwrap());
+
+    tx.preimage = Some("11".repeat(32));
+    assert!(!tx.verify_preimage().unwrap());
+
+    tx.preimage = None;
+    assert!(!tx.verify_preimage().unwrap());
+
+    tx.preimage = Some("not hex".into());
+    assert!(matches!(
+        tx.verify_preimage(),
+        Err(LnBotError::InvalidHash(_))
+    ));
+}
+
+#[test]
+fn l402_pay_verify_preimage() {
+    let json = format!(
+        r#"{{"authorization":null,"paymentHash":"{}","preimage":"{}","amount":100,"fee":null,"paymentNumber":1,"status":"settled"}}"#,
+        ZERO_PREIMAGE_HASH, ZERO_PREIMAGE
+    );
+    let resp: L402PayResponse = serde_json::from_str(&json).unwrap();
+    assert!(resp.verify_preimage().unwrap());
+}
+
 // ---------------------------------------------------------------------------
 // Error display
 // ---------------------------------------------------------------------------
@@ -380,6 +568,7 @@ fn payment_event_type_from_str() {
 fn error_display_bad_request() {
     let e = LnBotError::BadRequest {
         body: "invalid".into(),
+        error: None,
     };
     assert_eq!(e.to_string(), "Bad Request (400): invalid");
 }
@@ -388,6 +577,7 @@ fn error_display_bad_request() {
 fn error_display_unauthorized() {
     let e = LnBotError::Unauthorized {
         body: "no key".into(),
+        error: None,
     };
     assert_eq!(e.to_string(), "Unauthorized (401): no key");
 }
@@ -396,6 +586,7 @@ fn error_display_unauthorized() {
 fn error_display_forbidden() {
     let e = LnBotError::Forbidden {
         body: "denied".into(),
+        error: None,
     };
     assert_eq!(e.to_string(), "Forbidden (403): denied");
 }
@@ -404,6 +595,7 @@ fn error_display_forbidden() {
 fn error_display_not_found() {
     let e = LnBotError::NotFound {
         body: "missing".into(),
+        error: None,
     };
     assert_eq!(e.to_string(), "Not Found (404): missing");
 }
@@ -412,6 +604,7 @@ fn error_display_not_found() {
 fn error_display_conflict() {
     let e = LnBotError::Conflict {
         body: "exists".into(),
+        error: None,
     };
     assert_eq!(e.to_string(), "Conflict (409): exists");
 }
@@ -421,6 +614,16 @@ fn error_display_api() {
     let e = LnBotError::Api {
         status: 503,
         body: "unavailable".into(),
+        error: None,
     };
     assert_eq!(e.to_string(), "API error (HTTP 503): unavailable");
 }
+
+#[test]
+fn error_display_bolt11() {
+    let e = LnBotError::from(Bolt11Error::InvalidChecksum);
+    assert_eq!(
+        e.to_string(),
+        "Invalid BOLT11 invoice: invalid bech32 checksum"
+    );
+}
@@ -0,0 +1,347 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lnbot::webhooks::{self, SignatureFormat, WebhookError, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use lnbot::*;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+
+const SECRET: &str = "whsec_test";
+const BODY: &[u8] = br#"{"event":"invoice.settled","createdAt":"2024-01-01T00:00:00Z","data":{"number":7,"status":"settled","amount":100,"bolt11":"lnbc1...","reference":null,"memo":null,"preimage":null,"txNumber":3,"createdAt":null,"settledAt":null,"expiresAt":null}}"#;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn headers(timestamp: u64, signature: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+    headers.insert(SIGNATURE_HEADER, signature.parse().unwrap());
+    headers
+}
+
+fn signed(secret: &str, body: &[u8]) -> HeaderMap {
+    let ts = now();
+    headers(ts, &webhooks::sign(secret, ts, body))
+}
+
+// ---------------------------------------------------------------------------
+// Signing
+// ---------------------------------------------------------------------------
+
+#[test]
+fn sign_is_hmac_sha256_of_timestamp_and_body() {
+    assert_eq!(
+        webhooks::sign(SECRET, 1_700_000_000, br#"{"a":1}"#),
+        "v1=38877139021993b830af32feea6e18a8da83eb2f6e49ee50bd9e4cf4ca4d3789"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Verification
+// ---------------------------------------------------------------------------
+
+#[test]
+fn verify_returns_typed_event() {
+    let event = webhooks::verify(
+        SECRET,
+        &signed(SECRET, BODY),
+        BODY,
+        webhooks::DEFAULT_TOLERANCE,
+    )
+    .unwrap();
+    assert_eq!(
+        event.created_at.map(SystemTime::from),
+        Some(UNIX_EPOCH + Duration::from_secs(1_704_067_200))
+    );
+    match event.kind {
+        WalletEventKind::InvoiceSettled(invoice) => assert_eq!(invoice.number, 7),
+        other => panic!("expected InvoiceSettled, got {:?}", other),
+    }
+}
+
+#[test]
+fn verify_accepts_any_of_several_secrets() {
+    let headers = signed("whsec_new", BODY);
+    let result = webhooks::verify_with_secrets(
+        &["whsec_old", "whsec_new"],
+        &headers,
+        BODY,
+        webhooks::DEFAULT_TOLERANCE,
+    );
+    assert!(result.is_ok());
+}
+
+#[test]
+fn verify_accepts_any_of_several_signatures() {
+    let ts = now();
+    let header = format!(
+        "{}, {}",
+        webhooks::sign("whsec_old", ts, BODY),
+        webhooks::sign(SECRET, ts, BODY)
+    );
+    let result = webhooks::verify(
+        SECRET,
+        &headers(ts, &header),
+        BODY,
+        webhooks::DEFAULT_TOLERANCE,
+    );
+    assert!(result.is_ok());
+}
+
+#[test]
+fn verify_rejects_wrong_secret() {
+    let result = webhooks::verify(
+        "whsec_other",
+        &signed(SECRET, BODY),
+        BODY,
+        webhooks::DEFAULT_TOLERANCE,
+    );
+    assert!(matches!(result, Err(WebhookError::SignatureMismatch)));
+}
+
+#[test]
+fn verify_rejects_tampered_body() {
+    let headers = signed(SECRET, BODY);
+    let tampered = String::from_utf8_lossy(BODY).replace("\"amount\":100", "\"amount\":999");
+    let result = webhooks::verify(
+        SECRET,
+        &headers,
+        tampered.as_bytes(),
+        webhooks::DEFAULT_TOLERANCE,
+    );
+    assert!(matches!(result, Err(WebhookError::SignatureMismatch)));
+}
+
+#[test]
+fn verify_rejects_replayed_and_future_timestamps() {
+    for ts in [now() - 600, now() + 600] {
+        let headers = headers(ts, &webhooks::sign(SECRET, ts, BODY));
+        let result = webhooks::verify(SECRET, &headers, BODY, Duration::from_secs(300));
+        assert!(
+            matches!(result, Err(WebhookError::OutsideTolerance(skew)) if skew >= Duration::from_secs(599))
+        );
+    }
+}
+
+#[test]
+fn verify_rejects_missing_headers() {
+    let mut headers = signed(SECRET, BODY);
+    headers.remove(SIGNATURE_HEADER);
+    assert!(matches!(
+        webhooks::verify(SECRET, &headers, BODY, webhooks::DEFAULT_TOLERANCE),
+        Err(WebhookError::MissingHeader(SIGNATURE_HEADER))
+    ));
+
+    let mut headers = signed(SECRET, BODY);
+    headers.insert(TIMESTAMP_HEADER, "yesterday".parse().unwrap());
+    assert!(matches!(
+        webhooks::verify(SECRET, &headers, BODY, webhooks::DEFAULT_TOLERANCE),
+        Err(WebhookError::MissingHeader(TIMESTAMP_HEADER))
+    ));
+}
+
+#[test]
+fn verify_rejects_timestamp_beyond_system_time() {
+    let ts = u64::MAX;
+    let headers = headers(ts, &webhooks::sign(SECRET, ts, BODY));
+    let result = webhooks::verify(SECRET, &headers, BODY, webhooks::DEFAULT_TOLERANCE);
+    assert!(matches!(result, Err(WebhookError::OutsideTolerance(_))));
+
+    let receiver = webhooks::WebhookReceiver::new(SECRET);
+    assert!(matches!(
+        receiver.verify(&headers, BODY),
+        Err(WebhookError::OutsideTolerance(_))
+    ));
+}
+
+#[test]
+fn verify_rejects_signed_but_malformed_payload() {
+    let body = b"not json";
+    let result = webhooks::verify(
+        SECRET,
+        &signed(SECRET, body),
+        body,
+        webhooks::DEFAULT_TOLERANCE,
+    );
+    assert!(matches!(result, Err(WebhookError::InvalidPayload(_))));
+}
+
+#[test]
+fn verify_with_custom_format() {
+    let format = SignatureFormat::DEFAULT
+        .timestamp_header("webhook-timestamp")
+        .signature_header("webhook-signature")
+        .scheme("sha256=")
+        .separator(":");
+    let ts = now();
+    let signature = format.sign(SECRET, ts, BODY);
+    assert!(signature.starts_with("sha256="));
+    assert_ne!(signature[7..], webhooks::sign(SECRET, ts, BODY)[3..]);
+
+    let mut headers = HeaderMap::new();
+    headers.insert("webhook-timestamp", ts.to_string().parse().unwrap());
+    headers.insert("webhook-signature", signature.parse().unwrap());
+    assert!(format
+        .verify(&[SECRET], &headers, BODY, webhooks::DEFAULT_TOLERANCE)
+        .is_ok());
+    assert!(matches!(
+        webhooks::verify(SECRET, &headers, BODY, webhooks::DEFAULT_TOLERANCE),
+        Err(WebhookError::MissingHeader(TIMESTAMP_HEADER))
+    ));
+
+    let receiver = webhooks::WebhookReceiver::new(SECRET).format(format);
+    assert!(receiver.verify(&headers, BODY).unwrap().is_some());
+}
+
+#[test]
+fn webhook_error_converts_to_lnbot_error() {
+    let e = LnBotError::from(WebhookError::SignatureMismatch);
+    assert_eq!(e.to_string(), "Invalid webhook: no signature matches");
+}
+
+// ---------------------------------------------------------------------------
+// Receiver
+// ---------------------------------------------------------------------------
+
+fn counting_receiver(secret: &str) -> (webhooks::WebhookReceiver, Arc<AtomicUsize>) {
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+    let receiver = webhooks::WebhookReceiver::new(secret).on_event(move |event| {
+        let counter = counter.clone();
+        async move {
+            assert!(matches!(event.kind, WalletEventKind::InvoiceSettled(_)));
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    (receiver, count)
+}
+
+#[tokio::test]
+async fn receiver_dispatches_once_per_delivery() {
+    let (receiver, count) = counting_receiver(SECRET);
+
+    let status = receiver.handle(&signed(SECRET, BODY), BODY).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // A redelivery is re-signed with a new timestamp but carries the same body.
+    let status = receiver.handle(&signed(SECRET, BODY), BODY).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn receiver_rejects_bad_deliveries() {
+    let (receiver, count) = counting_receiver(SECRET);
+
+    let status = receiver.handle(&signed("whsec_other", BODY), BODY).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let status = receiver.handle(&signed(SECRET, b"{}"), b"{}").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    assert_eq!(count.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn receiver_accepts_rotated_secret() {
+    let (receiver, count) = counting_receiver("whsec_old");
+    let receiver = receiver.secret("whsec_new");
+
+    let status = receiver.handle(&signed("whsec_new", BODY), BODY).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+/// A receiver whose callback panics (or with `hang`, never finishes) the
+/// first time, and counts later calls.
+fn failing_once_receiver(hang: bool) -> (webhooks::WebhookReceiver, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let receiver = webhooks::WebhookReceiver::new(SECRET).on_event(move |_| {
+        let first = counter.fetch_add(1, Ordering::SeqCst) == 0;
+        async move {
+            if first && hang {
+                std::future::pending::<()>().await;
+            }
+            assert!(!first, "handler failed");
+        }
+    });
+    (receiver, calls)
+}
+
+#[tokio::test]
+async fn receiver_forgets_delivery_when_handler_panics() {
+    let (receiver, calls) = failing_once_receiver(false);
+
+    let first = receiver.clone();
+    let result = tokio::spawn(async move { first.handle(&signed(SECRET, BODY), BODY).await }).await;
+    assert!(result.unwrap_err().is_panic());
+
+    let status = receiver.handle(&signed(SECRET, BODY), BODY).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn receiver_forgets_cancelled_delivery() {
+    let (receiver, calls) = failing_once_receiver(true);
+
+    let headers = signed(SECRET, BODY);
+    let handling = receiver.handle(&headers, BODY);
+    assert!(tokio::time::timeout(Duration::from_millis(50), handling)
+        .await
+        .is_err());
+
+    let status = receiver.handle(&signed(SECRET, BODY), BODY).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn receiver_forget_accepts_redelivery() {
+    let receiver = webhooks::WebhookReceiver::new(SECRET);
+    assert!(receiver
+        .verify(&signed(SECRET, BODY), BODY)
+        .unwrap()
+        .is_some());
+    receiver.forget(BODY);
+    assert!(receiver
+        .verify(&signed(SECRET, BODY), BODY)
+        .unwrap()
+        .is_some());
+    assert!(receiver
+        .verify(&signed(SECRET, BODY), BODY)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn receiver_dedup_capacity_evicts_oldest() {
+    let receiver = webhooks::WebhookReceiver::new(SECRET).dedup_capacity(1);
+    let other = String::from_utf8_lossy(BODY).replace("\"number\":7", "\"number\":8");
+    let other = other.as_bytes();
+
+    assert!(receiver
+        .verify(&signed(SECRET, BODY), BODY)
+        .unwrap()
+        .is_some());
+    assert!(receiver
+        .verify(&signed(SECRET, BODY), BODY)
+        .unwrap()
+        .is_none());
+    assert!(receiver
+        .verify(&signed(SECRET, other), other)
+        .unwrap()
+        .is_some());
+    // BODY was evicted by the second delivery.
+    assert!(receiver
+        .verify(&signed(SECRET, BODY), BODY)
+        .unwrap()
+        .is_some());
+}